@@ -1,8 +1,9 @@
-use simple_pdf::{graphics, Page, PDF};
+use simple_pdf::{graphics, Page, PageSize, PDF};
 use std::fs::File;
 
 fn main() -> std::io::Result<()> {
     let mut pdf = PDF::from_file(File::create("simple")?);
+    pdf.set_media_box(PageSize::A4);
     let mut page = Page::new(); // Page builder
     page.add(
         graphics::Path::from((10f64, 10f64))
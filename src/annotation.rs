@@ -0,0 +1,272 @@
+use crate::graphics::{Color, Rect};
+use crate::pdf::{Dict, Name, ObjRef, PDFData, PDFString};
+use crate::PageRef;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// How the border of an annotation is drawn (`/S` of `/BS`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorderType {
+    Solid,
+    /// A dashed line, with the lengths of the dashes and gaps
+    Dashed(Vec<f64>),
+    /// Embossed, as if raised above the page
+    Beveled,
+    /// Engraved, as if pressed into the page
+    Inset,
+    /// Only along the bottom of the annotation
+    Underline,
+}
+impl BorderType {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Solid => "S",
+            Self::Dashed(_) => "D",
+            Self::Beveled => "B",
+            Self::Inset => "I",
+            Self::Underline => "U",
+        }
+    }
+}
+
+/// The border style dictionary of an annotation (`/BS`)
+///
+/// - width: the width of the border in points, 0 for no border
+/// - kind: see BorderType
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorderStyle {
+    width: f64,
+    kind: BorderType,
+}
+
+impl BorderStyle {
+    pub fn new(width: f64, kind: BorderType) -> Self {
+        Self { width, kind }
+    }
+    /// No border, so only the content under the annotation is visible
+    pub fn none() -> Self {
+        Self::new(0f64, BorderType::Solid)
+    }
+}
+
+impl PDFData for BorderStyle {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        let dict = Dict::from_vec(vec![("W", Rc::new(self.width))]);
+        // Solid is the default style
+        if self.kind != BorderType::Solid {
+            dict.add_entry("S", Name::new(self.kind.name()));
+        }
+        if let BorderType::Dashed(dashes) = &self.kind {
+            dict.add_entry(
+                "D",
+                Rc::new(dashes.iter().map(|d| Rc::new(*d)).collect::<Vec<_>>()),
+            );
+        }
+        dict.write(o)
+    }
+}
+
+/// The flags of an annotation (`/F`), combined with `|`, such as
+/// `AnnotationFlags::PRINT | AnnotationFlags::NO_VIEW` for an
+/// annotation that is printed but not shown on screen
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnnotationFlags(u32);
+
+impl AnnotationFlags {
+    /// Don't show the annotation if the viewer doesn't support its
+    /// subtype
+    pub const INVISIBLE: Self = Self(1);
+    /// Don't show or print the annotation, or let the user interact
+    /// with it
+    pub const HIDDEN: Self = Self(1 << 1);
+    /// Print the annotation when the page is printed
+    pub const PRINT: Self = Self(1 << 2);
+    /// Don't scale the appearance when the page is zoomed
+    pub const NO_ZOOM: Self = Self(1 << 3);
+    /// Don't rotate the appearance when the page is rotated
+    pub const NO_ROTATE: Self = Self(1 << 4);
+    /// Don't show the annotation on screen, or let the user interact
+    /// with it
+    pub const NO_VIEW: Self = Self(1 << 5);
+    /// Don't let the user interact with the annotation
+    pub const READ_ONLY: Self = Self(1 << 6);
+    /// Don't let the user move, resize, or delete the annotation
+    pub const LOCKED: Self = Self(1 << 7);
+    /// Invert NO_VIEW when the user interacts with the page
+    pub const TOGGLE_NO_VIEW: Self = Self(1 << 8);
+    /// Don't let the user change the contents of the annotation
+    pub const LOCKED_CONTENTS: Self = Self(1 << 9);
+    /// No flags
+    pub fn empty() -> Self {
+        Self(0)
+    }
+    /// The value written as `/F`
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for AnnotationFlags {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A link annotation, which opens a URI or shows a page when its area
+/// of the page is clicked
+///
+/// Viewers draw a border around links unless one is given, see border.
+#[derive(Debug, Clone)]
+pub struct Link {
+    rect: Rect,
+    target: Target,
+    border: Option<BorderStyle>,
+    color: Option<Option<Color>>,
+    flags: Option<AnnotationFlags>,
+}
+
+impl Link {
+    /// - rect: the area of the link, in default user space
+    /// - uri: the URI opened by the link
+    pub fn uri(rect: impl Into<Rect>, uri: &str) -> Self {
+        Self {
+            rect: rect.into(),
+            target: Target::Uri(uri.to_string()),
+            border: None,
+            color: None,
+            flags: None,
+        }
+    }
+    /// - rect: the area of the link, in default user space
+    /// - page: the page shown by the link, fitted to the window
+    ///
+    /// The page must be added to the same document, or PDF::write
+    /// returns an error.
+    pub fn page(rect: impl Into<Rect>, page: PageRef) -> Self {
+        Self {
+            target: Target::Page(page),
+            ..Self::uri(rect, "")
+        }
+    }
+    /// Sets the border of the link, see BorderStyle
+    pub fn border(mut self, border: BorderStyle) -> Self {
+        self.border = Some(border);
+        self
+    }
+    /// Sets the color of the border (`/C`), None for a transparent
+    /// border
+    ///
+    /// # Panics
+    ///
+    /// panics if the color isn't DeviceGray, DeviceRGB, or DeviceCMYK
+    pub fn color(mut self, color: Option<Color>) -> Self {
+        let valid = matches!(
+            color,
+            None | Some(Color::DeviceGray(..) | Color::DeviceRGB(..) | Color::DeviceCMYK(..))
+        );
+        assert!(valid, "Annotation colors must be gray, RGB, or CMYK");
+        self.color = Some(color);
+        self
+    }
+    /// Sets the flags of the link, see AnnotationFlags
+    pub fn flags(mut self, flags: AnnotationFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+    /// The annotation dictionary of the link
+    ///
+    /// - page: the page the link is on
+    /// - dest: the destination showing a page of the document
+    pub(crate) fn data(
+        &self,
+        page: Rc<dyn PDFData>,
+        dest: impl Fn(PageRef) -> io::Result<Rc<dyn PDFData>>,
+    ) -> io::Result<Rc<ObjRef<Dict>>> {
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("Annot") as Rc<dyn PDFData>),
+            ("Subtype", Name::new("Link")),
+            ("Rect", self.rect.as_data()),
+            ("P", page),
+        ]);
+        match &self.target {
+            Target::Uri(uri) => dict.add_entry(
+                "A",
+                Dict::from_vec(vec![
+                    ("S", Name::new("URI") as Rc<dyn PDFData>),
+                    ("URI", PDFString::new(uri.as_str())),
+                ]),
+            ),
+            Target::Page(r) => dict.add_entry("Dest", dest(*r)?),
+        }
+        dict.add_optional(
+            "BS",
+            self.border.clone().map(|b| Rc::new(b) as Rc<dyn PDFData>),
+        );
+        dict.add_optional(
+            "F",
+            self.flags
+                .map(|f| Rc::new(f.bits() as usize) as Rc<dyn PDFData>),
+        );
+        if let Some(color) = &self.color {
+            let components = match color {
+                None => vec![],
+                Some(Color::DeviceGray(g)) => vec![*g],
+                Some(Color::DeviceRGB(r, g, b)) => vec![*r, *g, *b],
+                Some(Color::DeviceCMYK(c, m, y, k)) => vec![*c, *m, *y, *k],
+                Some(_) => unreachable!(),
+            };
+            dict.add_entry(
+                "C",
+                Rc::new(components.into_iter().map(Rc::new).collect::<Vec<_>>()),
+            );
+        }
+        Ok(ObjRef::new(0, dict))
+    }
+}
+
+/// What a link does when clicked
+#[derive(Debug, Clone)]
+enum Target {
+    Uri(String),
+    Page(PageRef),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn borderless_link() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add_link(
+                Link::uri((10f64, 10f64, 100f64, 20f64), "https://example.com")
+                    .border(BorderStyle::none()),
+            );
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/BS <<\n/W 0\n>>\n"));
+    }
+
+    #[test]
+    fn print_only_flags() {
+        let flags = AnnotationFlags::PRINT | AnnotationFlags::NO_VIEW;
+        assert_eq!(flags.bits(), 36);
+        assert!(flags.contains(AnnotationFlags::PRINT));
+        assert!(!flags.contains(AnnotationFlags::HIDDEN));
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add_link(
+                Link::uri((10f64, 10f64, 100f64, 20f64), "https://example.com").flags(flags),
+            );
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/F 36\n"));
+    }
+}
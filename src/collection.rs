@@ -0,0 +1,131 @@
+use crate::pdf::{Dict, Name, PDFData, PDFString};
+use std::rc::Rc;
+
+/// How a viewer initially presents the files of a collection
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CollectionView {
+    /// A list of the files, with a column for each field
+    Details,
+    /// The files as tiles, with the first few fields
+    Tile,
+    /// Only the initial document, with the file list hidden
+    Hidden,
+}
+impl CollectionView {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Details => "D",
+            Self::Tile => "T",
+            Self::Hidden => "H",
+        }
+    }
+}
+
+/// The data shown in a field of a collection
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FieldType {
+    /// The name of the file
+    FileName,
+    /// The description of the file
+    Description,
+    /// The size of the file
+    Size,
+    /// When the file was last modified
+    ModDate,
+    /// When the file was created
+    CreationDate,
+    /// Text from the collection item of the file
+    Text,
+    /// A date from the collection item of the file
+    Date,
+    /// A number from the collection item of the file
+    Number,
+}
+impl FieldType {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::FileName => "F",
+            Self::Description => "Desc",
+            Self::Size => "Size",
+            Self::ModDate => "ModDate",
+            Self::CreationDate => "CreationDate",
+            Self::Text => "S",
+            Self::Date => "D",
+            Self::Number => "N",
+        }
+    }
+}
+
+/// A portable collection (`/Collection`), so viewers present the files
+/// attached with PDF::attach_file as a browsable package
+#[derive(Debug, Clone)]
+pub struct Collection {
+    view: CollectionView,
+    fields: Vec<(String, String, FieldType)>,
+}
+
+impl Collection {
+    pub fn new(view: CollectionView) -> Self {
+        Self {
+            view,
+            fields: vec![],
+        }
+    }
+    /// Adds a field to the schema of the collection, shown in the order
+    /// the fields are added
+    ///
+    /// - key: the key of the field in the schema
+    /// - name: the name shown to the user
+    /// - kind: see FieldType
+    pub fn field(mut self, key: &str, name: &str, kind: FieldType) -> Self {
+        self.fields.push((key.to_string(), name.to_string(), kind));
+        self
+    }
+    pub(crate) fn data(&self) -> Rc<Dict> {
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("Collection")),
+            ("View", Name::new(self.view.name())),
+        ]);
+        if !self.fields.is_empty() {
+            let schema = Dict::from_vec(vec![("Type", Name::new("CollectionSchema"))]);
+            for (order, (key, name, kind)) in self.fields.iter().enumerate() {
+                schema.add_entry(
+                    key.as_str(),
+                    Dict::from_vec(vec![
+                        ("Type", Name::new("CollectionField") as Rc<dyn PDFData>),
+                        ("Subtype", Name::new(kind.name())),
+                        ("N", PDFString::new(name.as_str())),
+                        ("O", Rc::new(order)),
+                    ]),
+                );
+            }
+            dict.add_entry("Schema", schema);
+        }
+        dict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn collection() {
+        let file = text(&written(|pdf| {
+            pdf.set_collection(Collection::new(CollectionView::Details).field(
+                "name",
+                "Name",
+                FieldType::FileName,
+            ));
+            pdf.attach_file("a.txt", b"first".to_vec());
+            pdf.attach_file("b.txt", b"second".to_vec());
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/Collection <<\n/Type /Collection\n/View /D\n/Schema <<\n"));
+        assert!(file.contains("/name <<\n/Type /CollectionField\n/Subtype /F\n/N (Name)\n/O 0\n>>"));
+        assert!(file.contains("/EmbeddedFiles <<\n/Names [(a.txt) 5 0 R (b.txt) 7 0 R]\n>>"));
+        assert!(file.contains("5 0 obj\n<<\n/Type /Filespec\n/F (a.txt)\n"));
+    }
+}
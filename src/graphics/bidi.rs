@@ -0,0 +1,279 @@
+//! Reordering of bidirectional text
+//!
+//! PDF content streams show glyphs left to right, so right to left
+//! runs (Hebrew, Arabic) have to be reordered into visual order before
+//! they are written. This implements the implicit part of the Unicode
+//! Bidirectional Algorithm (UAX #9) for a single paragraph: explicit
+//! embeddings, overrides and isolates are not supported, and characters
+//! are classified by block rather than with the full Unicode tables.
+//!
+//! Arabic shaping (choosing the initial, medial and final forms) is not
+//! performed, so the font has to handle it, if required.
+
+/// Bidi character classes, named as in UAX #9
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Class {
+    /// Left to right
+    L,
+    /// Right to left
+    R,
+    /// Arabic letter
+    AL,
+    /// European number
+    EN,
+    /// European separator
+    ES,
+    /// European terminator
+    ET,
+    /// Arabic number
+    AN,
+    /// Common separator
+    CS,
+    /// Non-spacing mark
+    NSM,
+    /// Whitespace
+    WS,
+    /// Other neutral
+    ON,
+}
+
+fn classify(c: char) -> Class {
+    match c {
+        '0'..='9' | '\u{06F0}'..='\u{06F9}' => Class::EN,
+        '\u{0660}'..='\u{0669}' | '\u{066B}' | '\u{066C}' => Class::AN,
+        '+' | '-' => Class::ES,
+        '#' | '$' | '%' | '\u{00A2}'..='\u{00A5}' | '\u{00B0}' | '\u{20AC}' => Class::ET,
+        ',' | '.' | '/' | ':' | '\u{00A0}' => Class::CS,
+        '\u{0300}'..='\u{036F}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{05BF}'
+        | '\u{05C1}'
+        | '\u{05C2}'
+        | '\u{05C4}'
+        | '\u{05C5}'
+        | '\u{05C7}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0670}' => Class::NSM,
+        '\u{0590}'..='\u{05FF}'
+        | '\u{07C0}'..='\u{085F}'
+        | '\u{FB1D}'..='\u{FB4F}'
+        | '\u{10800}'..='\u{10FFF}' => Class::R,
+        '\u{0600}'..='\u{07BF}'
+        | '\u{0860}'..='\u{08FF}'
+        | '\u{FB50}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}' => Class::AL,
+        c if c.is_whitespace() => Class::WS,
+        c if c.is_alphanumeric() => Class::L,
+        _ => Class::ON,
+    }
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '\u{00AB}' => '\u{00BB}',
+        '\u{00BB}' => '\u{00AB}',
+        c => c,
+    }
+}
+
+/// Whether the text contains any right to left characters
+pub fn has_rtl(text: &str) -> bool {
+    text.chars()
+        .any(|c| matches!(classify(c), Class::R | Class::AL | Class::AN))
+}
+
+/// Computes the embedding level of each character
+///
+/// The paragraph level is taken from the first strong character
+fn levels(chars: &[char]) -> Vec<u8> {
+    let mut types: Vec<Class> = chars.iter().copied().map(classify).collect();
+    let para = match types
+        .iter()
+        .find(|t| matches!(t, Class::L | Class::R | Class::AL))
+    {
+        Some(Class::L) | None => 0u8,
+        _ => 1u8,
+    };
+    let sos = if para == 0 { Class::L } else { Class::R };
+
+    // W1: marks take the type of the previous character
+    let mut prev = sos;
+    for t in types.iter_mut() {
+        if *t == Class::NSM {
+            *t = prev;
+        }
+        prev = *t;
+    }
+    // W2, W3: numbers after Arabic letters are Arabic numbers
+    let mut strong = sos;
+    for t in types.iter_mut() {
+        match *t {
+            Class::L | Class::R => strong = *t,
+            Class::AL => {
+                strong = Class::AL;
+                *t = Class::R;
+            }
+            Class::EN if strong == Class::AL => *t = Class::AN,
+            _ => (),
+        }
+    }
+    // W4: single separators between numbers
+    for i in 1..types.len().saturating_sub(1) {
+        let (before, after) = (types[i - 1], types[i + 1]);
+        types[i] = match (before, types[i], after) {
+            (Class::EN, Class::ES, Class::EN) | (Class::EN, Class::CS, Class::EN) => Class::EN,
+            (Class::AN, Class::CS, Class::AN) => Class::AN,
+            (_, t, _) => t,
+        };
+    }
+    // W5: terminators next to European numbers
+    let mut i = 0;
+    while i < types.len() {
+        if types[i] == Class::ET {
+            let start = i;
+            while i < types.len() && types[i] == Class::ET {
+                i += 1;
+            }
+            let touches = (start > 0 && types[start - 1] == Class::EN)
+                || (i < types.len() && types[i] == Class::EN);
+            if touches {
+                for t in types[start..i].iter_mut() {
+                    *t = Class::EN;
+                }
+            }
+        } else {
+            i += 1;
+        }
+    }
+    // W6, W7
+    let mut strong = sos;
+    for t in types.iter_mut() {
+        match *t {
+            Class::ES | Class::ET | Class::CS => *t = Class::ON,
+            Class::L | Class::R => strong = *t,
+            Class::EN if strong == Class::L => *t = Class::L,
+            _ => (),
+        }
+    }
+    // N1, N2: neutrals take the direction of the surrounding text if it
+    // agrees, and the paragraph direction otherwise
+    let direction = |t: Class| match t {
+        Class::L => Some(Class::L),
+        Class::R | Class::EN | Class::AN => Some(Class::R),
+        _ => None,
+    };
+    let mut i = 0;
+    while i < types.len() {
+        if direction(types[i]).is_none() {
+            let start = i;
+            while i < types.len() && direction(types[i]).is_none() {
+                i += 1;
+            }
+            let before = if start == 0 {
+                sos
+            } else {
+                direction(types[start - 1]).unwrap()
+            };
+            let after = if i == types.len() {
+                sos
+            } else {
+                direction(types[i]).unwrap()
+            };
+            let resolved = if before == after { before } else { sos };
+            for t in types[start..i].iter_mut() {
+                *t = resolved;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    // I1, I2
+    let mut levels: Vec<u8> = types
+        .iter()
+        .map(|t| match (para % 2, t) {
+            (0, Class::R) => para + 1,
+            (0, Class::AN) | (0, Class::EN) => para + 2,
+            (1, Class::L) | (1, Class::EN) | (1, Class::AN) => para + 1,
+            _ => para,
+        })
+        .collect();
+    // L1: trailing whitespace is reset to the paragraph level
+    for (c, l) in chars.iter().zip(levels.iter_mut()).rev() {
+        if !c.is_whitespace() {
+            break;
+        }
+        *l = para;
+    }
+    levels
+}
+
+/// Reorders the text from logical order into visual (left to right) order
+///
+/// Characters in right to left runs are mirrored where needed, so
+/// `(` in Hebrew text is shown as `)`, which the font then displays
+/// correctly.
+pub fn reorder(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if !has_rtl(text) {
+        return text.to_string();
+    }
+    let levels = levels(&chars);
+    let mut order: Vec<(char, u8)> = chars
+        .into_iter()
+        .zip(levels.iter().copied())
+        .map(|(c, l)| if l % 2 == 1 { (mirror(c), l) } else { (c, l) })
+        .collect();
+    let max = levels.iter().copied().max().unwrap_or(0);
+    let min_odd = levels
+        .iter()
+        .copied()
+        .filter(|l| l % 2 == 1)
+        .min()
+        .unwrap_or(1);
+    // L2: reverse every run at or above each level, from the highest
+    // level down to the lowest odd level
+    let mut level = max;
+    while level >= min_odd && level > 0 {
+        let mut i = 0;
+        while i < order.len() {
+            if order[i].1 >= level {
+                let start = i;
+                while i < order.len() && order[i].1 >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            } else {
+                i += 1;
+            }
+        }
+        level -= 1;
+    }
+    order.into_iter().map(|(c, _)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reorder_mixed() {
+        let logical = "abc \u{5E9}\u{5DC}\u{5D5}\u{5DD} def";
+        let visual = reorder(logical);
+        assert_ne!(visual, logical);
+        assert_eq!(visual, "abc \u{5DD}\u{5D5}\u{5DC}\u{5E9} def");
+    }
+
+    #[test]
+    fn reorder_left_to_right() {
+        assert_eq!(reorder("abc (def)"), "abc (def)");
+    }
+}
@@ -0,0 +1,245 @@
+use super::path::GraphicPath;
+use super::text::GraphicText;
+use super::{Color, Font, Graphic, GraphicContext, GraphicParameters, GraphicsContextType};
+use super::{Path, Point, Rect, Text};
+use std::rc::Rc;
+
+/// Shared layout for the simple charts
+///
+/// The axes are drawn along the left and bottom edges of the rect,
+/// and the values are scaled so the largest fills the height of the rect
+#[derive(Debug)]
+struct ChartLayout {
+    rect: Rect,
+    data: Vec<(String, f64)>,
+    axis_color: Color,
+    labels: Option<(Rc<Font>, f64)>,
+}
+
+impl ChartLayout {
+    fn new(rect: Rect, data: Vec<(String, f64)>) -> Self {
+        Self {
+            rect,
+            data,
+            axis_color: Color::default(),
+            labels: None,
+        }
+    }
+    /// Width of the slot each data point is drawn in
+    fn slot(&self) -> f64 {
+        self.rect.width() / self.data.len().max(1) as f64
+    }
+    /// Height of a value, relative to the bottom of the rect
+    ///
+    /// Negative values are drawn as zero
+    fn scale(&self, value: f64) -> f64 {
+        let max = self.data.iter().map(|(_, v)| *v).fold(0f64, f64::max);
+        if max > 0f64 {
+            self.rect.height() * value.max(0f64) / max
+        } else {
+            0f64
+        }
+    }
+    fn axes(&self) -> Rc<GraphicPath> {
+        let r = self.rect;
+        Path::from((r.x(), r.y() + r.height()))
+            .line_to((r.x(), r.y()))
+            .line_to((r.x() + r.width(), r.y()))
+            .stroke(self.axis_color.clone())
+    }
+    /// Labels are drawn below the x axis, starting at the
+    /// left of each slot
+    fn labels(&self) -> Vec<Rc<GraphicText>> {
+        let (font, size) = match &self.labels {
+            Some(l) => l.clone(),
+            None => return vec![],
+        };
+        let slot = self.slot();
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(i, (label, _))| {
+                Rc::new(
+                    Text::new(font.clone(), size)
+                        .move_to((
+                            self.rect.x() + slot * i as f64 + slot * 0.1,
+                            self.rect.y() - size,
+                        ))
+                        .text(label.clone())
+                        .fill(self.axis_color.clone()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A bar chart, drawn inside a rectangle
+#[derive(Debug)]
+pub struct BarChart {
+    layout: ChartLayout,
+    bar_color: Color,
+}
+
+impl BarChart {
+    /// Creates a bar chart with one bar for each data point
+    ///
+    /// - rect: the area the bars are drawn in, see Rect
+    /// - data: a label and a value for each bar
+    pub fn new(rect: impl Into<Rect>, data: Vec<(impl Into<String>, f64)>) -> Self {
+        Self {
+            layout: ChartLayout::new(
+                rect.into(),
+                data.into_iter().map(|(l, v)| (l.into(), v)).collect(),
+            ),
+            bar_color: Color::default(),
+        }
+    }
+    /// Sets the fill color of the bars
+    pub fn bar_color(mut self, color: Color) -> Self {
+        self.bar_color = color;
+        self
+    }
+    /// Sets the color of the axes and labels
+    pub fn axis_color(mut self, color: Color) -> Self {
+        self.layout.axis_color = color;
+        self
+    }
+    /// Draws the labels below the x axis, with the given font
+    pub fn labels(mut self, font: Rc<Font>, size: f64) -> Self {
+        self.layout.labels = Some((font, size));
+        self
+    }
+    /// Completes the chart
+    pub fn draw(self) -> Rc<GraphicChart> {
+        let slot = self.layout.slot();
+        let r = self.layout.rect;
+        let mut paths: Vec<Rc<GraphicPath>> = self
+            .layout
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, (_, v))| {
+                Path::new()
+                    .rect((
+                        r.x() + slot * i as f64 + slot * 0.1,
+                        r.y(),
+                        slot * 0.8,
+                        self.layout.scale(*v),
+                    ))
+                    .fill(self.bar_color.clone())
+            })
+            .collect();
+        paths.push(self.layout.axes());
+        Rc::new(GraphicChart {
+            params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+            paths,
+            labels: self.layout.labels(),
+        })
+    }
+}
+
+/// A line chart, drawn inside a rectangle
+#[derive(Debug)]
+pub struct LineChart {
+    layout: ChartLayout,
+    line_color: Color,
+}
+
+impl LineChart {
+    /// Creates a line chart, connecting each of the data points
+    ///
+    /// - rect: the area the line is drawn in, see Rect
+    /// - data: a label and a value for each point
+    pub fn new(rect: impl Into<Rect>, data: Vec<(impl Into<String>, f64)>) -> Self {
+        Self {
+            layout: ChartLayout::new(
+                rect.into(),
+                data.into_iter().map(|(l, v)| (l.into(), v)).collect(),
+            ),
+            line_color: Color::default(),
+        }
+    }
+    /// Sets the stroke color of the line
+    pub fn line_color(mut self, color: Color) -> Self {
+        self.line_color = color;
+        self
+    }
+    /// Sets the color of the axes and labels
+    pub fn axis_color(mut self, color: Color) -> Self {
+        self.layout.axis_color = color;
+        self
+    }
+    /// Draws the labels below the x axis, with the given font
+    pub fn labels(mut self, font: Rc<Font>, size: f64) -> Self {
+        self.layout.labels = Some((font, size));
+        self
+    }
+    /// Completes the chart
+    ///
+    /// Each point is placed in the middle of its slot along the x axis
+    pub fn draw(self) -> Rc<GraphicChart> {
+        let slot = self.layout.slot();
+        let r = self.layout.rect;
+        let mut points = self.layout.data.iter().enumerate().map(|(i, (_, v))| {
+            Point::new(
+                r.x() + slot * i as f64 + slot / 2f64,
+                r.y() + self.layout.scale(*v),
+            )
+        });
+        let mut paths = vec![];
+        if let Some(first) = points.next() {
+            paths.push(
+                points
+                    .fold(Path::from(first), |p, pt| p.line_to(pt))
+                    .stroke(self.line_color.clone()),
+            );
+        }
+        paths.push(self.layout.axes());
+        Rc::new(GraphicChart {
+            params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+            paths,
+            labels: self.layout.labels(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicChart {
+    params: GraphicParameters,
+    paths: Vec<Rc<GraphicPath>>,
+    labels: Vec<Rc<GraphicText>>,
+}
+
+impl Graphic for GraphicChart {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        for path in self.paths.iter() {
+            out.render(path.clone());
+        }
+        for label in self.labels.iter() {
+            out.render(label.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+
+    #[test]
+    fn bar_chart() {
+        let content = rendered(
+            BarChart::new(
+                (0f64, 0f64, 300f64, 100f64),
+                vec![("a", 1f64), ("b", 2f64), ("c", 4f64)],
+            )
+            .draw(),
+        );
+        assert_eq!(content.matches(" re f").count(), 3);
+        // The largest value fills the height
+        assert!(content.contains(" 10 0 80 25 re f 110 0 80 50 re f 210 0 80 100 re f"));
+    }
+}
@@ -0,0 +1,234 @@
+use super::Function;
+use crate::pdf::{types::Stream, Dict, Name, ObjRef, Object, PDFData};
+use std::rc::Rc;
+
+/// The number of components of the alternate space of a DeviceN or
+/// Separation color space
+fn alternate_components(alternate: &str) -> usize {
+    match alternate {
+        "DeviceGray" => 1,
+        "DeviceRGB" => 3,
+        "DeviceCMYK" => 4,
+        _ => panic!("The alternate space must be DeviceGray, DeviceRGB, or DeviceCMYK"),
+    }
+}
+
+/// A color space, which has to be added to the resources of the
+/// pages that use it
+///
+/// The same color space can be used on any number of pages, and any
+/// objects it references (like ICC profiles) are only written once.
+#[derive(Debug)]
+pub struct ColorSpace {
+    components: usize,
+    /// The color space array
+    data: Rc<dyn PDFData>,
+    profile: Option<Rc<ObjRef<Stream>>>,
+    /// Indirect objects the color space references
+    objects: Vec<Rc<dyn Object>>,
+}
+
+impl ColorSpace {
+    /// A color space defined by an ICC profile
+    ///
+    /// - profile: the ICC profile data
+    /// - components: the number of color components, 1, 3, or 4
+    ///
+    /// Viewers that don't support the profile fall back to
+    /// DeviceGray, DeviceRGB, or DeviceCMYK.
+    ///
+    /// # Panics
+    ///
+    /// panics if components is not 1, 3, or 4
+    pub fn icc_based(profile: Vec<u8>, components: usize) -> Rc<Self> {
+        let alternate = match components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            4 => "DeviceCMYK",
+            _ => panic!("ICC profiles must have 1, 3, or 4 components"),
+        };
+        let stream = ObjRef::new(
+            0,
+            Stream::new(
+                Dict::from_vec(vec![
+                    ("N", Rc::new(components) as Rc<dyn PDFData>),
+                    ("Alternate", Name::new(alternate)),
+                ]),
+                profile,
+            ),
+        );
+        Rc::new(Self {
+            components,
+            data: Rc::new(vec![
+                Name::new("ICCBased") as Rc<dyn PDFData>,
+                stream.clone(),
+            ]),
+            profile: Some(stream.clone()),
+            objects: vec![stream],
+        })
+    }
+    /// A color space made of named colorants, such as spot colors
+    /// (`/DeviceN`)
+    ///
+    /// - colorants: the names of the colorants, such as `["Cyan", "PANTONE 485 C"]`
+    /// - alternate: the space viewers without the colorants use,
+    ///   `"DeviceGray"`, `"DeviceRGB"`, or `"DeviceCMYK"`
+    /// - tint: maps the tint of each colorant to the alternate space,
+    ///   see Function
+    ///
+    /// Colors in the space are given with Color::DeviceN.
+    ///
+    /// # Panics
+    ///
+    /// panics if the alternate space is unknown, a colorant is `"All"`,
+    /// which is only allowed in a Separation, or the tint transform
+    /// doesn't map one input per colorant to the components of the
+    /// alternate space
+    pub fn device_n(colorants: &[&str], alternate: &str, tint: Rc<Function>) -> Rc<Self> {
+        let components = alternate_components(alternate);
+        assert!(
+            !colorants.contains(&"All"),
+            "All is only a Separation colorant"
+        );
+        assert_eq!(
+            tint.inputs(),
+            colorants.len(),
+            "The tint transform must have one input per colorant"
+        );
+        assert_eq!(
+            tint.outputs(),
+            components,
+            "The tint transform must have one output per alternate component"
+        );
+        let names: Vec<Rc<dyn PDFData>> = colorants
+            .iter()
+            .map(|c| Name::new(*c) as Rc<dyn PDFData>)
+            .collect();
+        Rc::new(Self {
+            components: colorants.len(),
+            data: Rc::new(vec![
+                Name::new("DeviceN") as Rc<dyn PDFData>,
+                Rc::new(names),
+                Name::new(alternate),
+                tint.data(),
+            ]),
+            profile: None,
+            objects: tint.objects(),
+        })
+    }
+    /// A color space of a single colorant, such as a spot color
+    /// (`/Separation`)
+    ///
+    /// - colorant: the name of the colorant, such as `"PANTONE 485 C"`.
+    ///   `"All"` paints on every separation, such as for registration
+    ///   and crop marks, and `"None"` paints on none, so nothing is
+    ///   visible
+    /// - alternate: the space viewers without the colorant use,
+    ///   `"DeviceGray"`, `"DeviceRGB"`, or `"DeviceCMYK"`
+    /// - tint: maps the tint to the alternate space, see Function
+    ///
+    /// Colors in the space are given with Color::DeviceN, with a single
+    /// tint from 0 to 1.
+    ///
+    /// # Panics
+    ///
+    /// panics if the alternate space is unknown, or the tint transform
+    /// doesn't map one input to the components of the alternate space
+    pub fn separation(colorant: &str, alternate: &str, tint: Rc<Function>) -> Rc<Self> {
+        let components = alternate_components(alternate);
+        assert_eq!(tint.inputs(), 1, "The tint transform must have one input");
+        assert_eq!(
+            tint.outputs(),
+            components,
+            "The tint transform must have one output per alternate component"
+        );
+        Rc::new(Self {
+            components: 1,
+            data: Rc::new(vec![
+                Name::new("Separation") as Rc<dyn PDFData>,
+                Name::new(colorant),
+                Name::new(alternate),
+                tint.data(),
+            ]),
+            profile: None,
+            objects: tint.objects(),
+        })
+    }
+    /// The `/All` separation, which paints on every separation, for
+    /// registration and crop marks
+    ///
+    /// The alternate space is DeviceCMYK, with the tint used for each
+    /// of the four components, so the marks are printed in every ink
+    /// even where separations aren't supported.
+    pub fn registration() -> Rc<Self> {
+        Self::separation(
+            "All",
+            "DeviceCMYK",
+            Function::calculator(&[0f64, 1f64], &[0f64, 1f64].repeat(4), "dup dup dup"),
+        )
+    }
+    /// The number of color components in this color space
+    pub fn components(&self) -> usize {
+        self.components
+    }
+    /// The ICC profile data, if this is an ICC profile based color space
+    pub fn profile(&self) -> Option<&[u8]> {
+        self.profile.as_ref().map(|p| p.data())
+    }
+    pub(super) fn data(&self) -> Rc<dyn PDFData> {
+        self.data.clone()
+    }
+    /// Indirect objects the color space references
+    pub(super) fn objects(&self) -> Vec<Rc<dyn Object>> {
+        self.objects.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Color, Path};
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    /// The file with a rect filled in a color of the space
+    fn written_space(space: Rc<ColorSpace>, tints: Vec<f64>) -> String {
+        text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceN(space, tints)),
+            );
+            pdf.add_page(page);
+        }))
+    }
+
+    #[test]
+    fn device_n_calculator() {
+        let tint = Function::calculator(&[0f64, 1f64].repeat(2), &[0f64, 1f64].repeat(4), "0 0");
+        let file = written_space(
+            ColorSpace::device_n(&["Cyan", "Magenta"], "DeviceCMYK", tint),
+            vec![1f64, 0.5],
+        );
+        assert!(file.contains("[/DeviceN [/Cyan /Magenta] /DeviceCMYK "));
+        assert!(file.contains("/FunctionType 4\n"));
+        assert!(file.contains("/Domain [0 1 0 1]\n/Range [0 1 0 1 0 1 0 1]\n"));
+        assert!(file.contains("stream\n{ 0 0 }\nendstream"));
+    }
+
+    #[test]
+    fn registration() {
+        let file = written_space(ColorSpace::registration(), vec![1f64]);
+        assert!(file.contains("/CS1 [/Separation /All /DeviceCMYK 5 0 R]\n"));
+        assert!(file.contains("stream\n{ dup dup dup }\nendstream"));
+        assert!(file.contains(" /CS1 cs  1 scn "));
+    }
+
+    #[test]
+    #[should_panic(expected = "All is only a Separation colorant")]
+    fn device_n_all() {
+        let tint = Function::calculator(&[0f64, 1f64], &[0f64, 1f64], "");
+        ColorSpace::device_n(&["All"], "DeviceGray", tint);
+    }
+}
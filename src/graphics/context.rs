@@ -1,22 +1,38 @@
-use super::{GraphicContext, Parameter};
+use super::{ColorSpace, GraphicContext, Parameter};
 // use crate::pdf::{Dict, Name};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-pub trait Graphic: Sized {
+/// Something that can be drawn in a GraphicContext
+///
+/// Graphics are object safe, so different kinds can be drawn together,
+/// see Group.
+pub trait Graphic: std::fmt::Debug {
     fn get_graphics_parameters(&self) -> &GraphicParameters;
     fn render(&self, out: &mut GraphicContext);
+    /// The area the graphic covers, in the coordinates it is rendered in
+    ///
+    /// This doesn't need to be exact, and None means unknown
+    fn bounds(&self) -> Option<Rect> {
+        None
+    }
     fn set_fill_color(&self, color: Color) {
         self.get_graphics_parameters().fill_color(color);
     }
-    fn fill_color(self, color: Color) -> Self {
+    fn fill_color(self, color: Color) -> Self
+    where
+        Self: Sized,
+    {
         self.get_graphics_parameters().fill_color(color);
         self
     }
     fn set_stroke_color(&self, color: Color) {
         self.get_graphics_parameters().stroke_color(color);
     }
-    fn stroke_color(self, color: Color) -> Self {
+    fn stroke_color(self, color: Color) -> Self
+    where
+        Self: Sized,
+    {
         self.get_graphics_parameters().stroke_color(color);
         self
     }
@@ -29,19 +45,18 @@ pub enum GraphicsContextType {
 }
 impl GraphicsContextType {
     fn color(&self) -> bool {
-        match self {
-            Self::NoColor => false,
-            _ => true,
-        }
+        !matches!(self, Self::NoColor)
     }
 }
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct GraphicParameters {
     context_type: GraphicsContextType,
     transform: (),
     clipping_path: (),
-    fill: RefCell<Color>,
-    stroke: RefCell<Color>,
+    /// The colors, or None to draw with the current color
+    fill: RefCell<Option<Color>>,
+    stroke: RefCell<Option<Color>>,
     text_state: (),
     line_state: (),
     rendering_intent: (),
@@ -49,13 +64,15 @@ pub struct GraphicParameters {
 }
 
 impl GraphicParameters {
+    /// Parameters that set the colors that are Some, and keep the
+    /// current colors otherwise
     pub fn with_colors(fill: Option<Color>, stroke: Option<Color>) -> Self {
         let tmp = Self {
             context_type: GraphicsContextType::Normal,
             transform: (),
             clipping_path: (),
-            fill: RefCell::new(Color::default()),
-            stroke: RefCell::new(Color::default()),
+            fill: RefCell::new(None),
+            stroke: RefCell::new(None),
             text_state: (),
             line_state: (),
             rendering_intent: (),
@@ -74,31 +91,44 @@ impl GraphicParameters {
             context_type,
             transform: (),
             clipping_path: (),
-            fill: RefCell::new(Color::default()),
-            stroke: RefCell::new(Color::default()),
+            fill: RefCell::new(Some(Color::default())),
+            stroke: RefCell::new(Some(Color::default())),
             text_state: (),
             line_state: (),
             rendering_intent: (),
             blend_state: (),
         }
     }
+    /// The parameters at the start of a content stream of this type
+    pub fn initial(&self) -> Self {
+        Self::with_type(self.context_type)
+    }
     pub fn update(ctx: &mut GraphicContext, new: &Self) {
         // Clones Rc to allow mutating the current params
         let old = ctx.current.clone();
-        if old.context_type.color() {
+        if old.context_type.color() && new.context_type.color() {
             // Fill Color
-            new.fill.borrow().write(&old.fill.borrow(), false, ctx);
-            *old.fill.borrow_mut() = new.fill.borrow().clone();
+            if let Some(fill) = new.fill.borrow().clone() {
+                fill.write(&old.fill(), false, ctx);
+                *old.fill.borrow_mut() = Some(fill);
+            }
             // Stroke Color
-            new.stroke.borrow().write(&old.stroke.borrow(), true, ctx);
-            *old.stroke.borrow_mut() = new.stroke.borrow().clone();
+            if let Some(stroke) = new.stroke.borrow().clone() {
+                let prev = old.stroke.borrow().clone().unwrap_or_default();
+                stroke.write(&prev, true, ctx);
+                *old.stroke.borrow_mut() = Some(stroke);
+            }
         }
     }
     pub fn fill_color(&self, color: Color) {
-        *self.fill.borrow_mut() = color;
+        *self.fill.borrow_mut() = Some(color);
+    }
+    /// The fill color, black if it isn't set
+    pub fn fill(&self) -> Color {
+        self.fill.borrow().clone().unwrap_or_default()
     }
     pub fn stroke_color(&self, color: Color) {
-        *self.stroke.borrow_mut() = color;
+        *self.stroke.borrow_mut() = Some(color);
     }
 }
 impl Default for GraphicParameters {
@@ -107,8 +137,8 @@ impl Default for GraphicParameters {
             context_type: GraphicsContextType::Normal,
             transform: (),
             clipping_path: (),
-            fill: RefCell::new(Color::default()),
-            stroke: RefCell::new(Color::default()),
+            fill: RefCell::new(Some(Color::default())),
+            stroke: RefCell::new(Some(Color::default())),
             text_state: (),
             line_state: (),
             rendering_intent: (),
@@ -116,7 +146,7 @@ impl Default for GraphicParameters {
         }
     }
 }
-use crate::pdf::{types::Stream, Name, ObjRef, Object};
+use crate::pdf::{types::Stream, Name, ObjRef, PDFWrite};
 pub struct PatternBuilder {
     graphics: GraphicContext,
 }
@@ -135,8 +165,9 @@ impl PatternBuilder {
     pub fn add(&mut self, g: Rc<impl Graphic>) {
         self.graphics.render(g);
     }
-    fn render(self) -> Color {
-        let (streams, resources) = self.graphics.compile();
+    #[allow(dead_code)]
+    fn render(self, write: &mut PDFWrite) -> Color {
+        let (streams, _resources) = self.graphics.compile(write).unwrap();
         if streams.len() != 1 {
             panic!("The graphics context for a pattern may only generate one stream!");
         }
@@ -152,14 +183,55 @@ pub enum Color {
     DeviceRGB(f64, f64, f64),
     DeviceCMYK(f64, f64, f64, f64),
     Pattern(Rc<Name>, Rc<ObjRef<Stream>>),
+    /// A color in an ICC profile based color space, see
+    /// ColorSpace::icc_based
+    ICCBased(Rc<ColorSpace>, Vec<f64>),
+    /// A color in a DeviceN or Separation color space, with the tint
+    /// of each colorant, see ColorSpace::device_n and
+    /// ColorSpace::separation
+    DeviceN(Rc<ColorSpace>, Vec<f64>),
 }
-impl Color {
-    pub fn default() -> Self {
+impl Default for Color {
+    fn default() -> Self {
         Self::DeviceGray(0f64)
     }
+}
+impl Color {
     pub fn red() -> Self {
         Self::DeviceRGB(1f64, 0f64, 0f64)
     }
+    /// Relative luminance, as defined by WCAG 2
+    ///
+    /// CMYK colors are converted to RGB without a profile. Returns None
+    /// for patterns, ICC based, and DeviceN colors
+    pub fn luminance(&self) -> Option<f64> {
+        let (r, g, b) = match self {
+            Self::DeviceGray(g) => (*g, *g, *g),
+            Self::DeviceRGB(r, g, b) => (*r, *g, *b),
+            Self::DeviceCMYK(c, m, y, k) => (
+                (1f64 - c) * (1f64 - k),
+                (1f64 - m) * (1f64 - k),
+                (1f64 - y) * (1f64 - k),
+            ),
+            Self::Pattern(..) | Self::ICCBased(..) | Self::DeviceN(..) => return None,
+        };
+        let linear = |c: f64| {
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+    }
+    /// Contrast ratio between two colors, as defined by WCAG 2
+    ///
+    /// Ranges from 1 (no contrast) to 21 (black on white). Returns None
+    /// if either luminance is unknown, see luminance
+    pub fn contrast_ratio(&self, other: &Color) -> Option<f64> {
+        let (a, b) = (self.luminance()?, other.luminance()?);
+        Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+    }
     fn set_colorspace(stroke: bool) -> &'static str {
         if stroke {
             "CS "
@@ -174,14 +246,28 @@ impl Color {
             "scn "
         }
     }
+    /// Whether self is in the same color space as prev, so the
+    /// color space doesn't need to be set again
+    fn same_space(&self, prev: &Color) -> bool {
+        match (self, prev) {
+            (Self::ICCBased(a, _), Self::ICCBased(b, _))
+            | (Self::DeviceN(a, _), Self::DeviceN(b, _)) => Rc::ptr_eq(a, b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(prev),
+        }
+    }
     /// Writes self to out using cs and sc to set the color mode
     ///
     /// - prev: &Color, the current color
     /// - cs: the color space command
     /// - sc: the set color command
     /// - out: output
+    ///
+    /// Nothing is written if self is the current color
     fn write(&self, prev: &Color, stroke: bool, out: &mut GraphicContext) {
-        if std::mem::discriminant(self) != std::mem::discriminant(prev) {
+        if self == prev {
+            return;
+        }
+        if !self.same_space(prev) {
             match self {
                 Self::DeviceGray(..) => out.command(
                     &mut [Name::new("DeviceGray").into()],
@@ -199,6 +285,10 @@ impl Color {
                     &mut [Name::new("Pattern").into()],
                     Self::set_colorspace(stroke),
                 ),
+                Self::ICCBased(space, _) | Self::DeviceN(space, _) => {
+                    let name = out.add_colorspace(space.clone());
+                    out.command(&mut [name.into()], Self::set_colorspace(stroke))
+                }
             }
         }
         match self {
@@ -214,12 +304,64 @@ impl Color {
                 out.add_resource(obj.clone());
                 out.command(&mut [name.clone().into()], Self::set_color(stroke))
             }
+            Self::ICCBased(_, components) | Self::DeviceN(_, components) => out.command(
+                &mut components.iter().map(|c| c.into()).collect::<Vec<_>>(),
+                Self::set_color(stroke),
+            ),
+        }
+    }
+}
+
+/// Components closer than this are the same color: far below what any
+/// output device can distinguish
+const COLOR_EPSILON: f64 = 1e-6;
+
+/// Colors are equal if they are in the same color space, and their
+/// components are within COLOR_EPSILON of each other
+impl PartialEq for Color {
+    fn eq(&self, other: &Self) -> bool {
+        let close = |a: &[f64], b: &[f64]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() <= COLOR_EPSILON)
+        };
+        match (self, other) {
+            (Self::DeviceGray(a), Self::DeviceGray(b)) => close(&[*a], &[*b]),
+            (Self::DeviceRGB(r1, g1, b1), Self::DeviceRGB(r2, g2, b2)) => {
+                close(&[*r1, *g1, *b1], &[*r2, *g2, *b2])
+            }
+            (Self::DeviceCMYK(c1, m1, y1, k1), Self::DeviceCMYK(c2, m2, y2, k2)) => {
+                close(&[*c1, *m1, *y1, *k1], &[*c2, *m2, *y2, *k2])
+            }
+            (Self::Pattern(a, obj_a), Self::Pattern(b, obj_b)) => {
+                a == b && Rc::ptr_eq(obj_a, obj_b)
+            }
+            (Self::ICCBased(_, a), Self::ICCBased(_, b))
+            | (Self::DeviceN(_, a), Self::DeviceN(_, b)) => self.same_space(other) && close(a, b),
+            _ => false,
         }
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Point(f64, f64);
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self(x, y)
+    }
+    pub fn x(&self) -> f64 {
+        self.0
+    }
+    pub fn y(&self) -> f64 {
+        self.1
+    }
+    /// The point as `[x, y]`
+    pub fn to_array(&self) -> [f64; 2] {
+        [self.0, self.1]
+    }
+    /// A point from `[x, y]`
+    pub fn from_array(a: [f64; 2]) -> Self {
+        Self(a[0], a[1])
+    }
+}
 
 impl From<(f64, f64)> for Point {
     fn from(o: (f64, f64)) -> Self {
@@ -248,12 +390,64 @@ impl Rect {
     pub fn new(x: f64, y: f64, w: f64, h: f64) -> Self {
         Self(x, y, w, h)
     }
+    /// The rect as `[x, y, width, height]`, the order of Rect::new
+    pub fn to_array(&self) -> [f64; 4] {
+        [self.0, self.1, self.2, self.3]
+    }
+    /// A rect from `[x, y, width, height]`, see from_corners for the
+    /// order of a PDF rectangle
+    pub fn from_array(a: [f64; 4]) -> Self {
+        Self(a[0], a[1], a[2], a[3])
+    }
+    /// A rect from its lower left and upper right corners, the order of
+    /// a PDF rectangle like `[100 100 712 892]`
+    pub fn from_corners(llx: f64, lly: f64, urx: f64, ury: f64) -> Self {
+        Self(llx, lly, urx - llx, ury - lly)
+    }
+    /// The smallest rect containing all of the points, or None if there
+    /// are no points
+    pub fn enclosing(points: impl IntoIterator<Item = Point>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (mut min, mut max) = (first, first);
+        for p in points {
+            min = Point(min.0.min(p.0), min.1.min(p.1));
+            max = Point(max.0.max(p.0), max.1.max(p.1));
+        }
+        Some(Self(min.0, min.1, max.0 - min.0, max.1 - min.1))
+    }
+    /// The corners of the rect, counter-clockwise from (x, y)
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            Point(self.0, self.1),
+            Point(self.0 + self.2, self.1),
+            Point(self.0 + self.2, self.1 + self.3),
+            Point(self.0, self.1 + self.3),
+        ]
+    }
+    /// The smallest rect containing both rects
+    pub fn union(&self, other: &Rect) -> Self {
+        Self::enclosing(self.corners().iter().chain(other.corners().iter()).copied()).unwrap()
+    }
+    pub fn x(&self) -> f64 {
+        self.0
+    }
+    pub fn y(&self) -> f64 {
+        self.1
+    }
+    pub fn width(&self) -> f64 {
+        self.2
+    }
+    pub fn height(&self) -> f64 {
+        self.3
+    }
+    /// The rect as a PDF rectangle, `[llx lly urx ury]`
     pub fn as_data(&self) -> Rc<Vec<Rc<f64>>> {
         Rc::new(vec![
             Rc::new(self.0),
             Rc::new(self.1),
-            Rc::new(self.2),
-            Rc::new(self.3),
+            Rc::new(self.0 + self.2),
+            Rc::new(self.1 + self.3),
         ])
     }
 }
@@ -281,3 +475,180 @@ impl From<Rect> for Parameter {
         }
     }
 }
+
+/// The point of a rect a Position is measured from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// A distance from an Anchor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Offset {
+    /// A distance in points
+    Points(f64),
+    /// A percentage of the width or height of the rect
+    Percent(f64),
+}
+impl Offset {
+    fn resolve(&self, length: f64) -> f64 {
+        match self {
+            Self::Points(p) => *p,
+            Self::Percent(p) => length * p / 100f64,
+        }
+    }
+}
+
+/// A point relative to a rect, such as 10pt below the top center
+///
+/// The offsets are measured from the anchor, x to the right and y
+/// downward, so a position reads like "50% across, 10pt from the top".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    anchor: Anchor,
+    x: Offset,
+    y: Offset,
+}
+impl Position {
+    pub fn new(anchor: Anchor, x: Offset, y: Offset) -> Self {
+        Self { anchor, x, y }
+    }
+    /// The anchor itself, with no offset
+    pub fn at(anchor: Anchor) -> Self {
+        Self::new(anchor, Offset::Points(0f64), Offset::Points(0f64))
+    }
+    /// The point in rect, with y growing upward
+    pub fn resolve(&self, rect: Rect) -> Point {
+        let (w, h) = (rect.width(), rect.height());
+        let (ax, ay) = match self.anchor {
+            Anchor::TopLeft => (0f64, h),
+            Anchor::Top => (w / 2f64, h),
+            Anchor::TopRight => (w, h),
+            Anchor::Left => (0f64, h / 2f64),
+            Anchor::Center => (w / 2f64, h / 2f64),
+            Anchor::Right => (w, h / 2f64),
+            Anchor::BottomLeft => (0f64, 0f64),
+            Anchor::Bottom => (w / 2f64, 0f64),
+            Anchor::BottomRight => (w, 0f64),
+        };
+        Point(
+            rect.x() + ax + self.x.resolve(w),
+            rect.y() + ay - self.y.resolve(h),
+        )
+    }
+}
+
+/// An affine transformation matrix
+///
+/// Stored as `[a b c d e f]`, the same order the `cm` operator takes,
+/// and maps a point (x, y) to (a*x + c*y + e, b*x + d*y + f)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix(f64, f64, f64, f64, f64, f64);
+impl Matrix {
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self(a, b, c, d, e, f)
+    }
+    /// The matrix as a PDF array, `[a b c d e f]`
+    pub fn as_data(&self) -> Rc<Vec<Rc<f64>>> {
+        Rc::new(
+            [self.0, self.1, self.2, self.3, self.4, self.5]
+                .iter()
+                .map(|v| Rc::new(*v))
+                .collect(),
+        )
+    }
+    pub fn identity() -> Self {
+        Self(1f64, 0f64, 0f64, 1f64, 0f64, 0f64)
+    }
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self(1f64, 0f64, 0f64, 1f64, x, y)
+    }
+    pub fn scale(x: f64, y: f64) -> Self {
+        Self(x, 0f64, 0f64, y, 0f64, 0f64)
+    }
+    /// Rotation counter-clockwise by angle, in radians
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self(cos, sin, -sin, cos, 0f64, 0f64)
+    }
+    /// Returns a matrix that applies self, and then other
+    pub fn then(&self, o: &Matrix) -> Self {
+        Self(
+            self.0 * o.0 + self.1 * o.2,
+            self.0 * o.1 + self.1 * o.3,
+            self.2 * o.0 + self.3 * o.2,
+            self.2 * o.1 + self.3 * o.3,
+            self.4 * o.0 + self.5 * o.2 + o.4,
+            self.4 * o.1 + self.5 * o.3 + o.5,
+        )
+    }
+    /// Transforms a point by this matrix
+    pub fn apply(&self, p: impl Into<Point>) -> Point {
+        let p = p.into();
+        Point(
+            self.0 * p.0 + self.2 * p.1 + self.4,
+            self.1 * p.0 + self.3 * p.1 + self.5,
+        )
+    }
+}
+
+impl From<Matrix> for Parameter {
+    fn from(m: Matrix) -> Self {
+        Self {
+            raw: [m.0, m.1, m.2, m.3, m.4, m.5]
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+                .into_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_eq() {
+        assert_eq!(
+            Color::DeviceRGB(0.2f64, 0.4f64, 0.6f64),
+            Color::DeviceRGB(0.2f64, 0.4f64, 0.6f64 + 1e-9)
+        );
+        assert_ne!(
+            Color::DeviceRGB(0.2f64, 0.4f64, 0.6f64),
+            Color::DeviceRGB(0.2f64, 0.4f64, 0.7f64)
+        );
+    }
+
+    #[test]
+    fn rect_array() {
+        let rect = Rect::new(10f64, 20f64, 30f64, 40f64);
+        assert_eq!(rect.to_array(), [10f64, 20f64, 30f64, 40f64]);
+        assert_eq!(Rect::from_array(rect.to_array()), rect);
+        assert_eq!(Point::from_array([1f64, 2f64]), Point::new(1f64, 2f64));
+    }
+
+    #[test]
+    fn position_center() {
+        let page = Rect::new(0f64, 0f64, 612f64, 792f64);
+        assert_eq!(
+            Position::at(Anchor::Center).resolve(page),
+            Point::new(306f64, 396f64)
+        );
+        let top = Position::new(
+            Anchor::TopLeft,
+            Offset::Percent(50f64),
+            Offset::Points(10f64),
+        );
+        assert_eq!(top.resolve(page), Point::new(306f64, 782f64));
+    }
+}
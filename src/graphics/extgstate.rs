@@ -0,0 +1,134 @@
+use super::Stamp;
+use crate::pdf::{Dict, Name, PDFData};
+use std::rc::Rc;
+
+/// Which values of a soft mask's group give the mask (`/S` of `/SMask`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SoftMaskType {
+    /// The luminosity of the group, so white is opaque and black is
+    /// transparent
+    Luminosity,
+    /// The alpha of the group, so only its transparency matters
+    Alpha,
+}
+impl SoftMaskType {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Luminosity => "Luminosity",
+            Self::Alpha => "Alpha",
+        }
+    }
+}
+
+/// A graphics state parameter dictionary (`/ExtGState`), for the parts
+/// of the graphics state that don't have their own operator
+///
+/// Set it with GraphicContext::set_ext_gstate, which adds it to the
+/// resources of the page.
+#[derive(Debug)]
+pub struct ExtGState {
+    dict: Rc<Dict>,
+}
+
+impl Default for ExtGState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtGState {
+    pub fn new() -> Self {
+        Self {
+            dict: Dict::from_vec(vec![("Type", Name::new("ExtGState"))]),
+        }
+    }
+    /// Sets the constant alpha used for filling (`/ca`)
+    ///
+    /// - alpha: from 0 (transparent) to 1 (opaque)
+    pub fn fill_alpha(self, alpha: f64) -> Self {
+        self.dict.add_entry("ca", Rc::new(alpha));
+        self
+    }
+    /// Sets the constant alpha used for stroking (`/CA`)
+    ///
+    /// - alpha: from 0 (transparent) to 1 (opaque)
+    pub fn stroke_alpha(self, alpha: f64) -> Self {
+        self.dict.add_entry("CA", Rc::new(alpha));
+        self
+    }
+    /// Sets whether the alpha values are shape rather than opacity
+    /// values (`/AIS`)
+    pub fn alpha_is_shape(self, ais: bool) -> Self {
+        self.dict.add_entry("AIS", Rc::new(ais));
+        self
+    }
+    /// Sets whether the glyphs of a text object knock each other out
+    /// where they overlap (`/TK`), rather than compositing on top of
+    /// each other. Viewers default to true
+    pub fn text_knockout(self, knockout: bool) -> Self {
+        self.dict.add_entry("TK", Rc::new(knockout));
+        self
+    }
+    /// Masks what is painted with the content of a stamp (`/SMask`),
+    /// such as a gradient for a vignette
+    ///
+    /// - mask: the content giving the mask, in the coordinates of the
+    ///   page when the ExtGState is set. It is made a transparency group
+    ///   (`/Group`), so it can be used as a mask
+    /// - kind: see SoftMaskType
+    pub fn soft_mask(self, mask: &Rc<Stamp>, kind: SoftMaskType) -> Self {
+        mask.make_group();
+        self.dict.add_entry(
+            "SMask",
+            Dict::from_vec(vec![
+                ("Type", Name::new("Mask") as Rc<dyn PDFData>),
+                ("S", Name::new(kind.name())),
+                ("G", mask.object()),
+            ]),
+        );
+        self
+    }
+    pub(super) fn data(&self) -> Rc<dyn PDFData> {
+        self.dict.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Color, Path};
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn luminosity_soft_mask() {
+        let file = text(&written(|pdf| {
+            let mask = pdf.define_stamp((0f64, 0f64, 100f64, 100f64), |ctx| {
+                ctx.render(
+                    Path::new()
+                        .rect((0f64, 0f64, 50f64, 100f64))
+                        .fill(Color::DeviceGray(1f64)),
+                );
+            });
+            let mut page = Page::new();
+            page.graphics.set_ext_gstate(Rc::new(
+                ExtGState::new().soft_mask(&mask, SoftMaskType::Luminosity),
+            ));
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/SMask <<\n/Type /Mask\n/S /Luminosity\n/G 4 0 R\n>>\n"));
+        assert!(file.contains("4 0 obj\n<<\n/Length 23\n/Type /XObject\n/Subtype /Form\n"));
+        assert!(file.contains("/Group <<\n/Type /Group\n/S /Transparency\n"));
+    }
+
+    #[test]
+    fn text_knockout() {
+        let state = ExtGState::new().text_knockout(false);
+        let mut data = vec![];
+        state.data().write(&mut data).unwrap();
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "<<\n/Type /ExtGState\n/TK false\n>>\n"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use super::text::GraphicText;
+use super::{Color, Font, Rect, Text};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Where words may be hyphenated, for the language of a TextFlow
+///
+/// The words are given with their hyphenation points, such as
+/// `"hy-phen-ation"`, and matched ignoring case and any punctuation
+/// around them.
+#[derive(Debug, Clone, Default)]
+pub struct Hyphenation {
+    /// The break points of each word, counted in characters
+    words: HashMap<String, Vec<usize>>,
+}
+
+impl Hyphenation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a word, with a `-` at each point it may be broken
+    pub fn word(mut self, hyphenated: &str) -> Self {
+        let mut breaks = vec![];
+        let mut word = String::new();
+        for c in hyphenated.chars() {
+            if c == '-' {
+                breaks.push(word.chars().count());
+            } else {
+                word.extend(c.to_lowercase());
+            }
+        }
+        self.words.insert(word, breaks);
+        self
+    }
+    /// Splits the word at the last break point where the start, with a
+    /// hyphen, still fits
+    fn split(&self, word: &str, fits: impl Fn(&str) -> bool) -> Option<(String, String)> {
+        let chars: Vec<char> = word.chars().collect();
+        let start = chars.iter().position(|c| c.is_alphabetic())?;
+        let end = chars.iter().rposition(|c| c.is_alphabetic())? + 1;
+        let key: String = chars[start..end]
+            .iter()
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        let breaks = self.words.get(&key)?;
+        breaks.iter().rev().find_map(|b| {
+            let head: String = chars[..start + b].iter().collect();
+            let head = format!("{}-", head);
+            if fits(&head) {
+                Some((head, chars[start + b..].iter().collect()))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Text that is wrapped to fit inside a rectangle
+///
+/// Lines are broken at spaces, or within words if hyphenation is set,
+/// and words too wide for a line are broken between characters. The
+/// text fills the first column from top to bottom, then the next
+/// column, and what doesn't fit on the page is returned by `layout`, to
+/// be placed on the next page.
+///
+/// Each line of the text starts a new paragraph, and empty lines are
+/// kept as blank lines.
+#[derive(Debug, Clone)]
+pub struct TextFlow {
+    font: Rc<Font>,
+    size: f64,
+    leading: f64,
+    columns: usize,
+    gutter: f64,
+    color: Color,
+    hyphenation: Option<Hyphenation>,
+    /// The remaining words of each paragraph
+    paragraphs: Vec<Vec<String>>,
+}
+
+impl TextFlow {
+    /// Creates an empty flow, with a single column
+    ///
+    /// The leading defaults to 1.2 times the font size
+    pub fn new(font: Rc<Font>, size: f64) -> Self {
+        Self {
+            font,
+            size,
+            leading: size * 1.2,
+            columns: 1,
+            gutter: 0f64,
+            color: Color::default(),
+            hyphenation: None,
+            paragraphs: vec![],
+        }
+    }
+    /// Adds text to the end of the flow
+    pub fn text(mut self, text: impl AsRef<str>) -> Self {
+        self.paragraphs.extend(
+            text.as_ref()
+                .lines()
+                .map(|l| l.split_whitespace().map(String::from).collect()),
+        );
+        self
+    }
+    /// Sets the distance between the baselines of two lines
+    pub fn leading(mut self, leading: f64) -> Self {
+        self.leading = leading;
+        self
+    }
+    /// Splits the rect into columns
+    ///
+    /// - count: the number of columns
+    /// - gutter: the space between two columns
+    ///
+    /// # Panics
+    ///
+    /// panics if count is zero
+    pub fn columns(mut self, count: usize, gutter: f64) -> Self {
+        assert!(count > 0, "A flow needs at least one column");
+        self.columns = count;
+        self.gutter = gutter;
+        self
+    }
+    /// Sets the fill color of the text
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+    /// Hyphenates words that don't fit at the end of a line, at the
+    /// points given by the hyphenation
+    pub fn hyphenate(mut self, hyphenation: Hyphenation) -> Self {
+        self.hyphenation = Some(hyphenation);
+        self
+    }
+    /// Whether all of the text has been placed
+    pub fn is_empty(&self) -> bool {
+        self.paragraphs.is_empty()
+    }
+    /// Width of each column, when the flow is placed in the rect
+    pub fn column_width(&self, rect: impl Into<Rect>) -> f64 {
+        let rect = rect.into();
+        (rect.width() - self.gutter * (self.columns - 1) as f64) / self.columns as f64
+    }
+    /// Places as much of the text as fits in the rect
+    ///
+    /// The first baseline of each column is one font size below the top
+    /// of the rect. Returns the text, and the rest of the flow if the
+    /// text didn't fit.
+    ///
+    /// # Panics
+    ///
+    /// panics if the rect isn't tall enough for a single line
+    pub fn layout(mut self, rect: impl Into<Rect>) -> (Rc<GraphicText>, Option<Self>) {
+        let rect = rect.into();
+        assert!(
+            rect.height() >= self.size,
+            "The rect must be tall enough for at least one line"
+        );
+        let per_column = ((rect.height() - self.size) / self.leading).floor() as usize + 1;
+        let width = self.column_width(rect);
+        let top = rect.y() + rect.height() - self.size;
+
+        let mut text = Text::new(self.font.clone(), self.size);
+        let mut lines = 0;
+        while lines < per_column * self.columns && !self.paragraphs.is_empty() {
+            let column = lines / per_column;
+            let x = rect.x() + (width + self.gutter) * column as f64;
+            let y = top - self.leading * (lines % per_column) as f64;
+            let line = self.next_line(width);
+            text = text.move_to((x, y)).text(line);
+            lines += 1;
+        }
+        let text = Rc::new(text.fill(self.color.clone()));
+        if self.paragraphs.is_empty() {
+            (text, None)
+        } else {
+            (text, Some(self))
+        }
+    }
+    /// Removes the words of the next line from the first paragraph
+    fn next_line(&mut self, width: f64) -> String {
+        let (font, size) = (&self.font, self.size);
+        let words = &mut self.paragraphs[0];
+        let mut line = String::new();
+        let mut count = 0;
+        for word in words.iter() {
+            let candidate = if line.is_empty() {
+                word.clone()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if font.text_width(&candidate, size) > width {
+                break;
+            }
+            line = candidate;
+            count += 1;
+        }
+        // The start of the next word may still fit, with a hyphen
+        let split = match (&self.hyphenation, words.get(count)) {
+            (Some(h), Some(word)) => h.split(word, |head| {
+                let candidate = if line.is_empty() {
+                    head.to_string()
+                } else {
+                    format!("{} {}", line, head)
+                };
+                font.text_width(&candidate, size) <= width
+            }),
+            _ => None,
+        };
+        if let Some((head, tail)) = split {
+            line = if line.is_empty() {
+                head
+            } else {
+                format!("{} {}", line, head)
+            };
+            words[count] = tail;
+            words.drain(..count);
+        } else if count == 0 && !words.is_empty() {
+            // The word is wider than the column, so break it up
+            let word = words.remove(0);
+            // At least one character is placed on each line
+            let split = word
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| i)
+                .take_while(|i| font.text_width(&word[..*i], size) <= width)
+                .last()
+                .unwrap_or_else(|| word.chars().next().map_or(0, char::len_utf8));
+            line = word[..split].to_string();
+            if split < word.len() {
+                words.insert(0, word[split..].to_string());
+            }
+        } else {
+            words.drain(..count);
+        }
+        if words.is_empty() {
+            self.paragraphs.remove(0);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+
+    #[test]
+    fn two_columns() {
+        let flow = TextFlow::new(Font::helvetica(), 10f64)
+            .leading(12f64)
+            .columns(2, 20f64)
+            .text("one two three four five six seven eight nine ten eleven twelve");
+        assert_eq!(flow.column_width((0f64, 0f64, 200f64, 30f64)), 90f64);
+        let (text, rest) = flow.layout((0f64, 0f64, 200f64, 30f64));
+        assert!(rest.is_none());
+        // The first line of the second column is a column width and
+        // the gutter right of the first, at the top again
+        assert!(rendered(text).contains("(five six seven eight) Tj 110 12 Td (nine ten eleven) Tj"));
+    }
+
+    #[test]
+    fn hyphenate() {
+        let flow = TextFlow::new(Font::helvetica(), 10f64)
+            .leading(12f64)
+            .hyphenate(Hyphenation::new().word("hy-phen-ation"))
+            .text("some hyphenation");
+        let (text, _) = flow.layout((0f64, 0f64, 65f64, 100f64));
+        assert!(rendered(text).contains("(some hyphen-) Tj 0 -12 Td (ation) Tj"));
+    }
+}
@@ -0,0 +1,296 @@
+use crate::pdf::{types::Stream, Dict, ObjRef, Object, PDFData};
+use std::rc::Rc;
+
+/// A function mapping a number of inputs to a number of outputs, used
+/// by tint transforms, shadings, and transfer functions
+///
+/// Functions that are streams are only written once, however many
+/// objects use them.
+#[derive(Debug)]
+pub struct Function {
+    inputs: usize,
+    outputs: usize,
+    data: Rc<dyn PDFData>,
+    /// Indirect objects the function references
+    objects: Vec<Rc<dyn Object>>,
+}
+
+/// An array of numbers
+fn numbers(values: &[f64]) -> Rc<dyn PDFData> {
+    Rc::new(values.iter().map(|v| Rc::new(*v)).collect::<Vec<_>>())
+}
+
+/// The number of min/max pairs in a domain or range
+fn pairs(values: &[f64], what: &str) -> usize {
+    assert!(
+        !values.is_empty() && values.len().is_multiple_of(2),
+        "The {} must be pairs of min and max values",
+        what
+    );
+    values.len() / 2
+}
+
+impl Function {
+    /// A PostScript calculator function (type 4)
+    ///
+    /// - domain: the min and max of each input, `[min0 max0 min1 max1 ...]`
+    /// - range: the min and max of each output
+    /// - code: the calculator program, such as `"{ 0 exch 0 0 }"`. The
+    ///   enclosing braces are added if they are missing
+    ///
+    /// The program starts with the inputs on the stack, and must leave
+    /// the outputs on the stack.
+    ///
+    /// # Panics
+    ///
+    /// panics if the domain or range isn't a list of pairs
+    pub fn calculator(domain: &[f64], range: &[f64], code: &str) -> Rc<Self> {
+        let (inputs, outputs) = (pairs(domain, "domain"), pairs(range, "range"));
+        let code = code.trim();
+        let code = if code.starts_with('{') {
+            code.to_string()
+        } else {
+            format!("{{ {} }}", code)
+        };
+        let stream = ObjRef::new(
+            0,
+            Stream::new(
+                Dict::from_vec(vec![
+                    ("FunctionType", Rc::new(4usize) as Rc<dyn PDFData>),
+                    ("Domain", numbers(domain)),
+                    ("Range", numbers(range)),
+                ]),
+                code.into_bytes(),
+            ),
+        );
+        Rc::new(Self {
+            inputs,
+            outputs,
+            data: stream.clone(),
+            objects: vec![stream],
+        })
+    }
+    /// A sampled function (type 0), which interpolates a table of values
+    ///
+    /// - domain: the min and max of each input, `[min0 max0 min1 max1 ...]`
+    /// - range: the min and max of each output
+    /// - size: the number of samples along each input
+    /// - samples: the outputs at each sample point, with the first input
+    ///   varying fastest, as integers from 0 to 2^bits - 1, which are
+    ///   mapped onto the range
+    /// - bits: the bits per sample, 1, 2, 4, 8, 12, 16, 24, or 32
+    ///
+    /// # Panics
+    ///
+    /// panics if the domain or range isn't a list of pairs, size doesn't
+    /// have an entry per input, the number of samples isn't
+    /// `size[0] * size[1] * ... * outputs`, or a sample doesn't fit in
+    /// the bits per sample
+    pub fn sampled(
+        domain: &[f64],
+        range: &[f64],
+        size: &[usize],
+        samples: &[u32],
+        bits: usize,
+    ) -> Rc<Self> {
+        let (inputs, outputs) = (pairs(domain, "domain"), pairs(range, "range"));
+        assert!(
+            [1, 2, 4, 8, 12, 16, 24, 32].contains(&bits),
+            "Unsupported bits per sample"
+        );
+        assert_eq!(size.len(), inputs, "size must have an entry per input");
+        assert!(size.iter().all(|s| *s > 0), "size must be positive");
+        assert_eq!(
+            samples.len(),
+            size.iter().product::<usize>() * outputs,
+            "Incorrect number of samples"
+        );
+        let max = (1u64 << bits) - 1;
+        assert!(
+            samples.iter().all(|s| u64::from(*s) <= max),
+            "A sample doesn't fit in the bits per sample"
+        );
+        // Samples are packed most significant bit first, and only the
+        // end of the data is padded to a whole byte
+        let mut data = Vec::with_capacity((samples.len() * bits).div_ceil(8));
+        let (mut acc, mut len) = (0u64, 0);
+        for sample in samples {
+            acc = (acc << bits) | u64::from(*sample);
+            len += bits;
+            while len >= 8 {
+                len -= 8;
+                data.push((acc >> len) as u8);
+            }
+            acc &= (1u64 << len) - 1;
+        }
+        if len > 0 {
+            data.push((acc << (8 - len)) as u8);
+        }
+        let encode: Vec<f64> = size.iter().flat_map(|s| [0f64, (*s - 1) as f64]).collect();
+        let stream = ObjRef::new(
+            0,
+            Stream::new(
+                Dict::from_vec(vec![
+                    ("FunctionType", Rc::new(0usize) as Rc<dyn PDFData>),
+                    ("Domain", numbers(domain)),
+                    ("Range", numbers(range)),
+                    (
+                        "Size",
+                        Rc::new(size.iter().map(|s| Rc::new(*s)).collect::<Vec<_>>()),
+                    ),
+                    ("BitsPerSample", Rc::new(bits)),
+                    ("Encode", numbers(&encode)),
+                    ("Decode", numbers(range)),
+                ]),
+                data,
+            ),
+        );
+        Rc::new(Self {
+            inputs,
+            outputs,
+            data: stream.clone(),
+            objects: vec![stream],
+        })
+    }
+    /// An exponential interpolation function (type 2), with one input
+    /// from 0 to 1
+    ///
+    /// - c0: the outputs at 0
+    /// - c1: the outputs at 1
+    /// - n: the exponent, 1 for linear interpolation
+    ///
+    /// # Panics
+    ///
+    /// panics if c0 and c1 have different lengths
+    pub fn exponential(c0: &[f64], c1: &[f64], n: f64) -> Rc<Self> {
+        assert_eq!(c0.len(), c1.len(), "c0 and c1 must have the same length");
+        Rc::new(Self {
+            inputs: 1,
+            outputs: c0.len(),
+            data: Dict::from_vec(vec![
+                ("FunctionType", Rc::new(2usize) as Rc<dyn PDFData>),
+                ("Domain", numbers(&[0f64, 1f64])),
+                ("C0", numbers(c0)),
+                ("C1", numbers(c1)),
+                ("N", Rc::new(n)),
+            ]),
+            objects: vec![],
+        })
+    }
+    /// A stitching function (type 3), which uses a different function
+    /// for each part of its input from 0 to 1
+    ///
+    /// - functions: the function of each part, with one input
+    /// - bounds: where each part ends and the next starts, in
+    ///   increasing order
+    /// - encode: the input range of each function the part is mapped
+    ///   onto, such as `[0 1 0 1 ...]`
+    ///
+    /// # Panics
+    ///
+    /// panics if the functions don't all have one input and the same
+    /// number of outputs, or bounds and encode don't match the number
+    /// of functions
+    pub fn stitching(functions: Vec<Rc<Function>>, bounds: &[f64], encode: &[f64]) -> Rc<Self> {
+        assert!(
+            !functions.is_empty(),
+            "Stitching needs at least one function"
+        );
+        let outputs = functions[0].outputs;
+        assert!(
+            functions
+                .iter()
+                .all(|f| f.inputs == 1 && f.outputs == outputs),
+            "The functions must have one input and the same number of outputs"
+        );
+        assert_eq!(
+            bounds.len(),
+            functions.len() - 1,
+            "There must be one bound between each pair of functions"
+        );
+        assert_eq!(
+            encode.len(),
+            functions.len() * 2,
+            "There must be an encode pair per function"
+        );
+        let objects = functions.iter().flat_map(|f| f.objects()).collect();
+        let functions: Vec<_> = functions.iter().map(|f| f.data()).collect();
+        Rc::new(Self {
+            inputs: 1,
+            outputs,
+            data: Dict::from_vec(vec![
+                ("FunctionType", Rc::new(3usize) as Rc<dyn PDFData>),
+                ("Domain", numbers(&[0f64, 1f64])),
+                ("Functions", Rc::new(functions)),
+                ("Bounds", numbers(bounds)),
+                ("Encode", numbers(encode)),
+            ]),
+            objects,
+        })
+    }
+    /// The number of inputs the function takes
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+    /// The number of outputs the function returns
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+    pub(crate) fn data(&self) -> Rc<dyn PDFData> {
+        self.data.clone()
+    }
+    pub(crate) fn objects(&self) -> Vec<Rc<dyn Object>> {
+        self.objects.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The function as written to a file, with the stream data
+    fn written(function: &Function) -> Vec<u8> {
+        let mut out = vec![];
+        match function.objects().first() {
+            Some(stream) => stream.write_data(&mut out).unwrap(),
+            None => function.data().write(&mut out).unwrap(),
+        }
+        out
+    }
+
+    #[test]
+    fn sampled() {
+        let function = Function::sampled(
+            &[0f64, 1f64],
+            &[0f64, 1f64].repeat(3),
+            &[2],
+            &[1, 2, 3, 4, 5, 6],
+            4,
+        );
+        assert_eq!((function.inputs(), function.outputs()), (1, 3));
+        let file = written(&function);
+        let file = String::from_utf8_lossy(&file);
+        assert!(file.contains("/FunctionType 0\n"));
+        assert!(file.contains("/Size [2]\n/BitsPerSample 4\n/Encode [0 1]\n"));
+        // Two samples of three outputs, packed four bits each
+        assert!(file.contains("/Length 3\n"));
+        assert!(file.ends_with("stream\n\x12\x34\x56\nendstream\n"));
+    }
+
+    #[test]
+    fn stitching() {
+        let segments = vec![
+            Function::exponential(&[0f64], &[0.5], 1f64),
+            Function::exponential(&[0.5], &[0.8], 2f64),
+            Function::exponential(&[0.8], &[1f64], 1f64),
+        ];
+        let function = Function::stitching(segments, &[0.25, 0.75], &[0f64, 1f64].repeat(3));
+        assert_eq!((function.inputs(), function.outputs()), (1, 1));
+        let file = written(&function);
+        let file = String::from_utf8_lossy(&file);
+        assert!(file.starts_with("<<\n/FunctionType 3\n/Domain [0 1]\n"));
+        assert_eq!(file.matches("/FunctionType 2\n").count(), 3);
+        assert!(file.contains("/N 2\n"));
+        assert!(file.ends_with("/Bounds [0.25 0.75]\n/Encode [0 1 0 1 0 1]\n>>\n"));
+    }
+}
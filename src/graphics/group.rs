@@ -0,0 +1,151 @@
+use super::{Graphic, GraphicContext, GraphicParameters, GraphicsContextType, Matrix, Rect};
+use std::rc::Rc;
+
+/// Several graphics drawn as one, such as a shape and its label
+///
+/// The graphics are drawn in the order they are added, inside a single
+/// `q` and `Q`, so a transform applies to all of them.
+#[derive(Debug, Default)]
+pub struct Group {
+    items: Vec<Rc<dyn Graphic>>,
+    matrix: Option<Matrix>,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a graphic to the group, drawn after the graphics already
+    /// in it
+    pub fn with(mut self, graphic: Rc<dyn Graphic>) -> Self {
+        self.items.push(graphic);
+        self
+    }
+    /// A group of copies of the graphic, each drawn with its own transform
+    ///
+    /// - count: the number of copies
+    /// - transform: gives the matrix of each copy, from its index
+    ///
+    /// Such as eight copies rotated around (100, 100):
+    /// `Group::repeat(g, 8, |i| Matrix::translate(-100.0, -100.0)
+    /// .then(&Matrix::rotate(i as f64 * PI / 4.0))
+    /// .then(&Matrix::translate(100.0, 100.0)))`
+    pub fn repeat(
+        graphic: Rc<dyn Graphic>,
+        count: usize,
+        transform: impl Fn(usize) -> Matrix,
+    ) -> Self {
+        let mut group = Self::new();
+        for i in 0..count {
+            group = group.with(
+                Self::new()
+                    .with(graphic.clone())
+                    .transform(transform(i))
+                    .draw(),
+            );
+        }
+        group
+    }
+    /// Applies the matrix to the whole group (`cm`)
+    ///
+    /// - matrix: see Matrix
+    pub fn transform(mut self, matrix: Matrix) -> Self {
+        self.matrix = Some(matrix);
+        self
+    }
+    pub fn draw(self) -> Rc<GraphicGroup> {
+        Rc::new(GraphicGroup {
+            params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+            items: self.items,
+            matrix: self.matrix,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicGroup {
+    params: GraphicParameters,
+    items: Vec<Rc<dyn Graphic>>,
+    matrix: Option<Matrix>,
+}
+
+impl Graphic for GraphicGroup {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn bounds(&self) -> Option<Rect> {
+        let bounds = self
+            .items
+            .iter()
+            .filter_map(|g| g.bounds())
+            .reduce(|a, b| a.union(&b))?;
+        match self.matrix {
+            Some(m) => Rect::enclosing(bounds.corners().iter().map(|p| m.apply(*p))),
+            None => Some(bounds),
+        }
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        out.save_state();
+        if let Some(matrix) = self.matrix {
+            out.command(&mut [matrix.into()], "cm");
+        }
+        for item in self.items.iter() {
+            out.draw(&**item);
+        }
+        out.restore_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+    use crate::graphics::{Color, Font, Path, Text};
+
+    #[test]
+    fn group() {
+        let group = Group::new()
+            .with(
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0f64)),
+            )
+            .with(Rc::new(
+                Text::new(Font::helvetica(), 12f64)
+                    .text("Hi")
+                    .fill(Color::DeviceGray(0f64)),
+            ))
+            .transform(Matrix::translate(5f64, 5f64))
+            .draw();
+        assert_eq!(
+            rendered(group),
+            " q 1 0 0 1 5 5 cm 0 0 10 10 re f BT /helvetica 12 Tf 0 0 Td (Hi) Tj ET Q"
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        let line = Path::from((100f64, 100f64))
+            .line_to((100f64, 150f64))
+            .stroke(Color::DeviceGray(0f64));
+        let copies = Group::repeat(line, 8, |i| {
+            Matrix::translate(-100f64, -100f64)
+                .then(&Matrix::rotate(i as f64 * std::f64::consts::PI / 4f64))
+                .then(&Matrix::translate(100f64, 100f64))
+        })
+        .draw();
+        let content = rendered(copies);
+        let count = |op| content.split(' ').filter(|t| *t == op).count();
+        // The copies, and the group around them
+        assert_eq!((count("q"), count("cm"), count("Q")), (9, 8, 9));
+        // The six operands before each cm
+        let mut matrices: Vec<Vec<&str>> = content
+            .split(" cm ")
+            .take(8)
+            .map(|block| block.split(' ').rev().take(6).collect())
+            .collect();
+        matrices.sort();
+        matrices.dedup();
+        assert_eq!(matrices.len(), 8);
+    }
+}
@@ -0,0 +1,643 @@
+use super::{Graphic, GraphicContext, GraphicParameters, GraphicsContextType, Matrix, Point, Rect};
+use crate::pdf::{
+    flate::{self, Predictor},
+    types::Stream,
+    Dict, Name, ObjRef, PDFData,
+};
+use std::io;
+use std::rc::Rc;
+
+/// How an image is scaled into a target rectangle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FitMode {
+    /// Scale the image to fit entirely inside the rectangle,
+    /// preserving the aspect ratio, and center it
+    Contain,
+    /// Scale the image to cover the entire rectangle, preserving
+    /// the aspect ratio. The parts outside the rectangle are clipped
+    Cover,
+    /// Scale the image to exactly fill the rectangle, ignoring the
+    /// aspect ratio
+    Stretch,
+}
+
+/// An image XObject
+///
+/// The image is only embedded once, no matter how many times
+/// (or on how many pages) it is drawn. Images created separately, but
+/// with the same content, such as a logo loaded for each page, are also
+/// only embedded once.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    /// Maps the unit square onto itself, so the image is displayed upright
+    orientation: Matrix,
+    object: Rc<ObjRef<Stream>>,
+    /// Whether the image is a stencil mask, painted in the fill color
+    stencil: bool,
+    /// The layout of the samples, while they are uncompressed
+    samples: Option<Predictor>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, color_space: &str, data: Vec<u8>) -> Self {
+        let colors = if color_space == "DeviceRGB" { 3 } else { 1 };
+        Self {
+            width,
+            height,
+            orientation: Matrix::identity(),
+            object: ObjRef::new(
+                0,
+                Stream::new(
+                    Dict::from_vec(vec![
+                        ("Type", Name::new("XObject")),
+                        ("Subtype", Name::new("Image")),
+                        ("Width", Rc::new(width)),
+                        ("Height", Rc::new(height)),
+                        ("ColorSpace", Name::new(color_space)),
+                        ("BitsPerComponent", Rc::new(8usize)),
+                    ]),
+                    data,
+                ),
+            ),
+            stencil: false,
+            samples: Some(Predictor {
+                colors,
+                bits: 8,
+                columns: width,
+            }),
+        }
+    }
+    /// Creates an image from raw 8 bit RGB samples
+    ///
+    /// - data: `width * height * 3` bytes, row by row from the top left
+    ///
+    /// # Panics
+    ///
+    /// panics if data is not the correct length
+    pub fn from_rgb(width: usize, height: usize, data: Vec<u8>) -> Self {
+        assert_eq!(data.len(), width * height * 3, "Incorrect image data size");
+        Self::new(width, height, "DeviceRGB", data)
+    }
+    /// Creates an image from raw 8 bit grayscale samples
+    ///
+    /// - data: `width * height` bytes, row by row from the top left
+    ///
+    /// # Panics
+    ///
+    /// panics if data is not the correct length
+    pub fn from_gray(width: usize, height: usize, data: Vec<u8>) -> Self {
+        assert_eq!(data.len(), width * height, "Incorrect image data size");
+        Self::new(width, height, "DeviceGray", data)
+    }
+    /// Creates a stencil mask (`/ImageMask`), which paints the fill color
+    /// through the mask, and leaves the rest untouched
+    ///
+    /// - bits: 1 bit per pixel, row by row from the top left, with each
+    ///   row padded to a whole byte. Pixels that are 0 are painted, see
+    ///   decode to paint the 1s instead
+    ///
+    /// The fill color is black, unless it is set on the drawn image
+    /// with set_fill_color.
+    ///
+    /// # Panics
+    ///
+    /// panics if bits is not the correct length
+    pub fn stencil(width: usize, height: usize, bits: Vec<u8>) -> Self {
+        assert_eq!(
+            bits.len(),
+            width.div_ceil(8) * height,
+            "Incorrect image data size"
+        );
+        Self {
+            width,
+            height,
+            orientation: Matrix::identity(),
+            object: ObjRef::new(
+                0,
+                Stream::new(
+                    Dict::from_vec(vec![
+                        ("Type", Name::new("XObject") as Rc<dyn PDFData>),
+                        ("Subtype", Name::new("Image")),
+                        ("Width", Rc::new(width)),
+                        ("Height", Rc::new(height)),
+                        ("ImageMask", Rc::new(true)),
+                        ("BitsPerComponent", Rc::new(1usize)),
+                    ]),
+                    bits,
+                ),
+            ),
+            stencil: true,
+            samples: Some(Predictor {
+                colors: 1,
+                bits: 1,
+                columns: width,
+            }),
+        }
+    }
+    /// Embeds a baseline or progressive JPEG file
+    ///
+    /// - data: the complete contents of the JPEG file
+    ///
+    /// The data is embedded as is, using the DCTDecode filter. If the
+    /// file has an EXIF orientation tag, drawing the image will rotate
+    /// or flip it so it is displayed upright, and width and height
+    /// refer to the upright image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an InvalidData error if the file is not a JPEG, or the frame
+    /// header could not be found
+    pub fn from_jpeg(data: Vec<u8>) -> io::Result<Self> {
+        let info = jpeg::parse(&data)?;
+        let color_space = match info.components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            4 => "DeviceCMYK",
+            _ => return Err(jpeg::invalid("Unsupported number of components")),
+        };
+        let meta = Dict::from_vec(vec![
+            ("Type", Name::new("XObject")),
+            ("Subtype", Name::new("Image")),
+            ("Width", Rc::new(info.width)),
+            ("Height", Rc::new(info.height)),
+            ("ColorSpace", Name::new(color_space)),
+            ("BitsPerComponent", Rc::new(info.bits)),
+            ("Filter", Name::new("DCTDecode")),
+        ]);
+        let (width, height) = if info.orientation >= 5 {
+            (info.height, info.width)
+        } else {
+            (info.width, info.height)
+        };
+        Ok(Self {
+            width,
+            height,
+            orientation: jpeg::orientation_matrix(info.orientation),
+            object: ObjRef::new(0, Stream::new(meta, data)),
+            stencil: false,
+            samples: None,
+        })
+    }
+    /// Attaches an XMP metadata stream to the image (`/Metadata`)
+    ///
+    /// - xmp: the XMP packet, as UTF-8 encoded XML
+    pub fn with_metadata(self, xmp: Vec<u8>) -> Self {
+        let metadata = super::metadata_stream(xmp);
+        self.object.add_entry("Metadata", metadata.clone());
+        self.object.add_dependent(metadata);
+        self
+    }
+    /// Sets whether viewers smooth the image when it is scaled up
+    /// (`/Interpolate`)
+    pub fn interpolate(self, interpolate: bool) -> Self {
+        self.object.add_entry("Interpolate", Rc::new(interpolate));
+        self
+    }
+    /// Compresses the samples with the FlateDecode filter, using the PNG
+    /// predictors, which suits photos as well as flat artwork
+    ///
+    /// JPEG images are already compressed, and are left as they are.
+    /// Only drawings of the image made after this call use the
+    /// compressed data. Uses level 6, see compress_level
+    pub fn compress(self) -> Self {
+        self.compress_level(flate::DEFAULT_LEVEL)
+    }
+    /// Compresses the samples, see compress
+    ///
+    /// - level: from 0 (no compression) to 9 (smallest output). Lower
+    ///   levels are faster, higher levels search further for repeated
+    ///   data
+    ///
+    /// # Panics
+    ///
+    /// panics if the level is above 9
+    pub fn compress_level(mut self, level: u32) -> Self {
+        assert!(level <= 9, "The compression level must be from 0 to 9");
+        if let Some(predictor) = self.samples.take() {
+            let samples: &Stream = &self.object;
+            let stream = Stream::flate(samples.meta(), samples.data(), Some(predictor), level);
+            for obj in samples.dependent_objects() {
+                stream.add_dependent(obj);
+            }
+            self.object = ObjRef::new(0, stream);
+        }
+        self
+    }
+    /// Maps the samples onto the color components (`/Decode`)
+    ///
+    /// - array: a min and max for each color component, such as
+    ///   `[1 0]` to invert a grayscale image or mask
+    ///
+    /// # Panics
+    ///
+    /// panics if array isn't a list of pairs
+    pub fn decode(self, array: Vec<f64>) -> Self {
+        assert!(
+            !array.is_empty() && array.len().is_multiple_of(2),
+            "The decode array must be pairs of min and max values"
+        );
+        let array: Vec<_> = array.into_iter().map(Rc::new).collect();
+        self.object.add_entry("Decode", Rc::new(array));
+        self
+    }
+    /// Makes the pixels with colors in the ranges transparent (`/Mask`)
+    ///
+    /// - ranges: the min and max sample value of each color component,
+    ///   such as `[(255, 255), (0, 0), (255, 255)]` for magenta in an
+    ///   RGB image. A pixel is transparent if every component is in its
+    ///   range
+    ///
+    /// # Panics
+    ///
+    /// panics if the image is a stencil mask, see stencil
+    pub fn color_key_mask(self, ranges: Vec<(u8, u8)>) -> Self {
+        assert!(!self.stencil, "Stencil masks can't have a mask");
+        let array: Vec<_> = ranges
+            .into_iter()
+            .flat_map(|(min, max)| [Rc::new(min as usize), Rc::new(max as usize)])
+            .collect();
+        self.object.add_entry("Mask", Rc::new(array));
+        self
+    }
+    /// Width of the image, in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Height of the image, in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Draws the image using the given matrix
+    ///
+    /// - matrix: see Matrix
+    ///
+    /// Images are drawn into the unit square, so the matrix has
+    /// to scale the image to the desired size. The image's orientation
+    /// is applied before the matrix.
+    pub fn draw_matrix(&self, matrix: Matrix) -> Rc<GraphicImage> {
+        Rc::new(GraphicImage {
+            params: self.params(),
+            image: self.clone(),
+            matrix: self.orientation.then(&matrix),
+            clip: None,
+        })
+    }
+    /// Draws the image, stretched to fill the rectangle
+    ///
+    /// - rect: See Rect
+    pub fn draw(&self, rect: impl Into<Rect>) -> Rc<GraphicImage> {
+        self.draw_fit(rect, FitMode::Stretch)
+    }
+    /// Draws the image at its physical size, for the resolution it was
+    /// made for
+    ///
+    /// - origin: the bottom left corner of the image, see Point
+    /// - dpi: the pixels per inch, so each pixel is `72 / dpi` points
+    ///
+    /// # Panics
+    ///
+    /// panics if dpi isn't positive
+    pub fn draw_at_dpi(&self, origin: impl Into<Point>, dpi: f64) -> Rc<GraphicImage> {
+        assert!(dpi > 0f64, "The DPI of an image must be positive");
+        let origin = origin.into();
+        let scale = 72f64 / dpi;
+        self.draw(Rect::new(
+            origin.x(),
+            origin.y(),
+            self.width as f64 * scale,
+            self.height as f64 * scale,
+        ))
+    }
+    /// Draws the image into the rectangle, scaled according to fit
+    ///
+    /// - rect: See Rect
+    /// - fit: See FitMode
+    pub fn draw_fit(&self, rect: impl Into<Rect>, fit: FitMode) -> Rc<GraphicImage> {
+        let rect = rect.into();
+        let matrix = self.fit_matrix(rect, fit);
+        Rc::new(GraphicImage {
+            params: self.params(),
+            image: self.clone(),
+            matrix,
+            clip: if fit == FitMode::Cover {
+                Some(rect)
+            } else {
+                None
+            },
+        })
+    }
+    /// The parameters the image is drawn with: only stencil masks use
+    /// the colors
+    fn params(&self) -> GraphicParameters {
+        if self.stencil {
+            GraphicParameters::default()
+        } else {
+            GraphicParameters::with_type(GraphicsContextType::NoColor)
+        }
+    }
+    /// Computes the `cm` matrix used to draw this image into rect
+    pub fn fit_matrix(&self, rect: Rect, fit: FitMode) -> Matrix {
+        let (w, h) = (self.width as f64, self.height as f64);
+        let (sx, sy) = match fit {
+            FitMode::Stretch => (rect.width(), rect.height()),
+            FitMode::Contain => {
+                let s = (rect.width() / w).min(rect.height() / h);
+                (w * s, h * s)
+            }
+            FitMode::Cover => {
+                let s = (rect.width() / w).max(rect.height() / h);
+                (w * s, h * s)
+            }
+        };
+        self.orientation.then(&Matrix::new(
+            sx,
+            0f64,
+            0f64,
+            sy,
+            rect.x() + (rect.width() - sx) / 2f64,
+            rect.y() + (rect.height() - sy) / 2f64,
+        ))
+    }
+}
+
+mod jpeg {
+    use super::Matrix;
+    use std::io;
+
+    pub struct JpegInfo {
+        pub width: usize,
+        pub height: usize,
+        pub bits: usize,
+        pub components: usize,
+        pub orientation: u16,
+    }
+
+    pub fn invalid(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    fn u16_be(data: &[u8], i: usize) -> Option<usize> {
+        Some(((*data.get(i)? as usize) << 8) | *data.get(i + 1)? as usize)
+    }
+
+    /// Reads the frame header, and the EXIF orientation if present
+    pub fn parse(data: &[u8]) -> io::Result<JpegInfo> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Err(invalid("Not a JPEG file"));
+        }
+        let mut orientation = 1;
+        let mut i = 2;
+        while i + 4 <= data.len() {
+            if data[i] != 0xFF {
+                return Err(invalid("Malformed JPEG marker"));
+            }
+            let marker = data[i + 1];
+            if marker == 0xFF {
+                // Fill byte
+                i += 1;
+                continue;
+            }
+            let len = u16_be(data, i + 2).ok_or_else(|| invalid("Truncated JPEG"))?;
+            let segment = data
+                .get(i + 4..i + 2 + len)
+                .ok_or_else(|| invalid("Truncated JPEG"))?;
+            match marker {
+                0xE1 if segment.starts_with(b"Exif\0\0") => {
+                    if let Some(o) = exif_orientation(&segment[6..]) {
+                        orientation = o;
+                    }
+                }
+                0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                    if segment.len() < 6 {
+                        return Err(invalid("Truncated JPEG frame header"));
+                    }
+                    return Ok(JpegInfo {
+                        bits: segment[0] as usize,
+                        height: u16_be(segment, 1).unwrap(),
+                        width: u16_be(segment, 3).unwrap(),
+                        components: segment[5] as usize,
+                        orientation,
+                    });
+                }
+                // Start of scan, the frame header must come before this
+                0xDA => break,
+                _ => (),
+            }
+            i += 2 + len;
+        }
+        Err(invalid("No JPEG frame header"))
+    }
+
+    /// Finds the orientation tag (0x0112) in the first IFD of the TIFF
+    /// structure embedded in an EXIF segment
+    fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+        let big = match tiff.get(0..2)? {
+            b"MM" => true,
+            b"II" => false,
+            _ => return None,
+        };
+        let read16 = |i: usize| -> Option<u16> {
+            let b = [*tiff.get(i)?, *tiff.get(i + 1)?];
+            Some(if big {
+                u16::from_be_bytes(b)
+            } else {
+                u16::from_le_bytes(b)
+            })
+        };
+        let read32 = |i: usize| -> Option<u32> {
+            let b = [
+                *tiff.get(i)?,
+                *tiff.get(i + 1)?,
+                *tiff.get(i + 2)?,
+                *tiff.get(i + 3)?,
+            ];
+            Some(if big {
+                u32::from_be_bytes(b)
+            } else {
+                u32::from_le_bytes(b)
+            })
+        };
+        let ifd = read32(4)? as usize;
+        let count = read16(ifd)? as usize;
+        for n in 0..count {
+            let entry = ifd + 2 + n * 12;
+            if read16(entry)? == 0x0112 {
+                let value = read16(entry + 8)?;
+                return if (1..=8).contains(&value) {
+                    Some(value)
+                } else {
+                    None
+                };
+            }
+        }
+        None
+    }
+
+    /// The matrix that maps the stored image onto the upright image,
+    /// both in the unit square
+    pub fn orientation_matrix(orientation: u16) -> Matrix {
+        let (a, b, c, d, e, f) = match orientation {
+            // Mirrored horizontally
+            2 => (-1, 0, 0, 1, 1, 0),
+            // Rotated 180
+            3 => (-1, 0, 0, -1, 1, 1),
+            // Mirrored vertically
+            4 => (1, 0, 0, -1, 0, 1),
+            // Transposed
+            5 => (0, -1, -1, 0, 1, 1),
+            // Rotated 90 clockwise
+            6 => (0, -1, 1, 0, 0, 1),
+            // Transversed
+            7 => (0, 1, 1, 0, 0, 0),
+            // Rotated 90 counter-clockwise
+            8 => (0, 1, -1, 0, 1, 0),
+            _ => (1, 0, 0, 1, 0, 0),
+        };
+        Matrix::new(a as f64, b as f64, c as f64, d as f64, e as f64, f as f64)
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicImage {
+    params: GraphicParameters,
+    image: Image,
+    matrix: Matrix,
+    clip: Option<Rect>,
+}
+
+impl Graphic for GraphicImage {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn bounds(&self) -> Option<Rect> {
+        let drawn = Rect::enclosing(
+            Rect::new(0f64, 0f64, 1f64, 1f64)
+                .corners()
+                .iter()
+                .map(|p| self.matrix.apply(*p)),
+        );
+        self.clip.or(drawn)
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        let name = out.add_xobject(self.image.object.clone());
+        out.command(&mut [], "q");
+        if let Some(clip) = self.clip {
+            out.command(&mut [clip.into()], "re");
+            out.command(&mut [], "W");
+            out.command(&mut [], "n");
+        }
+        let matrix = if out.flipped {
+            // Flips the unit square, so the image stays upright
+            Matrix::new(1f64, 0f64, 0f64, -1f64, 0f64, 1f64).then(&self.matrix)
+        } else {
+            self.matrix
+        };
+        out.command(&mut [matrix.into()], "cm");
+        out.command(&mut [name.into()], "Do");
+        out.command(&mut [], "Q");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn fit_contain() {
+        let image = Image::from_gray(2, 1, vec![0, 0]);
+        let matrix = image.fit_matrix(Rect::new(0f64, 0f64, 100f64, 100f64), FitMode::Contain);
+        // Scaled to the width, and centered vertically
+        assert_eq!(matrix, Matrix::new(100f64, 0f64, 0f64, 50f64, 0f64, 25f64));
+    }
+
+    /// The markers of a JPEG file up to its frame header, for a 2x1
+    /// grayscale image with the EXIF orientation
+    fn jpeg(orientation: u8) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1, 0, 34];
+        data.extend_from_slice(b"Exif\0\0II\x2A\0\x08\0\0\0\x01\0");
+        data.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, orientation, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&[0xFF, 0xC0, 0, 8, 8, 0, 1, 0, 2, 1]);
+        data
+    }
+
+    #[test]
+    fn jpeg_rotated_90() {
+        let image = Image::from_jpeg(jpeg(6)).unwrap();
+        assert_eq!((image.width(), image.height()), (1, 2));
+        let matrix = image.fit_matrix(Rect::new(0f64, 0f64, 10f64, 20f64), FitMode::Stretch);
+        // The top left of the stored image is drawn at the top right
+        assert_eq!(matrix.apply((0f64, 1f64)), Point::new(10f64, 20f64));
+        assert_eq!(matrix, Matrix::new(0f64, -20f64, 10f64, 0f64, 0f64, 20f64));
+    }
+
+    /// The file with the image drawn on a page
+    fn written_image(image: Image) -> String {
+        text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(image.draw(Rect::new(0f64, 0f64, 10f64, 10f64)));
+            pdf.add_page(page);
+        }))
+    }
+
+    /// The dictionary of the image stream in the file
+    fn image_dict(file: &str) -> &str {
+        let start = file[..file.find("/Subtype /Image").unwrap()]
+            .rfind("<<")
+            .unwrap();
+        &file[start..start + file[start..].find("stream").unwrap()]
+    }
+
+    #[test]
+    fn metadata() {
+        let file =
+            written_image(Image::from_gray(1, 1, vec![0]).with_metadata(b"<x:xmpmeta/>".to_vec()));
+        // The image refers to the metadata stream
+        let reference = image_dict(&file).split("/Metadata ").nth(1).unwrap();
+        let num = reference.split(' ').next().unwrap();
+        assert!(reference.starts_with(&format!("{} 0 R\n", num)));
+        assert!(file.contains(&format!(
+            "{} 0 obj\n<<\n/Type /Metadata\n/Subtype /XML",
+            num
+        )));
+    }
+
+    #[test]
+    fn interpolate() {
+        let file = written_image(Image::from_gray(1, 1, vec![0]).interpolate(true));
+        assert!(image_dict(&file).contains("/Interpolate true\n"));
+    }
+
+    #[test]
+    fn decode() {
+        let file = written_image(Image::from_gray(1, 1, vec![0]).decode(vec![1f64, 0f64]));
+        assert!(image_dict(&file).contains("/Decode [1 0]\n"));
+    }
+
+    #[test]
+    fn stencil() {
+        let file = written_image(Image::stencil(8, 1, vec![0b1010_1010]));
+        let dict = image_dict(&file);
+        assert!(dict.contains("/ImageMask true\n"));
+        assert!(!dict.contains("/ColorSpace"));
+    }
+
+    #[test]
+    fn color_key_mask() {
+        let file = written_image(Image::from_gray(1, 1, vec![0]).color_key_mask(vec![(0, 16)]));
+        assert!(image_dict(&file).contains("/Mask [0 16]\n"));
+    }
+
+    #[test]
+    fn draw_at_dpi() {
+        let image = Image::from_gray(300, 150, vec![0; 300 * 150]);
+        let placed = image.draw_at_dpi((10f64, 20f64), 300f64);
+        assert_eq!(placed.bounds(), Some(Rect::new(10f64, 20f64, 72f64, 36f64)));
+        assert_eq!(rendered(placed), " q 72 0 0 36 10 20 cm /Im1 Do Q");
+    }
+}
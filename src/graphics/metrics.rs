@@ -0,0 +1,273 @@
+//! Glyph widths for the 14 standard fonts
+//!
+//! Widths are taken from Adobe's AFM files for the standard fonts, in
+//! thousandths of the font size, for the character codes 32 to 126 in
+//! WinAnsiEncoding for the text fonts, and the built-in encoding of
+//! Symbol and ZapfDingbats.
+
+/// The characters a font has glyphs for
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Charset {
+    /// Latin text
+    Latin,
+    /// Greek letters and mathematical symbols
+    Symbol,
+    /// Dingbats, which aren't mapped to Unicode
+    Dingbats,
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    widths: [u16; 95],
+    charset: Charset,
+}
+
+impl Metrics {
+    /// The character code for c in the font's built-in encoding, as
+    /// a character, if the font has a glyph for it
+    ///
+    /// Latin fonts map the characters of WinAnsiEncoding, and
+    /// ZapfDingbats only maps space
+    pub fn encode(&self, c: char) -> Option<char> {
+        match self.charset {
+            Charset::Latin => win_ansi_encode(c),
+            Charset::Symbol => symbol(c),
+            Charset::Dingbats => Some(c).filter(|c| *c == ' '),
+        }
+    }
+    /// Width of a character, in thousandths of the font size
+    ///
+    /// Accented letters use the width of their base letter,
+    /// and other characters without metrics use the width of `n`.
+    pub fn width(&self, c: char) -> u16 {
+        let c = base_char(self.encode(c).unwrap_or(c));
+        if (' '..='~').contains(&c) {
+            self.widths[c as usize - 32]
+        } else {
+            self.widths['n' as usize - 32]
+        }
+    }
+}
+
+/// The characters WinAnsiEncoding adds to Latin-1, at codes 128 to 159
+const WIN_ANSI: [(char, u8); 27] = [
+    ('\u{20AC}', 0x80),
+    ('\u{201A}', 0x82),
+    ('\u{0192}', 0x83),
+    ('\u{201E}', 0x84),
+    ('\u{2026}', 0x85),
+    ('\u{2020}', 0x86),
+    ('\u{2021}', 0x87),
+    ('\u{02C6}', 0x88),
+    ('\u{2030}', 0x89),
+    ('\u{0160}', 0x8A),
+    ('\u{2039}', 0x8B),
+    ('\u{0152}', 0x8C),
+    ('\u{017D}', 0x8E),
+    ('\u{2018}', 0x91),
+    ('\u{2019}', 0x92),
+    ('\u{201C}', 0x93),
+    ('\u{201D}', 0x94),
+    ('\u{2022}', 0x95),
+    ('\u{2013}', 0x96),
+    ('\u{2014}', 0x97),
+    ('\u{02DC}', 0x98),
+    ('\u{2122}', 0x99),
+    ('\u{0161}', 0x9A),
+    ('\u{203A}', 0x9B),
+    ('\u{0153}', 0x9C),
+    ('\u{017E}', 0x9E),
+    ('\u{0178}', 0x9F),
+];
+
+/// The WinAnsiEncoding code for c, as a character
+pub fn win_ansi_encode(c: char) -> Option<char> {
+    match c {
+        ' '..='~' | '\u{00A0}'..='\u{00FF}' => Some(c),
+        _ => WIN_ANSI
+            .iter()
+            .find(|(u, _)| *u == c)
+            .map(|(_, code)| *code as char),
+    }
+}
+
+/// The character of a WinAnsiEncoding code, see win_ansi_encode
+pub fn win_ansi_decode(code: u8) -> Option<char> {
+    match code {
+        b' '..=b'~' | 0xA0..=0xFF => Some(code as char),
+        _ => WIN_ANSI.iter().find(|(_, c)| *c == code).map(|(u, _)| *u),
+    }
+}
+
+/// Maps Unicode to the Symbol font's encoding
+fn symbol(c: char) -> Option<char> {
+    const GREEK: &str = "ΑΒΧΔΕΦΓΗΙϑΚΛΜΝΟΠΘΡΣΤΥςΩΞΨΖ";
+    const GREEK_LOWER: &str = "αβχδεφγηιϕκλμνοπθρστυϖωξψζ";
+    if let Some(i) = GREEK.chars().position(|g| g == c) {
+        return Some((b'A' + i as u8) as char);
+    }
+    if let Some(i) = GREEK_LOWER.chars().position(|g| g == c) {
+        return Some((b'a' + i as u8) as char);
+    }
+    match c {
+        ' ' | '!' | '#' | '%' | '&' | '(' | ')' | '+' | ',' | '.' | '/' => Some(c),
+        '0'..='9' | ':' | ';' | '<' | '=' | '>' | '?' | '[' | ']' | '_' => Some(c),
+        '{' | '|' | '}' => Some(c),
+        '\u{2200}' => Some('"'),
+        '\u{2203}' => Some('$'),
+        '\u{220B}' => Some('\''),
+        '\u{2217}' => Some('*'),
+        '\u{2212}' => Some('-'),
+        '\u{2245}' => Some('@'),
+        '\u{2234}' => Some('\\'),
+        '\u{22A5}' => Some('^'),
+        '\u{223C}' => Some('~'),
+        _ => None,
+    }
+}
+
+/// Maps accented letters, as WinAnsi codes, to their unaccented letter
+fn base_char(c: char) -> char {
+    match c {
+        '\u{008A}' => 'S',
+        '\u{008E}' => 'Z',
+        '\u{009A}' => 's',
+        '\u{009E}' => 'z',
+        '\u{009F}' => 'Y',
+        '\u{00C0}'..='\u{00C5}' => 'A',
+        '\u{00C7}' => 'C',
+        '\u{00C8}'..='\u{00CB}' => 'E',
+        '\u{00CC}'..='\u{00CF}' => 'I',
+        '\u{00D1}' => 'N',
+        '\u{00D2}'..='\u{00D6}' | '\u{00D8}' => 'O',
+        '\u{00D9}'..='\u{00DC}' => 'U',
+        '\u{00DD}' => 'Y',
+        '\u{00E0}'..='\u{00E5}' => 'a',
+        '\u{00E7}' => 'c',
+        '\u{00E8}'..='\u{00EB}' => 'e',
+        '\u{00EC}'..='\u{00EF}' => 'i',
+        '\u{00F1}' => 'n',
+        '\u{00F2}'..='\u{00F6}' | '\u{00F8}' => 'o',
+        '\u{00F9}'..='\u{00FC}' => 'u',
+        '\u{00FD}' | '\u{00FF}' => 'y',
+        '\u{00A0}' => ' ',
+        c => c,
+    }
+}
+
+/// Helvetica and Helvetica-Oblique
+pub static HELVETICA: Metrics = Metrics {
+    widths: [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Helvetica-Bold and Helvetica-BoldOblique
+pub static HELVETICA_BOLD: Metrics = Metrics {
+    widths: [
+        278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722,
+        722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611,
+        611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556,
+        500, 389, 280, 389, 584,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Times-Roman
+pub static TIMES_ROMAN: Metrics = Metrics {
+    widths: [
+        250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, 921, 722, 667, 667,
+        722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, 556, 722, 667, 556, 611, 722,
+        722, 944, 722, 722, 611, 333, 278, 333, 469, 500, 333, 444, 500, 444, 500, 444, 333, 500,
+        500, 278, 278, 500, 278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500,
+        444, 480, 200, 480, 541,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Times-Bold
+pub static TIMES_BOLD: Metrics = Metrics {
+    widths: [
+        250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 930, 722, 667, 722,
+        722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778, 611, 778, 722, 556, 667, 722,
+        722, 1000, 722, 722, 667, 333, 278, 333, 581, 500, 333, 500, 556, 444, 556, 444, 333, 500,
+        556, 278, 333, 556, 278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500,
+        444, 394, 220, 394, 520,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Times-Italic
+pub static TIMES_ITALIC: Metrics = Metrics {
+    widths: [
+        250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500, 920, 611, 611, 667,
+        722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722, 611, 722, 611, 500, 556, 722,
+        611, 833, 611, 556, 556, 389, 278, 389, 422, 500, 333, 500, 500, 444, 500, 444, 278, 500,
+        500, 278, 278, 444, 278, 722, 500, 500, 500, 500, 389, 389, 278, 500, 444, 667, 444, 444,
+        389, 400, 275, 400, 541,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Times-BoldItalic
+pub static TIMES_BOLD_ITALIC: Metrics = Metrics {
+    widths: [
+        250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 832, 667, 667, 667,
+        722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722, 611, 722, 667, 556, 611, 722,
+        667, 889, 667, 611, 611, 333, 278, 333, 570, 500, 333, 500, 500, 444, 500, 444, 333, 500,
+        556, 278, 278, 500, 278, 778, 556, 500, 500, 500, 389, 389, 278, 556, 444, 667, 500, 444,
+        389, 348, 220, 348, 570,
+    ],
+    charset: Charset::Latin,
+};
+
+/// All four Courier fonts
+pub static COURIER: Metrics = Metrics {
+    widths: [
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600,
+    ],
+    charset: Charset::Latin,
+};
+
+/// Symbol
+pub static SYMBOL: Metrics = Metrics {
+    widths: [
+        250, 333, 713, 500, 549, 833, 778, 439, 333, 333, 500, 549, 250, 549, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 549, 549, 549, 444, 549, 722, 667, 722,
+        612, 611, 763, 603, 722, 333, 631, 722, 686, 889, 722, 722, 768, 741, 556, 592, 611, 690,
+        439, 768, 645, 795, 611, 333, 863, 333, 658, 500, 500, 631, 549, 549, 494, 439, 521, 411,
+        603, 329, 603, 549, 549, 576, 521, 549, 549, 521, 549, 603, 439, 576, 713, 686, 493, 686,
+        494, 480, 200, 480, 549,
+    ],
+    charset: Charset::Symbol,
+};
+
+/// ZapfDingbats
+pub static ZAPF_DINGBATS: Metrics = Metrics {
+    widths: [
+        278, 974, 961, 974, 980, 719, 789, 790, 791, 690, 960, 939, 549, 855, 911, 933, 911, 945,
+        974, 755, 846, 762, 761, 571, 677, 763, 760, 759, 754, 494, 552, 537, 577, 692, 786, 788,
+        788, 790, 793, 794, 816, 823, 789, 841, 823, 833, 816, 831, 923, 744, 723, 749, 790, 792,
+        695, 776, 768, 792, 759, 707, 708, 682, 701, 826, 815, 789, 789, 707, 687, 696, 689, 786,
+        787, 713, 791, 785, 791, 873, 761, 762, 762, 759, 759, 892, 892, 788, 784, 438, 138, 277,
+        415, 392, 392, 668, 668,
+    ],
+    charset: Charset::Dingbats,
+};
@@ -1,13 +1,66 @@
-use crate::pdf::{Dict, Name, Object};
+use crate::pdf::{types::Stream, Dict, Name, ObjRef, Object, PDFData, PDFWrite, Raw};
 use std::collections::LinkedList;
 use std::rc::Rc;
 pub mod path;
-pub use path::Path;
+pub use path::{Path, Winding};
 pub mod text;
-pub use text::{Font, Text};
+pub use text::{BaseEncoding, EncodingError, Font, FontEncoding, Text};
+pub mod bidi;
+pub mod image;
+pub use image::{FitMode, Image};
+pub mod colorspace;
+pub use colorspace::ColorSpace;
+pub mod function;
+pub use function::Function;
+pub mod extgstate;
+pub use extgstate::{ExtGState, SoftMaskType};
+pub mod vision;
+pub use vision::ColorVision;
+pub mod stamp;
+pub use stamp::Stamp;
+pub mod group;
+pub use group::Group;
+pub mod chart;
+pub use chart::{BarChart, LineChart};
+pub mod flow;
+pub use flow::{Hyphenation, TextFlow};
+pub mod svg;
+pub use svg::Svg;
 pub mod context;
+mod metrics;
+mod truetype;
 use context::GraphicParameters;
-pub use context::{Color, Graphic, GraphicsContextType, Point, Rect};
+pub use context::{
+    Anchor, Color, Graphic, GraphicsContextType, Matrix, Offset, Point, Position, Rect,
+};
+
+/// The content streams and resource dict of a compiled GraphicContext
+pub type Compiled = (Vec<Rc<ObjRef<Stream>>>, Rc<Dict>);
+
+/// The reason a GraphicContext couldn't be compiled
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// Fonts selected by the content, that aren't in the resources
+    MissingFonts(Vec<String>),
+    /// Characters of text that were left out, since they aren't in the
+    /// encoding of any of its fonts, see Text::text
+    UnencodableText(Vec<char>),
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingFonts(names) => {
+                write!(f, "fonts missing from the resources: {}", names.join(", "))
+            }
+            Self::UnencodableText(chars) => {
+                write!(f, "{}", text::EncodingError(chars.clone()))
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
 
 #[derive(Debug)]
 pub struct GraphicContext {
@@ -19,8 +72,58 @@ pub struct GraphicContext {
     // Resource Dict
     resources: Rc<Dict>,
     fonts: Rc<Dict>,
+    xobjects: Rc<Dict>,
+    xobject_names: Vec<(Rc<ObjRef<Stream>>, Rc<Name>)>,
+    color_spaces: Rc<Dict>,
+    color_space_names: Vec<(Rc<ColorSpace>, Rc<Name>)>,
+    ext_gstates: Rc<Dict>,
+    ext_gstate_names: Vec<(Rc<ExtGState>, Rc<Name>)>,
+    /// Resource categories without their own field, such as `/Properties`
+    other_resources: Vec<(Name, Rc<Dict>)>,
+    /// Fill color and position of each piece of text
+    text_colors: Vec<(Color, Point)>,
+    /// The union of the bounds of the rendered graphics
+    bounds: Option<Rect>,
+    /// Whether the y-axis points down, see set_top_left
+    flipped: bool,
+    /// Characters left out of the text rendered, see Text::text
+    unencodable: Vec<char>,
     external_resources: Vec<Rc<dyn Object>>,
 }
+impl Clone for GraphicContext {
+    /// Copies the content and resources, so the copy can be rendered
+    /// to and compiled separately
+    fn clone(&self) -> Self {
+        Self {
+            current: Rc::new((*self.current).clone()),
+            stack: self.stack.clone(),
+            stream: self.stream.clone(),
+            resources: self.resources.copy(),
+            fonts: self.fonts.copy(),
+            xobjects: self.xobjects.copy(),
+            xobject_names: self.xobject_names.clone(),
+            color_spaces: self.color_spaces.copy(),
+            color_space_names: self.color_space_names.clone(),
+            ext_gstates: self.ext_gstates.copy(),
+            ext_gstate_names: self.ext_gstate_names.clone(),
+            other_resources: self
+                .other_resources
+                .iter()
+                .map(|(n, d)| (n.clone(), d.copy()))
+                .collect(),
+            text_colors: self.text_colors.clone(),
+            bounds: self.bounds,
+            flipped: self.flipped,
+            unencodable: self.unencodable.clone(),
+            external_resources: self.external_resources.clone(),
+        }
+    }
+}
+impl Default for GraphicContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl GraphicContext {
     pub fn new() -> Self {
         Self {
@@ -32,6 +135,17 @@ impl GraphicContext {
                 Rc::new(vec![Name::new("PDF"), Name::new("Text")]),
             )]),
             fonts: Dict::new(),
+            xobjects: Dict::new(),
+            xobject_names: vec![],
+            color_spaces: Dict::new(),
+            color_space_names: vec![],
+            ext_gstates: Dict::new(),
+            ext_gstate_names: vec![],
+            other_resources: vec![],
+            text_colors: vec![],
+            bounds: None,
+            flipped: false,
+            unencodable: vec![],
             external_resources: vec![],
         }
     }
@@ -45,55 +159,524 @@ impl GraphicContext {
                 Rc::new(vec![Name::new("PDF"), Name::new("Text")]),
             )]),
             fonts: Dict::new(),
+            xobjects: Dict::new(),
+            xobject_names: vec![],
+            color_spaces: Dict::new(),
+            color_space_names: vec![],
+            ext_gstates: Dict::new(),
+            ext_gstate_names: vec![],
+            other_resources: vec![],
+            text_colors: vec![],
+            bounds: None,
+            flipped: false,
+            unencodable: vec![],
             external_resources: vec![],
         }
     }
-    pub fn render(&mut self, object: Rc<impl Graphic>) {
+    pub fn render(&mut self, object: Rc<impl Graphic + ?Sized>) {
+        self.draw(&*object);
+        if let Some(bounds) = object.bounds() {
+            self.bounds = Some(match self.bounds {
+                Some(b) => b.union(&bounds),
+                None => bounds,
+            });
+        }
+    }
+    /// Renders the object, without adding it to the content bounds
+    fn draw(&mut self, object: &(impl Graphic + ?Sized)) {
         // Check Colors, and update as needed
         GraphicParameters::update(self, object.get_graphics_parameters());
         // Render object
         object.render(self);
     }
+    /// Renders the object before everything that has already been
+    /// rendered
+    ///
+    /// The object is wrapped in `q` and `Q`, so the rest of the content
+    /// still starts from the state it was rendered with.
+    pub fn render_first(&mut self, object: Rc<impl Graphic + ?Sized>) {
+        let rest = std::mem::take(&mut self.stream);
+        // The object is rendered from the state at the start of the stream
+        let initial = Rc::new(self.current.initial());
+        let after = std::mem::replace(&mut self.current, initial);
+        self.save_state();
+        self.render(object);
+        self.restore_state();
+        self.current = after;
+        self.stream.extend(rest);
+    }
+    /// Removes everything rendered, and the resources it uses, so the
+    /// context can be reused
+    ///
+    /// The buffers keep their allocated space, so content of a similar
+    /// size can be rendered again without reallocating. The top left mode
+    /// of set_top_left is kept. A compiled context can't be reused, since
+    /// the writer keeps its stream and resources until the document is
+    /// written.
+    pub fn reset(&mut self) {
+        self.current = Rc::new(self.current.initial());
+        self.stack.clear();
+        self.stream.clear();
+        self.resources.clear();
+        self.resources.add_entry(
+            "ProcSet",
+            Rc::new(vec![Name::new("PDF"), Name::new("Text")]),
+        );
+        for dict in [
+            &self.fonts,
+            &self.xobjects,
+            &self.color_spaces,
+            &self.ext_gstates,
+        ] {
+            dict.clear();
+        }
+        self.xobject_names.clear();
+        self.color_space_names.clear();
+        self.ext_gstate_names.clear();
+        self.other_resources.clear();
+        self.text_colors.clear();
+        self.bounds = None;
+        self.unencodable.clear();
+        self.external_resources.clear();
+    }
+    /// Whether nothing has been rendered yet
+    pub fn is_empty(&self) -> bool {
+        self.stream.is_empty()
+    }
+    /// The length of the content stream so far, in bytes
+    pub(crate) fn content_len(&self) -> usize {
+        self.stream.len()
+    }
+    /// Makes (0, 0) the top left of the page, with y growing downward
+    ///
+    /// The content is wrapped in a `cm` that flips the y-axis. Text and
+    /// images are flipped back, so they stay upright, and images are drawn
+    /// downward from the top left corner of their rect.
+    ///
+    /// # Panics
+    ///
+    /// panics if content has already been rendered
+    pub fn set_top_left(&mut self) {
+        assert!(self.is_empty(), "Content has already been rendered");
+        self.flipped = true;
+    }
+    /// Whether (0, 0) is the top left of the page, see set_top_left
+    pub fn is_top_left(&self) -> bool {
+        self.flipped
+    }
+    /// Wraps the content in the `cm` flipping the y-axis, if the y-axis
+    /// points down, see set_top_left
+    pub(crate) fn flip(&mut self, media_box: Rect) {
+        if self.flipped {
+            let rest = std::mem::take(&mut self.stream);
+            let flip = Matrix::new(
+                1f64,
+                0f64,
+                0f64,
+                -1f64,
+                media_box.x(),
+                media_box.y() + media_box.height(),
+            );
+            self.command(&mut [], "q");
+            self.command(&mut [flip.into()], "cm");
+            self.stream.extend(rest);
+            self.command(&mut [], "Q");
+        }
+    }
+    /// Sets the width of stroked lines (`w`), until the state is restored
+    pub fn set_line_width(&mut self, width: f64) {
+        self.command(&mut [width.into()], "w");
+    }
+    /// Sets the current colors, which graphics that only set one of the
+    /// colors, such as filled paths, keep for the other
+    pub fn set_colors(&mut self, fill: Color, stroke: Color) {
+        GraphicParameters::update(
+            self,
+            &GraphicParameters::with_colors(Some(fill), Some(stroke)),
+        );
+    }
+    /// Saves the graphics state (`q`)
+    pub fn save_state(&mut self) {
+        self.stack.push_back((*self.current).clone());
+        self.command(&mut [], "q");
+    }
+    /// Restores the last saved graphics state (`Q`)
+    ///
+    /// # Panics
+    ///
+    /// panics if there is no saved state
+    pub fn restore_state(&mut self) {
+        let saved = self
+            .stack
+            .pop_back()
+            .expect("restore_state called without a saved state");
+        self.current = Rc::new(saved);
+        self.command(&mut [], "Q");
+    }
+    /// Sets the parameters in the graphics state dictionary (`gs`)
+    ///
+    /// The dictionary is added to the resources, and stays set until
+    /// the state is restored
+    pub fn set_ext_gstate(&mut self, state: Rc<ExtGState>) {
+        let name = match self
+            .ext_gstate_names
+            .iter()
+            .find(|(s, _)| Rc::ptr_eq(s, &state))
+        {
+            Some((_, name)) => name.clone(),
+            None => {
+                let name = Name::new(format!("GS{}", self.ext_gstate_names.len() + 1));
+                self.ext_gstates.add_entry(name.clone(), state.data());
+                self.ext_gstate_names.push((state, name.clone()));
+                name
+            }
+        };
+        self.command(&mut [name.into()], "gs");
+    }
+    /// Adds an entry to the resources, written exactly as given
+    ///
+    /// - category: the resource type, such as `"Properties"` or `"Shading"`
+    /// - name: the name used by the content, without the `/`
+    /// - value: the PDF syntax of the resource, such as
+    ///   `"<< /Type /OCG /Name (Layer) >>"`
+    ///
+    /// This is meant for content the library doesn't support yet. Names
+    /// starting with `Im`, `CS`, or `GS` and a number may clash with the
+    /// resources added by the library.
+    pub fn add_named_resource(&mut self, category: &str, name: &str, value: &str) {
+        self.resource_dict(category)
+            .add_entry(name, Raw::new(value));
+    }
+    /// The dict of a resource type, which is added if it is missing
+    fn resource_dict(&mut self, category: &str) -> Rc<Dict> {
+        match category {
+            "Font" => self.fonts.clone(),
+            "XObject" => self.xobjects.clone(),
+            "ColorSpace" => self.color_spaces.clone(),
+            "ExtGState" => self.ext_gstates.clone(),
+            _ => {
+                let category = Name::from(category);
+                match self.other_resources.iter().find(|(n, _)| *n == category) {
+                    Some((_, dict)) => dict.clone(),
+                    None => {
+                        let dict = Dict::new();
+                        self.other_resources.push((category, dict.clone()));
+                        dict
+                    }
+                }
+            }
+        }
+    }
+    /// Replaces every occurrence of the placeholder in the content
+    /// with the value
+    pub(crate) fn replace_placeholder(&mut self, placeholder: &str, value: &str) {
+        let (from, to) = (placeholder.as_bytes(), value.as_bytes());
+        if from.is_empty() {
+            return;
+        }
+        let mut out = Vec::with_capacity(self.stream.len());
+        let mut i = 0;
+        while i < self.stream.len() {
+            if self.stream[i..].starts_with(from) {
+                out.extend_from_slice(to);
+                i += from.len();
+            } else {
+                out.push(self.stream[i]);
+                i += 1;
+            }
+        }
+        self.stream = out;
+    }
+    /// Changes every RGB color in the content to how it appears with
+    /// the color vision deficiency, see the vision module
+    pub fn simulate_color_vision(&mut self, vision: ColorVision) {
+        self.stream = vision::transform_stream(&self.stream, vision);
+    }
+    /// Records the fill color of text drawn at a position
+    fn add_text_color(&mut self, color: Color, pos: Point) {
+        self.text_colors.push((color, pos));
+    }
+    /// The union of the bounds of everything rendered, see Graphic::bounds
+    ///
+    /// Graphics rendered with their own `cm` aren't transformed, so this
+    /// is only correct for content in page coordinates
+    pub(crate) fn content_bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+    /// The fill color and position of each piece of text
+    pub(crate) fn text_colors(&self) -> &[(Color, Point)] {
+        &self.text_colors
+    }
     fn command(&mut self, params: &mut [Parameter], operator: &str) {
         for p in params {
-            self.stream.push(' ' as u8);
+            self.stream.push(b' ');
             self.stream.append(&mut p.raw);
         }
-        self.stream.push(' ' as u8);
+        self.stream.push(b' ');
         self.stream.extend(operator.bytes());
     }
+    /// Starts a marked content sequence (`BMC` or `BDC`)
+    ///
+    /// - tag: the tag of the sequence, normally the structure type
+    /// - mcid: the marked content id, used to connect the content to
+    ///   the structure tree
+    pub fn begin_marked_content(&mut self, tag: Rc<Name>, mcid: Option<usize>) {
+        match mcid {
+            Some(mcid) => self.command(
+                &mut [
+                    tag.into(),
+                    Parameter {
+                        raw: format!("<</MCID {}>>", mcid).into_bytes(),
+                    },
+                ],
+                "BDC",
+            ),
+            None => self.command(&mut [tag.into()], "BMC"),
+        }
+    }
+    /// Starts a sequence of optional content (`/OC BDC`), ended by
+    /// end_marked_content
+    ///
+    /// - name: the name of the optional content group in the
+    ///   `/Properties` resources
+    /// - group: the optional content group
+    pub(crate) fn begin_optional_content(&mut self, name: Rc<Name>, group: Rc<dyn PDFData>) {
+        self.resource_dict("Properties")
+            .add_entry(name.clone(), group);
+        self.command(&mut [Name::new("OC").into(), name.into()], "BDC");
+    }
+    /// Ends the current marked content sequence (`EMC`)
+    pub fn end_marked_content(&mut self) {
+        self.command(&mut [], "EMC");
+    }
+    fn add_unencodable(&mut self, chars: &[char]) {
+        for c in chars {
+            if !self.unencodable.contains(c) {
+                self.unencodable.push(*c);
+            }
+        }
+    }
     fn add_resource(&mut self, obj: Rc<dyn Object>) {
         self.external_resources.push(obj);
     }
     fn add_font(&mut self, f: Rc<text::Font>) {
-        // self.fonts.add_entry(f.name(), f.object());
-        // self.external_resources.push(f.object());
-    }
-    pub fn compile(
-        self,
-        // write: &mut crate::pdf::PDFWrite,
-    ) -> (
-        Vec<Rc<crate::pdf::ObjRef<crate::pdf::types::Stream>>>,
-        Rc<crate::pdf::Dict>,
-    ) {
+        let name = f.name();
+        if self.fonts.get((*name).clone()).is_none() {
+            self.fonts.add_entry(name, f.object());
+            self.add_resource(f.object());
+        }
+    }
+    /// Checks that every font selected with `Tf` is in the resources
+    fn check_fonts(&self) -> Result<(), CompileError> {
+        let mut missing: Vec<String> = vec![];
+        let mut operands: Vec<&[u8]> = vec![];
+        for (start, end) in vision::tokens(&self.stream) {
+            let token = &self.stream[start..end];
+            if !(token[0].is_ascii_alphabetic() || token[0] == b'\'' || token[0] == b'"') {
+                operands.push(token);
+                continue;
+            }
+            // The operands of Tf are the font name and the size
+            if let (b"Tf", [.., name, _]) = (token, operands.as_slice()) {
+                let name = String::from_utf8_lossy(name);
+                let name = name.trim_start_matches('/');
+                if self.fonts.get(name).is_none() && !missing.iter().any(|m| m == name) {
+                    missing.push(name.to_string());
+                }
+            }
+            operands.clear();
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(CompileError::MissingFonts(missing))
+        }
+    }
+    /// Adds an XObject to the resources, and returns the name
+    /// it can be referenced by
+    ///
+    /// Adding the same object more than once returns the same name
+    fn add_xobject(&mut self, obj: Rc<ObjRef<Stream>>) -> Rc<Name> {
+        if let Some((_, name)) = self.xobject_names.iter().find(|(o, _)| Rc::ptr_eq(o, &obj)) {
+            return name.clone();
+        }
+        let name = Name::new(format!("Im{}", self.xobject_names.len() + 1));
+        self.xobjects.add_entry(name.clone(), obj.clone());
+        self.xobject_names.push((obj.clone(), name.clone()));
+        self.add_resource(obj);
+        name
+    }
+    /// Replaces the images that have the same content as an image of an
+    /// earlier page with that image, so it is only written once
+    pub(crate) fn share_images(&mut self, cache: &mut ImageCache) {
+        for (obj, name) in self.xobject_names.iter_mut() {
+            let key = match ImageCache::key(obj) {
+                Some(key) => key,
+                None => continue,
+            };
+            match cache.images.iter().find(|(k, _)| *k == key) {
+                Some((_, shared)) if !Rc::ptr_eq(shared, obj) => {
+                    let addr = Rc::as_ptr(obj) as *const ();
+                    self.external_resources
+                        .retain(|o| Rc::as_ptr(o) as *const () != addr);
+                    self.xobjects.add_entry(name.clone(), shared.clone());
+                    *obj = shared.clone();
+                }
+                Some(_) => {}
+                None => cache.images.push((key, obj.clone())),
+            }
+        }
+    }
+    /// Adds a color space to the resources, and returns the name
+    /// it can be referenced by
+    ///
+    /// Adding the same color space more than once returns the same name
+    fn add_colorspace(&mut self, space: Rc<ColorSpace>) -> Rc<Name> {
+        if let Some((_, name)) = self
+            .color_space_names
+            .iter()
+            .find(|(s, _)| Rc::ptr_eq(s, &space))
+        {
+            return name.clone();
+        }
+        let name = Name::new(format!("CS{}", self.color_space_names.len() + 1));
+        self.color_spaces.add_entry(name.clone(), space.data());
+        for obj in space.objects() {
+            self.add_resource(obj);
+        }
+        self.color_space_names.push((space, name.clone()));
+        name
+    }
+    /// Adds the resource categories that are in use to the resource dict
+    fn finish_resources(&self) {
         if !self.fonts.is_empty() {
-            self.resources.add_entry("Font", self.fonts);
+            self.resources.add_entry("Font", self.fonts.clone());
+        }
+        if !self.xobjects.is_empty() {
+            self.resources.add_entry("XObject", self.xobjects.clone());
+        }
+        if !self.color_spaces.is_empty() {
+            self.resources
+                .add_entry("ColorSpace", self.color_spaces.clone());
+        }
+        if !self.ext_gstates.is_empty() {
+            self.resources
+                .add_entry("ExtGState", self.ext_gstates.clone());
+        }
+        for (category, dict) in self.other_resources.iter() {
+            self.resources.add_entry(category.clone(), dict.clone());
+        }
+    }
+    /// Adds the content stream and the resources it uses to the writer
+    ///
+    /// Returns the content streams and the resource dict
+    ///
+    /// # Errors
+    ///
+    /// Returns CompileError::MissingFonts if the content selects a font
+    /// that isn't in the resources, or CompileError::UnencodableText if
+    /// characters were left out of text
+    pub fn compile(self, write: &mut PDFWrite) -> Result<Compiled, CompileError> {
+        self.check_fonts()?;
+        if !self.unencodable.is_empty() {
+            return Err(CompileError::UnencodableText(self.unencodable));
         }
+        let (stream, resources) = self.into_stream();
+        let streams = vec![ObjRef::new(0, stream)];
+        for obj in streams.iter().cloned() {
+            write.add_object(obj);
+        }
+        Ok((streams, resources))
+    }
+    /// Converts the content into a plain stream, for building other
+    /// objects such as patterns, forms, or Type3 glyphs
+    ///
+    /// Returns the stream, with an empty stream dictionary, and the
+    /// resource dict it uses. The objects the resources refer to are
+    /// added to a writer along with the stream.
+    pub fn into_stream(self) -> (Rc<Stream>, Rc<Dict>) {
+        self.finish_resources();
+        let stream = Stream::new(Dict::new(), self.stream);
+        for obj in self.external_resources {
+            stream.add_dependent(obj);
+        }
+        (stream, self.resources)
+    }
+    /// Compiles the content into a Form XObject
+    ///
+    /// - bbox: the bounding box of the form, in its own coordinates
+    pub(crate) fn compile_form(self, bbox: Rect, write: &mut PDFWrite) -> Rc<ObjRef<Stream>> {
+        let (stream, resources) = self.into_stream();
+        stream.meta().add_entry("Type", Name::new("XObject"));
+        stream.meta().add_entry("Subtype", Name::new("Form"));
+        stream.meta().add_entry("BBox", bbox.as_data());
+        stream.meta().add_entry("Resources", resources);
+        write.allocate(stream)
+    }
+}
+
+/// The images of the pages written so far, so images with the same
+/// content are only written once, see GraphicContext::share_images
+#[derive(Default)]
+pub(crate) struct ImageCache {
+    /// The content of each image, and the image
+    images: Vec<(Vec<u8>, Rc<ObjRef<Stream>>)>,
+}
 
-        let streams = vec![crate::pdf::ObjRef::new(
-            0,
-            crate::pdf::types::Stream::new(crate::pdf::Dict::new(), self.stream),
-        )];
-        // for obj in streams.iter().cloned() {
-        //     write.add_object(obj);
-        // }
-        // for obj in self.external_resources {
-        //     write.add_object(obj);
-        // }
-        (streams, self.resources)
+impl ImageCache {
+    /// The stream dictionary and data of an image XObject, or None for
+    /// other streams
+    ///
+    /// The entries of the dictionary are written in the order they were
+    /// added, so images built the same way have the same key. Objects
+    /// the dictionary refers to aren't numbered yet, so the ones added
+    /// with the image, such as its `/Metadata`, are compared by content,
+    /// and any others by identity.
+    fn key(obj: &ObjRef<Stream>) -> Option<Vec<u8>> {
+        let meta = obj.meta();
+        let dependents = Stream::dependent_objects(obj);
+        let mut key = vec![];
+        meta.get("Subtype")?.write(&mut key).ok()?;
+        if key != b"/Image" {
+            return None;
+        }
+        for (name, value) in meta.iter() {
+            name.write(&mut key).ok()?;
+            key.push(b' ');
+            let mut refs = vec![];
+            value.references(&mut refs);
+            if refs.is_empty() {
+                value.write(&mut key).ok()?;
+            } else {
+                for r in refs {
+                    match dependents.iter().find(|o| Rc::as_ptr(o) as *const () == r) {
+                        Some(o) => o.write_data(&mut key).ok()?,
+                        None => key.extend_from_slice(&(r as usize).to_le_bytes()),
+                    }
+                }
+            }
+            key.push(b'\n');
+        }
+        key.push(0);
+        key.extend_from_slice(obj.data());
+        Some(key)
     }
 }
 
+/// An XMP metadata stream, for the `/Metadata` of an object
+pub(crate) fn metadata_stream(xmp: Vec<u8>) -> Rc<ObjRef<Stream>> {
+    ObjRef::new(
+        0,
+        Stream::new(
+            Dict::from_vec(vec![
+                ("Type", Name::new("Metadata")),
+                ("Subtype", Name::new("XML")),
+            ]),
+            xmp,
+        ),
+    )
+}
+
 /// A raw, compiled representation of a set of parameters
 ///
 /// Should never have trailing whitespace
@@ -101,26 +684,43 @@ pub struct Parameter {
     raw: Vec<u8>,
 }
 
+/// A literal string, with backslashes and parentheses escaped, and
+/// control characters written as octal escapes, such as `\012` for a line feed
+fn literal_string(bytes: &[u8]) -> Parameter {
+    let mut raw = Vec::with_capacity(bytes.len() + 2);
+    raw.push(b'(');
+    for b in bytes {
+        match b {
+            b'\\' | b'(' | b')' => raw.extend([b'\\', *b]),
+            0..=0x1F | 0x7F => raw.extend(format!("\\{:03o}", b).bytes()),
+            _ => raw.push(*b),
+        }
+    }
+    raw.push(b')');
+    Parameter { raw }
+}
+
 impl From<&str> for Parameter {
     fn from(o: &str) -> Self {
-        Self {
-            raw: format!("({})", o).bytes().collect(),
-        }
+        literal_string(o.as_bytes())
     }
 }
 
 impl From<&String> for Parameter {
     fn from(o: &String) -> Self {
-        Self {
-            raw: format!("({})", o).bytes().collect(),
-        }
+        literal_string(o.as_bytes())
     }
 }
 impl From<String> for Parameter {
     fn from(o: String) -> Self {
-        Self {
-            raw: format!("({})", o).bytes().collect(),
-        }
+        literal_string(o.as_bytes())
+    }
+}
+
+/// A string of bytes, such as character codes
+impl From<&[u8]> for Parameter {
+    fn from(o: &[u8]) -> Self {
+        literal_string(o)
     }
 }
 
@@ -153,3 +753,173 @@ impl From<Rc<Name>> for Parameter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::{Buffer, PDFData};
+    use crate::tests::{text, written};
+    use crate::Page;
+    use std::cell::RefCell;
+
+    /// The content stream of a context the graphic is rendered into
+    pub(crate) fn rendered(graphic: Rc<impl Graphic>) -> String {
+        let mut ctx = GraphicContext::new();
+        ctx.render(graphic);
+        let (stream, _) = ctx.into_stream();
+        String::from_utf8_lossy(stream.data()).into_owned()
+    }
+
+    #[test]
+    fn into_stream() {
+        let mut ctx = GraphicContext::new();
+        ctx.render(
+            Path::from((0f64, 0f64))
+                .line_to((10f64, 20f64))
+                .stroke(Color::DeviceGray(0f64)),
+        );
+        let (stream, _) = ctx.into_stream();
+        assert_eq!(stream.data(), b" 0 0 m 10 20 l S");
+    }
+
+    #[test]
+    fn into_stream_adds_resources_with_the_stream() {
+        let mut ctx = GraphicContext::new();
+        ctx.render(Image::from_gray(1, 1, vec![0]).draw(Rect::new(0f64, 0f64, 10f64, 10f64)));
+        let (stream, resources) = ctx.into_stream();
+        stream.add_entry("Resources", resources);
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let mut write = PDFWrite::new(Box::new(Buffer(buffer.clone())));
+        let form = write.allocate(stream);
+        write.create_root(Dict::from_vec(vec![("Form", form as Rc<dyn PDFData>)]));
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("/Subtype /Image"));
+    }
+
+    #[test]
+    fn share_images_across_pages() {
+        let file = text(&written(|pdf| {
+            for _ in 0..3 {
+                let mut page = Page::new();
+                page.add(Image::from_gray(1, 1, vec![0]).draw(Rect::new(0f64, 0f64, 10f64, 10f64)));
+                pdf.add_page(page);
+            }
+        }));
+        assert_eq!(file.matches("/Subtype /Image").count(), 1);
+    }
+
+    #[test]
+    fn share_images_with_metadata() {
+        let file = text(&written(|pdf| {
+            for _ in 0..3 {
+                let mut page = Page::new();
+                page.add(
+                    Image::from_gray(1, 1, vec![0])
+                        .with_metadata(b"<x:xmpmeta/>".to_vec())
+                        .compress()
+                        .draw(Rect::new(0f64, 0f64, 10f64, 10f64)),
+                );
+                pdf.add_page(page);
+            }
+        }));
+        assert_eq!(file.matches("/Subtype /Image").count(), 1);
+        assert_eq!(file.matches("/Type /Metadata").count(), 1);
+    }
+
+    #[test]
+    fn missing_font() {
+        let mut ctx = GraphicContext::new();
+        ctx.render(Rc::new(
+            Text::new(Font::helvetica(), 12f64)
+                .text("ok")
+                .fill(Color::default()),
+        ));
+        ctx.command(&mut [Name::new("F9").into(), 12f64.into()], "Tf");
+        let mut write = PDFWrite::new(Box::new(Buffer(Rc::new(RefCell::new(vec![])))));
+        assert_eq!(
+            ctx.compile(&mut write).err(),
+            Some(CompileError::MissingFonts(vec!["F9".to_string()]))
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let draw = |ctx: &mut GraphicContext| {
+            ctx.render(Image::from_gray(1, 1, vec![0]).draw(Rect::new(0f64, 0f64, 10f64, 10f64)));
+            ctx.render(Rc::new(
+                Text::new(Font::helvetica(), 12f64)
+                    .text("Hi")
+                    .fill(Color::DeviceGray(0.5f64)),
+            ));
+        };
+        let resources = |ctx: &GraphicContext| {
+            let mut data = vec![];
+            ctx.resources.write(&mut data).unwrap();
+            data
+        };
+        let mut ctx = GraphicContext::new();
+        draw(&mut ctx);
+        let (first, first_resources) = (ctx.stream.clone(), resources(&ctx));
+        let capacity = ctx.stream.capacity();
+        ctx.reset();
+        draw(&mut ctx);
+        assert_eq!(ctx.stream, first);
+        assert_eq!(resources(&ctx), first_resources);
+        assert_eq!(ctx.stream.capacity(), capacity);
+    }
+
+    #[test]
+    fn font_resources() {
+        let mut ctx = GraphicContext::new();
+        ctx.render(Rc::new(
+            Text::new(Font::helvetica(), 12f64)
+                .text("Hi")
+                .fill(Color::default()),
+        ));
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let mut write = PDFWrite::new(Box::new(Buffer(buffer.clone())));
+        let (streams, resources) = ctx.compile(&mut write).unwrap();
+        write.create_root(Dict::from_vec(vec![
+            ("Contents", Rc::new(streams) as Rc<dyn PDFData>),
+            ("Resources", resources),
+        ]));
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("/Font <<\n/helvetica 2 0 R\n>>\n"));
+        assert!(file.contains("2 0 obj\n<<\n/Type /Font\n/Subtype /Type1\n/BaseFont /Helvetica\n"));
+    }
+
+    /// Reads a literal string back, as a reader does
+    fn unescape(raw: &[u8]) -> Vec<u8> {
+        assert_eq!((raw[0], raw[raw.len() - 1]), (b'(', b')'));
+        let mut out = vec![];
+        let mut bytes = raw[1..raw.len() - 1].iter();
+        while let Some(b) = bytes.next() {
+            match b {
+                b'\\' => match bytes.next().unwrap() {
+                    d @ b'0'..=b'7' => {
+                        let rest: Vec<u8> = bytes.by_ref().take(2).map(|d| d - b'0').collect();
+                        out.push((d - b'0') * 64 + rest[0] * 8 + rest[1]);
+                    }
+                    c => out.push(*c),
+                },
+                b'(' | b')' => panic!("Unescaped parenthesis in {:?}", raw),
+                _ => out.push(*b),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn literal_string_escapes() {
+        for s in ["a (b", "c) d", "back\\slash\\", "line\nfeed\ttab", "\\)"] {
+            let params: [Parameter; 3] = [s.into(), (&s.to_string()).into(), s.to_string().into()];
+            for p in params {
+                assert_eq!(unescape(&p.raw), s.as_bytes());
+            }
+        }
+        let p: Parameter = "(x)\n".into();
+        assert_eq!(p.raw, b"(\\(x\\)\\012)");
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Color, Graphic, GraphicContext, GraphicParameters, Point, Rect};
+use super::{Color, Graphic, GraphicContext, GraphicParameters, Matrix, Point, Rect};
 use std::rc::Rc;
 
 #[derive(Clone, Debug, Copy)]
@@ -9,25 +9,133 @@ enum PathPart {
     BezierLast(Point, Point),
     BezierNext(Point, Point),
 }
+impl PathPart {
+    fn transformed(self, m: &Matrix) -> Self {
+        match self {
+            Self::Start(p) => Self::Start(m.apply(p)),
+            Self::Line(p) => Self::Line(m.apply(p)),
+            Self::Bezier(p1, p2, p3) => Self::Bezier(m.apply(p1), m.apply(p2), m.apply(p3)),
+            Self::BezierLast(p2, p3) => Self::BezierLast(m.apply(p2), m.apply(p3)),
+            Self::BezierNext(p1, p2) => Self::BezierNext(m.apply(p1), m.apply(p2)),
+        }
+    }
+}
 #[derive(Clone, Debug)]
 enum SubPath {
     Parts(Vec<PathPart>, bool),
     Rect(Rect),
 }
 
+/// An arc of a circle, as beziers of at most a quarter circle
+///
+/// - line: whether the arc starts with a line to its first point,
+///   instead of starting a subpath there
+fn arc(center: Point, radius: f64, start: f64, end: f64, line: bool) -> Vec<PathPart> {
+    let at = |angle: f64, r: f64| {
+        let (sin, cos) = angle.sin_cos();
+        Point::new(center.x() + cos * r, center.y() + sin * r)
+    };
+    let first = at(start, radius);
+    let mut parts = vec![if line {
+        PathPart::Line(first)
+    } else {
+        PathPart::Start(first)
+    }];
+    let steps = ((end - start).abs() / std::f64::consts::FRAC_PI_2)
+        .ceil()
+        .max(1f64);
+    let step = (end - start) / steps;
+    // The distance of the control points along the tangents
+    let k = 4f64 / 3f64 * (step / 4f64).tan() * radius;
+    for i in 0..steps as usize {
+        let (a0, a1) = (start + step * i as f64, start + step * (i + 1) as f64);
+        let (p0, p3) = (at(a0, radius), at(a1, radius));
+        let (s0, c0) = a0.sin_cos();
+        let (s1, c1) = a1.sin_cos();
+        parts.push(PathPart::Bezier(
+            Point::new(p0.x() - s0 * k, p0.y() + c0 * k),
+            Point::new(p3.x() + s1 * k, p3.y() - c1 * k),
+            p3,
+        ));
+    }
+    parts
+}
+
+/// The rule deciding which areas are inside a path that crosses itself
+///
+/// See [Adobe's PDF 1.7 spec, 4.4.2](https://www.adobe.com/content/dam/acom/en/devnet/acrobat/pdfs/pdf_reference_1-7.pdf#G9.1850155)
+/// for more info
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Winding {
+    /// A point is inside if the path winds around it a non-zero number
+    /// of times
+    NonZero,
+    /// A point is inside if a ray from it crosses the path an odd
+    /// number of times
+    EvenOdd,
+}
+
 #[derive(Clone, Debug)]
 pub struct Path {
     path: Vec<SubPath>,
     /// Always Some, but Option to allow .take()
     cur: Option<Vec<PathPart>>,
+    /// Colors set by with_fill and with_stroke, used by draw
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    winding: Winding,
 }
 
+impl SubPath {
+    fn transformed(self, m: &Matrix) -> Self {
+        match self {
+            Self::Parts(parts, closed) => Self::Parts(
+                parts.into_iter().map(|p| p.transformed(m)).collect(),
+                closed,
+            ),
+            Self::Rect(r) => {
+                let origin = m.apply((r.x(), r.y()));
+                let right = m.apply((r.x() + r.width(), r.y()));
+                let top = m.apply((r.x(), r.y() + r.height()));
+                if right.y() == origin.y() && top.x() == origin.x() {
+                    Self::Rect(Rect::new(
+                        origin.x(),
+                        origin.y(),
+                        right.x() - origin.x(),
+                        top.y() - origin.y(),
+                    ))
+                } else {
+                    // Rotated or skewed, so it is no longer a rectangle
+                    let corner = m.apply((r.x() + r.width(), r.y() + r.height()));
+                    Self::Parts(
+                        vec![
+                            PathPart::Start(origin),
+                            PathPart::Line(right),
+                            PathPart::Line(corner),
+                            PathPart::Line(top),
+                        ],
+                        true,
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl Default for Path {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Path {
     /// Starts a new path
     pub fn new() -> Self {
         Self {
             path: vec![],
             cur: Some(vec![]),
+            fill: None,
+            stroke: None,
+            winding: Winding::NonZero,
         }
     }
     /// Starts a new path from the given point
@@ -37,6 +145,9 @@ impl Path {
         Self {
             path: vec![],
             cur: Some(vec![PathPart::Start(point.into())]),
+            fill: None,
+            stroke: None,
+            winding: Winding::NonZero,
         }
     }
     /// Starts a new subpath, without closing the current subpath
@@ -64,6 +175,14 @@ impl Path {
         self.cur = Some(vec![PathPart::Start(point.into())]);
         self
     }
+    /// Closes the current subpath (`h`), the next must be started with
+    /// move_to
+    pub(super) fn close(mut self) -> Self {
+        self.path
+            .push(SubPath::Parts(self.cur.take().unwrap(), true));
+        self.cur = Some(vec![]);
+        self
+    }
     /// Adds a line to the current subpath
     ///
     /// - point: See Point for more info
@@ -134,31 +253,162 @@ impl Path {
         self.path.push(SubPath::Rect(r.into()));
         self
     }
-    /// Complete the path with a stroking operation
+    /// Adds a pie slice to the path, from the center to the arc and back
+    ///
+    /// - center: the center of the circle, see Point
+    /// - radius: the radius of the circle
+    /// - start: the angle the slice starts at, in radians
+    ///   counter-clockwise from the positive x-axis
+    /// - end: the angle the slice ends at, counter-clockwise from start
+    ///   if it is larger
+    ///
+    /// Like rect, this adds a closed subpath without interrupting the
+    /// current subpath. The arc is drawn with a bezier per quarter
+    /// circle or less, which is within 0.03% of the radius.
+    pub fn pie(mut self, center: impl Into<Point>, radius: f64, start: f64, end: f64) -> Self {
+        let center = center.into();
+        let mut parts = vec![PathPart::Start(center)];
+        parts.extend(arc(center, radius, start, end, true));
+        self.path.push(SubPath::Parts(parts, true));
+        self
+    }
+    /// Adds a slice of a ring to the path, such as a segment of a donut
+    /// chart, see pie
+    ///
+    /// - inner: the radius of the hole
+    /// - outer: the outer radius of the ring
+    pub fn donut(
+        mut self,
+        center: impl Into<Point>,
+        inner: f64,
+        outer: f64,
+        start: f64,
+        end: f64,
+    ) -> Self {
+        let center = center.into();
+        let mut parts = arc(center, outer, start, end, false);
+        parts.extend(arc(center, inner, end, start, true));
+        self.path.push(SubPath::Parts(parts, true));
+        self
+    }
+    /// Applies the matrix to every point of the path
+    ///
+    /// - m: See Matrix
+    ///
+    /// This changes the coordinates of the path rather than the CTM (the
+    /// `cm` operator), so the line width isn't scaled with the path.
+    /// Rectangles stay rectangles unless the matrix rotates or skews them.
+    pub fn transformed(self, m: Matrix) -> Self {
+        Self {
+            path: self.path.into_iter().map(|p| p.transformed(&m)).collect(),
+            cur: self
+                .cur
+                .map(|c| c.into_iter().map(|p| p.transformed(&m)).collect()),
+            fill: self.fill,
+            stroke: self.stroke,
+            winding: self.winding,
+        }
+    }
+    /// The points along the first subpath, with curves split into short
+    /// lines
+    pub(super) fn flatten(&self) -> Vec<Point> {
+        // Steps each curve is split into
+        const STEPS: usize = 16;
+        let (parts, closed) = match self.path.first() {
+            Some(SubPath::Rect(r)) => {
+                let mut corners = r.corners().to_vec();
+                corners.push(corners[0]);
+                return corners;
+            }
+            Some(SubPath::Parts(parts, closed)) => (parts.clone(), *closed),
+            None => (self.cur.clone().unwrap(), false),
+        };
+        let mut points: Vec<Point> = vec![];
+        for part in parts {
+            let last = points.last().copied().unwrap_or(Point::new(0f64, 0f64));
+            let curve = match part {
+                PathPart::Start(p) | PathPart::Line(p) => {
+                    points.push(p);
+                    continue;
+                }
+                PathPart::Bezier(p1, p2, p3) => (p1, p2, p3),
+                PathPart::BezierLast(p2, p3) => (last, p2, p3),
+                PathPart::BezierNext(p1, p2) => (p1, p2, p2),
+            };
+            let (p1, p2, p3) = curve;
+            for i in 1..=STEPS {
+                let t = i as f64 / STEPS as f64;
+                let u = 1f64 - t;
+                let (a, b, c, d) = (u * u * u, 3f64 * u * u * t, 3f64 * u * t * t, t * t * t);
+                points.push(Point::new(
+                    a * last.x() + b * p1.x() + c * p2.x() + d * p3.x(),
+                    a * last.y() + b * p1.y() + c * p2.y() + d * p3.y(),
+                ));
+            }
+        }
+        if closed {
+            if let Some(first) = points.first().copied() {
+                points.push(first);
+            }
+        }
+        points
+    }
+    /// Sets the color used to fill the path when it is completed with draw
     ///
     /// - color: See Color
+    pub fn with_fill(mut self, color: Color) -> Self {
+        self.fill = Some(color);
+        self
+    }
+    /// Sets the color used to stroke the path when it is completed with draw
     ///
-    /// Draws the current path (ending the current subpath, without closing it)
+    /// - color: See Color
+    pub fn with_stroke(mut self, color: Color) -> Self {
+        self.stroke = Some(color);
+        self
+    }
+    /// Complete the path with the colors set by with_fill and with_stroke
     ///
-    /// # Note:
+    /// The path is filled if a fill color was set, and stroked if a stroke
+    /// color was set, so this is equivalent to fill, stroke, or stroke_fill.
     ///
-    /// Only adds the current subpath if it has more than one point. The PDF
-    /// spec says that painting or clipping with a subpath that only has a
-    /// single point is device dependent, so this should not cause a problem
-    pub fn stroke(mut self, color: Color) -> Rc<GraphicPath> {
+    /// # Panics
+    ///
+    /// panics if neither color was set
+    pub fn draw(self) -> Rc<GraphicPath> {
+        match (self.fill.clone(), self.stroke.clone()) {
+            (Some(fill), Some(stroke)) => self.stroke_fill(stroke, fill),
+            (Some(fill), None) => self.fill(fill),
+            (None, Some(stroke)) => self.stroke(stroke),
+            (None, None) => panic!("draw requires a fill or stroke color"),
+        }
+    }
+    /// Sets the rule used to decide what is inside the path when it is
+    /// filled
+    ///
+    /// - winding: See Winding
+    ///
+    /// The rule is used by every completion that fills the path, and
+    /// defaults to Winding::NonZero
+    pub fn winding(mut self, winding: Winding) -> Self {
+        self.winding = winding;
+        self
+    }
+    /// Ends the current subpath, without closing it, and paints the path
+    fn finish(mut self, fill: Option<Color>, stroke: Option<Color>) -> Rc<GraphicPath> {
         if self.cur.as_ref().unwrap().len() > 1 {
             self.path
                 .push(SubPath::Parts(self.cur.take().unwrap(), false));
         }
         Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(None, Some(color)),
+            stroke: stroke.is_some(),
+            fill: fill.is_some(),
+            even_odd: self.winding == Winding::EvenOdd,
+            params: GraphicParameters::with_colors(fill, stroke),
             path: self.path,
-            stroke: true,
-            fill: false,
-            even_odd: false,
         })
     }
-    /// Complete the path with a filling operation
+    /// Complete the path with a stroking operation
     ///
     /// - color: See Color
     ///
@@ -169,18 +419,10 @@ impl Path {
     /// Only adds the current subpath if it has more than one point. The PDF
     /// spec says that painting or clipping with a subpath that only has a
     /// single point is device dependent, so this should not cause a problem
-    pub fn fill(mut self, color: Color) -> Rc<GraphicPath> {
-        self.path
-            .push(SubPath::Parts(self.cur.take().unwrap(), false));
-        Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(Some(color), None),
-            path: self.path,
-            stroke: false,
-            fill: true,
-            even_odd: false,
-        })
+    pub fn stroke(self, color: Color) -> Rc<GraphicPath> {
+        self.finish(None, Some(color))
     }
-    /// Complete the path with a stroking and filling operation
+    /// Complete the path with a filling operation
     ///
     /// - color: See Color
     ///
@@ -191,93 +433,44 @@ impl Path {
     /// Only adds the current subpath if it has more than one point. The PDF
     /// spec says that painting or clipping with a subpath that only has a
     /// single point is device dependent, so this should not cause a problem
-    pub fn stroke_fill(mut self, stroke: Color, fill: Color) -> Rc<GraphicPath> {
-        self.path
-            .push(SubPath::Parts(self.cur.take().unwrap(), false));
-        Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(Some(fill), Some(stroke)),
-            path: self.path,
-            stroke: true,
-            fill: true,
-            even_odd: false,
-        })
+    pub fn fill(self, color: Color) -> Rc<GraphicPath> {
+        self.finish(Some(color), None)
     }
-    /// Complete the path with a stroking operation, using the even-odd
-    /// winding rule
+    /// Complete the path with a stroking and filling operation
     ///
     /// - color: See Color
     ///
     /// Draws the current path (ending the current subpath, without closing it)
     ///
-    /// # Notes:
+    /// # Note:
     ///
-    /// - See [Adobe's PDF 1.7 spec, 4.4.2, Even-Odd Rule](https://www.adobe.com/content/dam/acom/en/devnet/acrobat/pdfs/pdf_reference_1-7.pdf#G9.1850155)
-    /// for more info
-    /// - Only adds the current subpath if it has more than one point. The PDF
+    /// Only adds the current subpath if it has more than one point. The PDF
     /// spec says that painting or clipping with a subpath that only has a
     /// single point is device dependent, so this should not cause a problem
-    pub fn stroke_even_odd(mut self, color: Color) -> Rc<GraphicPath> {
-        self.path
-            .push(SubPath::Parts(self.cur.take().unwrap(), false));
-        Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(None, Some(color)),
-            path: self.path,
-            stroke: true,
-            fill: false,
-            even_odd: true,
-        })
+    pub fn stroke_fill(self, stroke: Color, fill: Color) -> Rc<GraphicPath> {
+        self.finish(Some(fill), Some(stroke))
     }
-    /// Complete the path with a filling operation, using the even-odd
+    /// Complete the path with a stroking operation, using the even-odd
     /// winding rule
     ///
-    /// - color: See Color
-    ///
-    /// Draws the current path (ending the current subpath, without closing it)
-    ///
-    /// # Notes:
+    /// Stroking doesn't depend on the winding rule, so this is the same
+    /// as stroke
+    pub fn stroke_even_odd(self, color: Color) -> Rc<GraphicPath> {
+        self.winding(Winding::EvenOdd).stroke(color)
+    }
+    /// Complete the path with a filling operation, using the even-odd
+    /// winding rule
     ///
-    /// - See [Adobe's PDF 1.7 spec, 4.4.2, Even-Odd Rule]
-    /// (https://www.adobe.com/content/dam/acom/en/devnet/acrobat/pdfs/pdf_reference_1-7.pdf#G9.1850155)
-    /// for more info
-    /// - Only adds the current subpath if it has more than one point. The PDF
-    /// spec says that painting or clipping with a subpath that only has a
-    /// single point is device dependent, so this should not cause a problem
-    pub fn fill_even_odd(mut self, color: Color) -> Rc<GraphicPath> {
-        self.path
-            .push(SubPath::Parts(self.cur.take().unwrap(), false));
-        Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(Some(color), None),
-            path: self.path,
-            stroke: false,
-            fill: true,
-            even_odd: true,
-        })
+    /// Equivalent to `.winding(Winding::EvenOdd).fill(color)`
+    pub fn fill_even_odd(self, color: Color) -> Rc<GraphicPath> {
+        self.winding(Winding::EvenOdd).fill(color)
     }
     /// Complete the path with a stroking and filling operation, using the even-odd
     /// winding rule
     ///
-    /// - color: See Color
-    ///
-    /// Draws the current path (ending the current subpath, without closing it)
-    ///
-    /// # Notes:
-    ///
-    /// - See [Adobe's PDF 1.7 spec, 4.4.2, Even-Odd Rule]
-    /// (https://www.adobe.com/content/dam/acom/en/devnet/acrobat/pdfs/pdf_reference_1-7.pdf#G9.1850155)
-    /// for more info
-    /// - Only adds the current subpath if it has more than one point. The PDF
-    /// spec says that painting or clipping with a subpath that only has a
-    /// single point is device dependent, so this should not cause a problem
-    pub fn stroke_fill_even_odd(mut self, stroke: Color, fill: Color) -> Rc<GraphicPath> {
-        self.path
-            .push(SubPath::Parts(self.cur.take().unwrap(), false));
-        Rc::new(GraphicPath {
-            params: GraphicParameters::with_colors(Some(fill), Some(stroke)),
-            path: self.path,
-            stroke: true,
-            fill: true,
-            even_odd: true,
-        })
+    /// Equivalent to `.winding(Winding::EvenOdd).stroke_fill(stroke, fill)`
+    pub fn stroke_fill_even_odd(self, stroke: Color, fill: Color) -> Rc<GraphicPath> {
+        self.winding(Winding::EvenOdd).stroke_fill(stroke, fill)
     }
 }
 #[derive(Debug)]
@@ -292,6 +485,28 @@ impl Graphic for GraphicPath {
     fn get_graphics_parameters(&self) -> &GraphicParameters {
         &self.params
     }
+    /// The bounds of the points and control points of the path, which
+    /// contain the curves. The line width isn't included
+    fn bounds(&self) -> Option<Rect> {
+        let mut points = vec![];
+        for subpath in &self.path {
+            match subpath {
+                SubPath::Parts(parts, _) => {
+                    for part in parts {
+                        match *part {
+                            PathPart::Start(p) | PathPart::Line(p) => points.push(p),
+                            PathPart::Bezier(p1, p2, p3) => points.extend([p1, p2, p3]),
+                            PathPart::BezierLast(p1, p2) | PathPart::BezierNext(p1, p2) => {
+                                points.extend([p1, p2])
+                            }
+                        }
+                    }
+                }
+                SubPath::Rect(r) => points.extend(r.corners()),
+            }
+        }
+        Rect::enclosing(points)
+    }
     fn render(&self, g: &mut GraphicContext) {
         for subpath in &self.path {
             match subpath {
@@ -338,3 +553,63 @@ impl Graphic for GraphicPath {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+
+    #[test]
+    fn draw_with_colors() {
+        let content = rendered(
+            Path::new()
+                .with_fill(Color::red())
+                .with_stroke(Color::DeviceRGB(0f64, 0f64, 1f64))
+                .rect((0f64, 0f64, 10f64, 10f64))
+                .draw(),
+        );
+        assert_eq!(
+            content,
+            " /DeviceRGB cs  1 0 0 scn  /DeviceRGB CS  0 0 1 SCN  0 0 10 10 re B"
+        );
+    }
+
+    #[test]
+    fn transformed() {
+        let mut ctx = GraphicContext::new();
+        ctx.set_line_width(1.5);
+        ctx.render(
+            Path::from((1f64, 2f64))
+                .line_to((3f64, 4f64))
+                .rect((5f64, 6f64, 7f64, 8f64))
+                .transformed(Matrix::scale(2f64, 2f64))
+                .stroke(Color::DeviceGray(0f64)),
+        );
+        let (stream, _) = ctx.into_stream();
+        // The coordinates are scaled, without a cm changing the line width
+        assert_eq!(stream.data(), b" 1.5 w 10 12 14 16 re 2 4 m 6 8 l S");
+    }
+
+    #[test]
+    fn even_odd_fill() {
+        let content = rendered(
+            Path::new()
+                .rect((0f64, 0f64, 10f64, 10f64))
+                .winding(Winding::EvenOdd)
+                .fill(Color::DeviceGray(0f64)),
+        );
+        assert_eq!(content, " 0 0 10 10 re f*");
+    }
+
+    #[test]
+    fn pie() {
+        let slice = Path::new()
+            .pie((50f64, 50f64), 10f64, 0f64, std::f64::consts::FRAC_PI_2)
+            .fill(Color::DeviceGray(0f64));
+        // Starts at the center, and h closes it back to the center
+        assert_eq!(
+            rendered(slice),
+            " 50 50 m 60 50 l 60 55.52284749830793 55.52284749830793 60 50 60 c h f"
+        );
+    }
+}
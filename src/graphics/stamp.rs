@@ -0,0 +1,90 @@
+use super::{Graphic, GraphicContext, GraphicParameters, Matrix, Rect};
+use crate::pdf::{types::Stream, Dict, Name, ObjRef};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Content drawn once, and placed any number of times, on any page
+/// (a Form XObject)
+///
+/// Created with PDF::define_stamp. The content is only written once,
+/// however many times it is placed.
+#[derive(Debug)]
+pub struct Stamp {
+    object: Rc<ObjRef<Stream>>,
+    bbox: Rect,
+    matrix: Cell<Matrix>,
+}
+
+impl Stamp {
+    pub(crate) fn new(object: Rc<ObjRef<Stream>>, bbox: Rect) -> Rc<Self> {
+        Rc::new(Self {
+            object,
+            bbox,
+            matrix: Cell::new(Matrix::identity()),
+        })
+    }
+    /// Sets the matrix mapping the coordinates of the stamp onto the
+    /// coordinates it is placed in (`/Matrix`)
+    ///
+    /// The matrix is applied before the matrix the stamp is placed with,
+    /// and applies wherever the stamp is placed
+    pub fn set_matrix(&self, matrix: Matrix) {
+        self.matrix.set(matrix);
+        self.object.add_entry("Matrix", matrix.as_data());
+    }
+    /// Makes the content a transparency group (`/Group`), which soft
+    /// masks require
+    pub(super) fn make_group(&self) {
+        self.object.add_entry(
+            "Group",
+            Dict::from_vec(vec![
+                ("Type", Name::new("Group")),
+                ("S", Name::new("Transparency")),
+                ("CS", Name::new("DeviceRGB")),
+            ]),
+        );
+    }
+    pub(super) fn object(&self) -> Rc<ObjRef<Stream>> {
+        self.object.clone()
+    }
+    /// The bounding box of the stamp, which its content is clipped to
+    pub fn bbox(&self) -> Rect {
+        self.bbox
+    }
+    /// Places the stamp, transformed by the matrix
+    ///
+    /// - matrix: see Matrix
+    pub fn place(self: &Rc<Self>, matrix: Matrix) -> Rc<GraphicStamp> {
+        Rc::new(GraphicStamp {
+            // The content of the stamp was drawn starting from the initial
+            // state, so the colors are reset before it is placed
+            params: GraphicParameters::default(),
+            stamp: self.clone(),
+            matrix,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicStamp {
+    params: GraphicParameters,
+    stamp: Rc<Stamp>,
+    matrix: Matrix,
+}
+
+impl Graphic for GraphicStamp {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn bounds(&self) -> Option<Rect> {
+        let matrix = self.stamp.matrix.get().then(&self.matrix);
+        Rect::enclosing(self.stamp.bbox.corners().iter().map(|p| matrix.apply(*p)))
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        let name = out.add_xobject(self.stamp.object.clone());
+        out.command(&mut [], "q");
+        out.command(&mut [self.matrix.into()], "cm");
+        out.command(&mut [name.into()], "Do");
+        out.command(&mut [], "Q");
+    }
+}
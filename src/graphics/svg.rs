@@ -0,0 +1,816 @@
+//! Drawing simple SVG documents as vector graphics
+//!
+//! Only a subset of SVG is supported: the rect, circle, ellipse, line,
+//! polyline, polygon, and path elements, inside any number of `<g>`
+//! elements. Shapes are painted with the fill, stroke, stroke-width,
+//! and fill-rule attributes, or the same properties in a style
+//! attribute, and placed with the transform attribute. Everything else,
+//! such as text, gradients, `<use>`, and CSS style sheets, is skipped.
+//! Rounded corners of rects are drawn square.
+
+use super::path::GraphicPath;
+use super::{Color, Graphic, GraphicContext, GraphicParameters, GraphicsContextType};
+use super::{Matrix, Path, Point, Rect, Winding};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::io;
+use std::rc::Rc;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// An element's start tag, or its end
+enum Tag<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        empty: bool,
+    },
+    End,
+}
+
+/// Splits the document into tags, skipping text, comments, and
+/// declarations
+fn tags(source: &str) -> io::Result<Vec<Tag<'_>>> {
+    let mut tags = vec![];
+    let mut rest = source;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let skip = [
+            ("<!--", "-->"),
+            ("<![CDATA[", "]]>"),
+            ("<?", "?>"),
+            ("<!", ">"),
+        ]
+        .iter()
+        .find(|(open, _)| rest.starts_with(open));
+        if let Some((_, close)) = skip {
+            let end = rest
+                .find(close)
+                .ok_or_else(|| invalid("Unclosed SVG markup"))?;
+            rest = &rest[end + close.len()..];
+            continue;
+        }
+        let end = tag_end(rest).ok_or_else(|| invalid("Unclosed SVG tag"))?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('/') {
+            tags.push(Tag::End);
+            continue;
+        }
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(tag.len());
+        tags.push(Tag::Start {
+            name: &tag[..name_end],
+            attributes: attributes(&tag[name_end..])?,
+            empty,
+        });
+    }
+    Ok(tags)
+}
+
+/// The position of the `>` ending the tag, outside of quoted values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn attributes(mut s: &str) -> io::Result<Vec<(&str, String)>> {
+    let mut attributes = vec![];
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Ok(attributes);
+        }
+        let eq = s
+            .find('=')
+            .ok_or_else(|| invalid("SVG attribute without a value"))?;
+        let name = s[..eq].trim();
+        s = s[eq + 1..].trim_start();
+        let quote = s
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| invalid("Unquoted SVG attribute value"))?;
+        let end = s[1..]
+            .find(quote)
+            .ok_or_else(|| invalid("Unclosed SVG attribute value"))?;
+        let value = s[1..end + 1]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&");
+        attributes.push((name, value));
+        s = &s[end + 2..];
+    }
+}
+
+/// Reads numbers and flags from path data, points, and transform lists
+struct Numbers<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Numbers<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s: s.as_bytes(),
+            i: 0,
+        }
+    }
+    fn skip_separators(&mut self) {
+        while self.i < self.s.len()
+            && (self.s[self.i].is_ascii_whitespace() || self.s[self.i] == b',')
+        {
+            self.i += 1;
+        }
+    }
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.i >= self.s.len()
+    }
+    /// The next byte, if it starts a number
+    fn at_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(self.s.get(self.i), Some(b'0'..=b'9' | b'.' | b'-' | b'+'))
+    }
+    /// The next command letter, or other delimiter
+    fn next_byte(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let b = self.s.get(self.i).copied();
+        self.i += 1;
+        b
+    }
+    fn number(&mut self) -> io::Result<f64> {
+        self.skip_separators();
+        let start = self.i;
+        let digits = |n: &mut Self| {
+            while n.i < n.s.len() && n.s[n.i].is_ascii_digit() {
+                n.i += 1;
+            }
+        };
+        if matches!(self.s.get(self.i), Some(b'-' | b'+')) {
+            self.i += 1;
+        }
+        digits(self);
+        if self.s.get(self.i) == Some(&b'.') {
+            self.i += 1;
+            digits(self);
+        }
+        // An exponent, but not the start of an `em` or `ex` unit
+        if matches!(self.s.get(self.i), Some(b'e' | b'E'))
+            && matches!(self.s.get(self.i + 1), Some(b'0'..=b'9' | b'-' | b'+'))
+        {
+            self.i += 2;
+            digits(self);
+        }
+        std::str::from_utf8(&self.s[start..self.i])
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| invalid("Invalid number in SVG"))
+    }
+    fn point(&mut self) -> io::Result<Point> {
+        Ok(Point::new(self.number()?, self.number()?))
+    }
+    /// An arc flag, which may be written without a separator
+    fn flag(&mut self) -> io::Result<bool> {
+        match self.next_byte() {
+            Some(b'0') => Ok(false),
+            Some(b'1') => Ok(true),
+            _ => Err(invalid("Invalid arc flag in SVG path")),
+        }
+    }
+}
+
+/// The number at the start of a length, ignoring its unit
+fn length(value: &str) -> io::Result<f64> {
+    Numbers::new(value).number()
+}
+
+/// An arc of an ellipse, as beziers of at most a quarter turn
+///
+/// - rotation: the angle of the x-axis of the ellipse, in radians
+/// - start: the angle the arc starts at, on the unit circle before it
+///   is scaled to the ellipse
+/// - sweep: the angle of the arc, counter-clockwise if positive
+fn arc(
+    path: Path,
+    center: Point,
+    radii: (f64, f64),
+    rotation: f64,
+    start: f64,
+    sweep: f64,
+) -> Path {
+    let (sin, cos) = rotation.sin_cos();
+    let m = Matrix::new(
+        radii.0 * cos,
+        radii.0 * sin,
+        -radii.1 * sin,
+        radii.1 * cos,
+        center.x(),
+        center.y(),
+    );
+    let steps = (sweep.abs() / FRAC_PI_2).ceil().max(1f64);
+    let step = sweep / steps;
+    let k = 4f64 / 3f64 * (step / 4f64).tan();
+    let mut path = path;
+    for i in 0..steps as usize {
+        let (a0, a1) = (start + step * i as f64, start + step * (i + 1) as f64);
+        let (s0, c0) = a0.sin_cos();
+        let (s1, c1) = a1.sin_cos();
+        path = path.curve_to(
+            m.apply((c0 - s0 * k, s0 + c0 * k)),
+            m.apply((c1 + s1 * k, s1 - c1 * k)),
+            m.apply((c1, s1)),
+        );
+    }
+    path
+}
+
+/// A closed ellipse, starting from its rightmost point
+fn ellipse(center: Point, rx: f64, ry: f64) -> Path {
+    let path = Path::new().move_to((center.x() + rx, center.y()));
+    arc(path, center, (rx, ry), 0f64, 0f64, 2f64 * PI).close()
+}
+
+/// The arc of an `A` command, from its endpoints, as described in the
+/// implementation notes of the SVG spec
+#[allow(clippy::too_many_arguments)]
+fn endpoint_arc(
+    path: Path,
+    from: Point,
+    to: Point,
+    rx: f64,
+    ry: f64,
+    degrees: f64,
+    large: bool,
+    sweep: bool,
+) -> Path {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0f64 || ry == 0f64 {
+        return path.line_to(to);
+    }
+    if from == to {
+        return path;
+    }
+    let rotation = degrees.to_radians();
+    let (sin, cos) = rotation.sin_cos();
+    let (dx, dy) = ((from.x() - to.x()) / 2f64, (from.y() - to.y()) / 2f64);
+    let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+    // Radii too small to reach are scaled up
+    let scale = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if scale > 1f64 {
+        rx *= scale.sqrt();
+        ry *= scale.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0f64).sqrt();
+    if large == sweep {
+        coef = -coef;
+    }
+    let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let center = Point::new(
+        cos * cx1 - sin * cy1 + (from.x() + to.x()) / 2f64,
+        sin * cx1 + cos * cy1 + (from.y() + to.y()) / 2f64,
+    );
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut delta = (end - start) % (2f64 * PI);
+    if sweep && delta < 0f64 {
+        delta += 2f64 * PI;
+    } else if !sweep && delta > 0f64 {
+        delta -= 2f64 * PI;
+    }
+    arc(path, center, (rx, ry), rotation, start, delta)
+}
+
+/// Parses the path data of a path element (`d`)
+fn path_data(d: &str) -> io::Result<Path> {
+    let mut n = Numbers::new(d);
+    let mut path = Path::new();
+    let origin = Point::new(0f64, 0f64);
+    let (mut cur, mut start) = (origin, origin);
+    // The last control point, for the smooth curves
+    let (mut last_cubic, mut last_quad): (Option<Point>, Option<Point>) = (None, None);
+    let mut command = None;
+    // After Z, the next drawing command starts a subpath at start
+    let mut closed = false;
+    while !n.at_end() {
+        if !n.at_number() {
+            command = n.next_byte();
+        } else if matches!(command, Some(b'Z' | b'z')) {
+            return Err(invalid("Numbers after Z in SVG path data"));
+        }
+        let c = command.ok_or_else(|| invalid("SVG path data must start with a command"))?;
+        let relative = c.is_ascii_lowercase();
+        // Relative coordinates are from the point the command starts at
+        let base = if relative { cur } else { origin };
+        let offset = |p: Point| Point::new(base.x() + p.x(), base.y() + p.y());
+        if closed && !matches!(c, b'M' | b'm' | b'Z' | b'z') {
+            path = path.move_to(cur);
+        }
+        closed = false;
+        let upper = c.to_ascii_uppercase();
+        let (cubic, quad) = match upper {
+            b'M' => {
+                cur = offset(n.point()?);
+                start = cur;
+                path = path.move_to(cur);
+                // Further pairs are lines
+                command = Some(if relative { b'l' } else { b'L' });
+                (None, None)
+            }
+            b'L' => {
+                cur = offset(n.point()?);
+                path = path.line_to(cur);
+                (None, None)
+            }
+            b'H' => {
+                let x = n.number()?;
+                cur = Point::new(base.x() + x, cur.y());
+                path = path.line_to(cur);
+                (None, None)
+            }
+            b'V' => {
+                let y = n.number()?;
+                cur = Point::new(cur.x(), base.y() + y);
+                path = path.line_to(cur);
+                (None, None)
+            }
+            b'C' | b'S' => {
+                let p1 = if upper == b'C' {
+                    offset(n.point()?)
+                } else {
+                    // The reflection of the last control point
+                    last_cubic.map_or(cur, |p| {
+                        Point::new(2f64 * cur.x() - p.x(), 2f64 * cur.y() - p.y())
+                    })
+                };
+                let (p2, p3) = (offset(n.point()?), offset(n.point()?));
+                path = path.curve_to(p1, p2, p3);
+                cur = p3;
+                (Some(p2), None)
+            }
+            b'Q' | b'T' => {
+                let q = if upper == b'Q' {
+                    offset(n.point()?)
+                } else {
+                    last_quad.map_or(cur, |p| {
+                        Point::new(2f64 * cur.x() - p.x(), 2f64 * cur.y() - p.y())
+                    })
+                };
+                let to = offset(n.point()?);
+                // The same curve as a cubic bezier
+                let third = |a: Point, b: Point| {
+                    Point::new(
+                        a.x() + 2f64 / 3f64 * (b.x() - a.x()),
+                        a.y() + 2f64 / 3f64 * (b.y() - a.y()),
+                    )
+                };
+                path = path.curve_to(third(cur, q), third(to, q), to);
+                cur = to;
+                (None, Some(q))
+            }
+            b'A' => {
+                let (rx, ry, degrees) = (n.number()?, n.number()?, n.number()?);
+                let (large, sweep) = (n.flag()?, n.flag()?);
+                let to = offset(n.point()?);
+                path = endpoint_arc(path, cur, to, rx, ry, degrees, large, sweep);
+                cur = to;
+                (None, None)
+            }
+            b'Z' => {
+                path = path.close();
+                cur = start;
+                closed = true;
+                (None, None)
+            }
+            _ => return Err(invalid("Unknown command in SVG path data")),
+        };
+        last_cubic = cubic;
+        last_quad = quad;
+    }
+    Ok(path)
+}
+
+/// The points of a polyline or polygon
+fn points(value: &str, close: bool) -> io::Result<Path> {
+    let mut n = Numbers::new(value);
+    let mut path = Path::new().move_to(n.point()?);
+    while !n.at_end() {
+        path = path.line_to(n.point()?);
+    }
+    Ok(if close { path.close() } else { path })
+}
+
+/// Parses a transform list, such as `translate(10 20) rotate(45)`
+fn transform(value: &str) -> io::Result<Matrix> {
+    let mut matrix = Matrix::identity();
+    for item in value.split(')') {
+        let item = item.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if item.trim().is_empty() {
+            continue;
+        }
+        let (name, args) = item
+            .split_once('(')
+            .ok_or_else(|| invalid("Invalid SVG transform"))?;
+        let mut n = Numbers::new(args);
+        let mut args = vec![];
+        while !n.at_end() {
+            args.push(n.number()?);
+        }
+        let arg = |i: usize, default: f64| args.get(i).copied().unwrap_or(default);
+        let m = match (name.trim(), args.len()) {
+            ("matrix", 6) => Matrix::new(args[0], args[1], args[2], args[3], args[4], args[5]),
+            ("translate", 1 | 2) => Matrix::translate(args[0], arg(1, 0f64)),
+            ("scale", 1 | 2) => Matrix::scale(args[0], arg(1, args[0])),
+            ("rotate", 1) => Matrix::rotate(args[0].to_radians()),
+            ("rotate", 3) => Matrix::translate(-args[1], -args[2])
+                .then(&Matrix::rotate(args[0].to_radians()))
+                .then(&Matrix::translate(args[1], args[2])),
+            ("skewX", 1) => Matrix::new(1f64, 0f64, args[0].to_radians().tan(), 1f64, 0f64, 0f64),
+            ("skewY", 1) => Matrix::new(1f64, args[0].to_radians().tan(), 0f64, 1f64, 0f64, 0f64),
+            _ => return Err(invalid("Invalid SVG transform")),
+        };
+        // The last transform of the list is applied first
+        matrix = m.then(&matrix);
+    }
+    Ok(matrix)
+}
+
+/// Parses a paint, None for `none`
+fn color(value: &str) -> io::Result<Option<Color>> {
+    let value = value.trim();
+    let rgb = |r: u8, g: u8, b: u8| {
+        Ok(Some(Color::DeviceRGB(
+            f64::from(r) / 255f64,
+            f64::from(g) / 255f64,
+            f64::from(b) / 255f64,
+        )))
+    };
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("Invalid SVG color"))?;
+        return match digits.len() {
+            3 => rgb(digits[0] * 17, digits[1] * 17, digits[2] * 17),
+            6 => rgb(
+                digits[0] * 16 + digits[1],
+                digits[2] * 16 + digits[3],
+                digits[4] * 16 + digits[5],
+            ),
+            _ => Err(invalid("Invalid SVG color")),
+        };
+    }
+    if let Some(args) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let components: Vec<f64> = args
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f64>().map(|p| p / 100f64),
+                    None => c.parse::<f64>().map(|v| v / 255f64),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid("Invalid SVG color"))?;
+        if components.len() != 3 {
+            return Err(invalid("Invalid SVG color"));
+        }
+        let c = |i: usize| components[i].clamp(0f64, 1f64);
+        return Ok(Some(Color::DeviceRGB(c(0), c(1), c(2))));
+    }
+    match value.to_ascii_lowercase().as_str() {
+        "none" | "transparent" => Ok(None),
+        "black" => rgb(0, 0, 0),
+        "white" => rgb(255, 255, 255),
+        "red" => rgb(255, 0, 0),
+        "lime" => rgb(0, 255, 0),
+        "blue" => rgb(0, 0, 255),
+        "yellow" => rgb(255, 255, 0),
+        "aqua" | "cyan" => rgb(0, 255, 255),
+        "fuchsia" | "magenta" => rgb(255, 0, 255),
+        "gray" | "grey" => rgb(128, 128, 128),
+        "silver" => rgb(192, 192, 192),
+        "maroon" => rgb(128, 0, 0),
+        "green" => rgb(0, 128, 0),
+        "navy" => rgb(0, 0, 128),
+        "olive" => rgb(128, 128, 0),
+        "purple" => rgb(128, 0, 128),
+        "teal" => rgb(0, 128, 128),
+        "orange" => rgb(255, 165, 0),
+        _ => Err(invalid("Unsupported SVG color")),
+    }
+}
+
+/// The painting properties, inherited from the enclosing elements
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    width: f64,
+    winding: Winding,
+    matrix: Matrix,
+    /// Not drawn, such as the contents of `<defs>`
+    hidden: bool,
+}
+
+impl Style {
+    fn apply(&mut self, name: &str, value: &str) -> io::Result<()> {
+        match name.trim() {
+            "fill" => self.fill = color(value)?,
+            "stroke" => self.stroke = color(value)?,
+            "stroke-width" => self.width = length(value)?,
+            "fill-rule" => {
+                self.winding = if value.trim() == "evenodd" {
+                    Winding::EvenOdd
+                } else {
+                    Winding::NonZero
+                }
+            }
+            "display" => self.hidden |= value.trim() == "none",
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// A shape of the document, with the transform and line width it is
+/// drawn with
+#[derive(Debug, Clone)]
+struct Shape {
+    matrix: Matrix,
+    width: Option<f64>,
+    path: Rc<GraphicPath>,
+}
+
+/// A parsed SVG document, which can be drawn any number of times
+#[derive(Debug, Clone)]
+pub struct Svg {
+    view_box: Rect,
+    width: f64,
+    height: f64,
+    shapes: Vec<Shape>,
+}
+
+impl Svg {
+    /// Parses an SVG document, see the module docs for what is supported
+    ///
+    /// # Errors
+    ///
+    /// Returns an InvalidData error if the markup, or the attributes of
+    /// a supported element, can't be parsed, or the root isn't an
+    /// `<svg>` element with a viewBox or a width and height
+    pub fn parse(source: &str) -> io::Result<Self> {
+        let mut stack: Vec<Style> = vec![];
+        let mut svg = None;
+        let mut shapes = vec![];
+        for tag in tags(source)? {
+            let (name, attributes, empty) = match tag {
+                Tag::Start {
+                    name,
+                    attributes,
+                    empty,
+                } => (name, attributes, empty),
+                Tag::End => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            let attribute = |key: &str| {
+                attributes
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.as_str())
+            };
+            let number = |key: &str| attribute(key).map_or(Ok(0f64), length);
+            let mut style = match stack.last() {
+                Some(parent) => parent.clone(),
+                None => {
+                    if name != "svg" {
+                        return Err(invalid("The root of an SVG document must be <svg>"));
+                    }
+                    let size = (attribute("width"), attribute("height"));
+                    let view_box = match (attribute("viewBox"), size) {
+                        (Some(view_box), _) => {
+                            let mut n = Numbers::new(view_box);
+                            Rect::new(n.number()?, n.number()?, n.number()?, n.number()?)
+                        }
+                        (None, (Some(w), Some(h))) => Rect::new(0f64, 0f64, length(w)?, length(h)?),
+                        _ => return Err(invalid("An SVG document needs a viewBox or size")),
+                    };
+                    let width = size.0.map_or(Ok(view_box.width()), length)?;
+                    let height = size.1.map_or(Ok(view_box.height()), length)?;
+                    svg = Some((view_box, width, height));
+                    Style {
+                        fill: Some(Color::DeviceRGB(0f64, 0f64, 0f64)),
+                        stroke: None,
+                        width: 1f64,
+                        winding: Winding::NonZero,
+                        matrix: Matrix::identity(),
+                        hidden: false,
+                    }
+                }
+            };
+            if matches!(
+                name,
+                "defs"
+                    | "symbol"
+                    | "clipPath"
+                    | "mask"
+                    | "pattern"
+                    | "marker"
+                    | "linearGradient"
+                    | "radialGradient"
+                    | "title"
+                    | "desc"
+                    | "metadata"
+                    | "style"
+            ) {
+                style.hidden = true;
+            }
+            for (key, value) in attributes.iter() {
+                style.apply(key, value)?;
+            }
+            if let Some(declarations) = attribute("style") {
+                for declaration in declarations.split(';') {
+                    if let Some((key, value)) = declaration.split_once(':') {
+                        style.apply(key, value)?;
+                    }
+                }
+            }
+            if let Some(t) = attribute("transform") {
+                style.matrix = transform(t)?.then(&style.matrix);
+            }
+            let path = if style.hidden {
+                None
+            } else {
+                match name {
+                    "rect" => Some(Path::new().rect((
+                        number("x")?,
+                        number("y")?,
+                        number("width")?,
+                        number("height")?,
+                    ))),
+                    "circle" => {
+                        let r = number("r")?;
+                        Some(ellipse(Point::new(number("cx")?, number("cy")?), r, r))
+                    }
+                    "ellipse" => Some(ellipse(
+                        Point::new(number("cx")?, number("cy")?),
+                        number("rx")?,
+                        number("ry")?,
+                    )),
+                    "line" => Some(
+                        Path::new()
+                            .move_to((number("x1")?, number("y1")?))
+                            .line_to((number("x2")?, number("y2")?)),
+                    ),
+                    "polyline" | "polygon" => match attribute("points") {
+                        Some(p) => Some(points(p, name == "polygon")?),
+                        None => None,
+                    },
+                    "path" => match attribute("d") {
+                        Some(d) => Some(path_data(d)?),
+                        None => None,
+                    },
+                    _ => None,
+                }
+            };
+            if let Some(path) = path {
+                let path = path.winding(style.winding);
+                let stroke = style.stroke.clone().filter(|_| style.width > 0f64);
+                let painted = match (style.fill.clone(), stroke) {
+                    (Some(fill), Some(stroke)) => Some(path.stroke_fill(stroke, fill)),
+                    (Some(fill), None) => Some(path.fill(fill)),
+                    (None, Some(stroke)) => Some(path.stroke(stroke)),
+                    (None, None) => None,
+                };
+                if let Some(painted) = painted {
+                    shapes.push(Shape {
+                        matrix: style.matrix,
+                        width: style.stroke.as_ref().map(|_| style.width),
+                        path: painted,
+                    });
+                }
+            }
+            if !empty {
+                stack.push(style);
+            }
+        }
+        let (view_box, width, height) = svg.ok_or_else(|| invalid("Missing <svg> element"))?;
+        if view_box.width() <= 0f64 || view_box.height() <= 0f64 {
+            return Err(invalid("The viewBox of an SVG document must have an area"));
+        }
+        Ok(Self {
+            view_box,
+            width,
+            height,
+            shapes,
+        })
+    }
+    /// The width of the document, from its width attribute, or its
+    /// viewBox. Units are ignored
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    /// The height of the document, see width
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+    /// Draws the document, with its viewBox stretched to fill the rect
+    ///
+    /// The y-axis is flipped, so the document is drawn upright. Use
+    /// width and height to keep the aspect ratio.
+    pub fn draw(&self, rect: impl Into<Rect>) -> Rc<GraphicSvg> {
+        let rect = rect.into();
+        let v = self.view_box;
+        let (sx, sy) = (rect.width() / v.width(), rect.height() / v.height());
+        Rc::new(GraphicSvg {
+            params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+            matrix: Matrix::new(
+                sx,
+                0f64,
+                0f64,
+                -sy,
+                rect.x() - v.x() * sx,
+                rect.y() + rect.height() + v.y() * sy,
+            ),
+            shapes: self.shapes.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicSvg {
+    params: GraphicParameters,
+    matrix: Matrix,
+    shapes: Vec<Shape>,
+}
+
+impl Graphic for GraphicSvg {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn bounds(&self) -> Option<Rect> {
+        let corners = self.shapes.iter().filter_map(|shape| {
+            let m = shape.matrix.then(&self.matrix);
+            let bounds = shape.path.bounds()?;
+            Some(bounds.corners().map(|p| m.apply(p)))
+        });
+        Rect::enclosing(corners.flatten())
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        out.save_state();
+        out.command(&mut [self.matrix.into()], "cm");
+        for shape in self.shapes.iter() {
+            out.save_state();
+            if shape.matrix != Matrix::identity() {
+                out.command(&mut [shape.matrix.into()], "cm");
+            }
+            if let Some(width) = shape.width {
+                out.set_line_width(width);
+            }
+            out.draw(&*shape.path);
+            out.restore_state();
+        }
+        out.restore_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+
+    #[test]
+    fn rect_and_path() {
+        let svg = Svg::parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">
+                <rect x="10" y="10" width="30" height="20" fill="#ff0000"/>
+                <path d="M 50 50 L 90 90" fill="none" stroke="blue" stroke-width="2"/>
+            </svg>"##,
+        )
+        .unwrap();
+        let content = rendered(svg.draw((0f64, 0f64, 100f64, 100f64)));
+        assert!(content.contains("q /DeviceRGB cs  1 0 0 scn  10 10 30 20 re f Q"));
+        assert!(content.contains("q 2 w /DeviceRGB CS  0 0 1 SCN  50 50 m 90 90 l S Q"));
+    }
+}
@@ -1,5 +1,11 @@
-use super::{Color, Graphic, GraphicContext, GraphicParameters, Point};
-use crate::pdf::{Dict, Name, ObjRef, PDFData};
+use super::image::GraphicImage;
+use super::metrics::{self, Metrics};
+use super::truetype::{FontDescriptor, TrueType};
+use super::{
+    bidi, Color, Graphic, GraphicContext, GraphicParameters, GraphicsContextType, Matrix, Path,
+    Point, Rect,
+};
+use crate::pdf::{Dict, Name, ObjRef, Object, PDFData};
 use std::io::{self, Write};
 use std::rc::Rc;
 
@@ -52,6 +58,7 @@ impl<T: PartialEq<T>> PartialEq for Update<T> {
 struct TextPart {
     text: String,
     font: Option<(Rc<Font>, f64)>,
+    matrix: Option<Matrix>,
     pos: Option<Point>,
 }
 
@@ -60,16 +67,57 @@ pub struct Text {
     parts: Vec<TextPart>,
     font: Update<(Rc<Font>, f64)>,
     pos: Update<Point>,
+    matrix: Option<Matrix>,
+    fallback: Vec<Rc<Font>>,
+    bidi: bool,
+    /// Characters that aren't in the encoding of any of the fonts
+    unencodable: Vec<char>,
 }
 
+/// Characters of text that aren't in the encoding of any of its fonts,
+/// see Text::try_text
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodingError(pub Vec<char>);
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let chars: Vec<String> = self.0.iter().map(|c| format!("{:?}", c)).collect();
+        write!(f, "characters missing from the fonts: {}", chars.join(", "))
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
 impl Text {
     pub fn new(font: Rc<Font>, size: f64) -> Self {
         Self {
             parts: vec![],
             font: Update::New((font, size)),
             pos: Update::New((0f64, 0f64).into()),
+            matrix: None,
+            fallback: vec![],
+            bidi: false,
+            unencodable: vec![],
         }
     }
+    /// Reorders text added after this point from logical to visual order
+    ///
+    /// Right to left runs, such as Hebrew or Arabic, are reversed so they
+    /// are placed correctly in mixed direction text. See the bidi module
+    /// for the limitations.
+    pub fn bidi(mut self, enabled: bool) -> Self {
+        self.bidi = enabled;
+        self
+    }
+    /// Sets the text matrix (`Tm`) for the text added after this point
+    ///
+    /// Positions from move_to are then relative to the matrix, which
+    /// can rotate, scale or skew the text
+    pub fn matrix(mut self, m: Matrix) -> Self {
+        self.matrix = Some(m);
+        self.pos = Update::Old(Point::new(0f64, 0f64));
+        self
+    }
     pub fn move_to(mut self, p: impl Into<Point>) -> Self {
         self.pos.replace(p.into());
         self
@@ -82,20 +130,215 @@ impl Text {
         self.font.replace((self.font.unwrap().0.clone(), size));
         self
     }
+    /// Sets the fonts used, in order, for characters the current
+    /// font doesn't have a glyph for
+    ///
+    /// The text is split into runs, so each character is shown with the
+    /// first font that has it. Characters no font has are shown with the
+    /// current font. See Font::has_glyph
+    pub fn fallback(mut self, fonts: Vec<Rc<Font>>) -> Self {
+        self.fallback = fonts;
+        self
+    }
+    /// The font and code of the character, from the first font of the
+    /// fallbacks that has a glyph for it, or None if the character
+    /// can't be written in any of the fonts
+    fn encode(&self, c: char) -> Option<(Rc<Font>, char)> {
+        let primary = &self.font.unwrap().0;
+        let (font, code) = std::iter::once(primary)
+            .chain(self.fallback.iter())
+            .find_map(|f| f.encode(c).map(|code| (f, code)))
+            .unwrap_or((primary, c));
+        if u32::from(code) <= 0xFF {
+            Some((font.clone(), code))
+        } else {
+            None
+        }
+    }
+    /// Adds text at the current position
+    ///
+    /// Each character is written as its code in the font's encoding,
+    /// from the first font of the fallbacks that has a glyph for it.
+    /// Characters that aren't in the encoding of any of the fonts, and
+    /// aren't a single byte, such as `'中'` with the standard fonts, are
+    /// left out, and PDF::write returns an error for them. See try_text
+    /// to check the text when it is added instead.
     pub fn text(mut self, p: impl Into<String>) -> Self {
-        self.parts.push(TextPart {
-            text: p.into(),
-            font: self.font.update(),
-            pos: self.pos.update(),
-        });
+        let text = p.into();
+        let text = if self.bidi {
+            bidi::reorder(&text)
+        } else {
+            text
+        };
+        let (primary, size) = self.font.unwrap().clone();
+        let mut runs: Vec<(Rc<Font>, String)> = vec![];
+        for c in text.chars() {
+            let (font, code) = match self.encode(c) {
+                Some(encoded) => encoded,
+                None => {
+                    self.unencodable.push(c);
+                    continue;
+                }
+            };
+            match runs.last_mut() {
+                Some((f, run)) if Rc::ptr_eq(f, &font) => run.push(code),
+                _ => runs.push((font, code.to_string())),
+            }
+        }
+        if runs.is_empty() {
+            runs.push((primary.clone(), text));
+        }
+        for (font, run) in runs {
+            self.font.replace((font, size));
+            self.parts.push(TextPart {
+                text: run,
+                font: self.font.update(),
+                matrix: self.matrix.take(),
+                pos: self.pos.update(),
+            });
+        }
+        self.font.replace((primary, size));
         self
     }
-    pub fn fill(mut self, color: Color) -> GraphicText {
+    /// Adds text at the current position, see text
+    ///
+    /// # Errors
+    ///
+    /// Returns the characters that aren't in the encoding of any of the
+    /// fonts, and aren't a single byte, such as `'中'` with the standard
+    /// fonts
+    pub fn try_text(self, p: impl Into<String>) -> Result<Self, EncodingError> {
+        let text = p.into();
+        let mut missing: Vec<char> = vec![];
+        for c in text.chars() {
+            if self.encode(c).is_none() && !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+        if missing.is_empty() {
+            Ok(self.text(text))
+        } else {
+            Err(EncodingError(missing))
+        }
+    }
+    /// Adds text along the path, such as a label following a curve
+    ///
+    /// - path: the path the baseline follows, from its start. Only the
+    ///   first subpath is followed
+    /// - text: the text, which is cut off where the path ends
+    ///
+    /// Each character is placed with its own text matrix (`Tm`), at its
+    /// distance along the path, and rotated to the direction of the path
+    /// at its center.
+    pub fn on_path(mut self, path: &Path, text: &str) -> Self {
+        let points = path.flatten();
+        if points.len() < 2 {
+            return self;
+        }
+        // The distance along the path of each point
+        let mut lengths = vec![0f64];
+        for w in points.windows(2) {
+            let (dx, dy) = (w[1].x() - w[0].x(), w[1].y() - w[0].y());
+            lengths.push(lengths.last().unwrap() + dx.hypot(dy));
+        }
+        let total = *lengths.last().unwrap();
+        // The point and direction at a distance along the path
+        let at = |d: f64| {
+            let i = lengths.iter().rposition(|l| *l <= d).unwrap_or(0);
+            let i = i.min(points.len().saturating_sub(2));
+            let (a, b) = (points[i], points[i + 1]);
+            let len = lengths[i + 1] - lengths[i];
+            let t = if len > 0f64 {
+                (d - lengths[i]) / len
+            } else {
+                0f64
+            };
+            let angle = (b.y() - a.y()).atan2(b.x() - a.x());
+            let p = Point::new(a.x() + (b.x() - a.x()) * t, a.y() + (b.y() - a.y()) * t);
+            (p, angle)
+        };
+        let (font, size) = self.font.unwrap().clone();
+        let mut distance = 0f64;
+        for c in text.chars() {
+            let width = font.text_width(&c.to_string(), size);
+            if distance + width > total {
+                break;
+            }
+            let (center, angle) = at(distance + width / 2f64);
+            let (cos, sin) = (angle.cos(), angle.sin());
+            let origin = Point::new(
+                center.x() - cos * width / 2f64,
+                center.y() - sin * width / 2f64,
+            );
+            self = self
+                .matrix(Matrix::new(cos, sin, -sin, cos, origin.x(), origin.y()))
+                .text(c.to_string());
+            distance += width;
+        }
+        self
+    }
+    /// Centers each line of the text horizontally, between 0 and width
+    ///
+    /// Only the text added so far is centered. The x of each line is
+    /// replaced, using the width of the line from Font::text_width, so
+    /// width is normally the width of the page. For pages whose MediaBox
+    /// doesn't start at 0, use centered_in.
+    pub fn centered_on(self, width: f64) -> Self {
+        self.centered_in(Rect::new(0f64, 0f64, width, 0f64))
+    }
+    /// Centers each line of the text horizontally in the rect, such as
+    /// the MediaBox of the page, see centered_on
+    ///
+    /// Only the left and right of the rect are used.
+    pub fn centered_in(mut self, rect: impl Into<Rect>) -> Self {
+        let rect = rect.into();
+        let mut font: Option<(Rc<Font>, f64)> = None;
+        // The part starting each line, and the width of the line
+        let mut lines: Vec<(usize, f64)> = vec![];
+        for (i, part) in self.parts.iter().enumerate() {
+            if let Some(f) = &part.font {
+                font = Some(f.clone());
+            }
+            let w = font
+                .as_ref()
+                .map_or(0f64, |(f, size)| f.text_width(&part.text, *size));
+            match lines.last_mut() {
+                Some((_, line)) if part.pos.is_none() => *line += w,
+                _ => lines.push((i, w)),
+            }
+        }
+        for (i, w) in lines {
+            if let Some(pos) = &mut self.parts[i].pos {
+                *pos = Point::new(rect.x() + (rect.width() - w) / 2f64, pos.y());
+            }
+        }
+        self
+    }
+    pub fn fill(self, color: Color) -> GraphicText {
         GraphicText {
             parts: self.parts,
             // fill: Some(color),
             // stroke: None,
             params: GraphicParameters::with_colors(Some(color), None),
+            clip: false,
+            unencodable: self.unencodable,
+        }
+    }
+    /// Fills the text with an image, by drawing the image with the text
+    /// as the clipping path (text rendering mode 7)
+    ///
+    /// - image: the image, placed as it would be without the text, such
+    ///   as from Image::draw with the bounds of the text
+    pub fn fill_image(self, image: Rc<GraphicImage>) -> GraphicTextClip {
+        GraphicTextClip {
+            params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+            text: GraphicText {
+                parts: self.parts,
+                params: GraphicParameters::with_type(GraphicsContextType::NoColor),
+                clip: true,
+                unencodable: self.unencodable,
+            },
+            image,
         }
     }
 }
@@ -107,40 +350,138 @@ impl Text {
 pub struct GraphicText {
     parts: Vec<TextPart>,
     params: GraphicParameters,
+    /// Whether the text is added to the clipping path, instead of filled
+    clip: bool,
+    /// Characters left out of the text, see Text::text
+    unencodable: Vec<char>,
 }
 
 impl Graphic for GraphicText {
     fn get_graphics_parameters(&self) -> &GraphicParameters {
         &self.params
     }
+    /// Approximate bounds, from the width of the text, and the font size
+    /// above and a fifth of it below each baseline
+    fn bounds(&self) -> Option<Rect> {
+        let mut points = vec![];
+        let (mut line, mut cursor) = (Point::new(0f64, 0f64), 0f64);
+        let mut matrix = Matrix::identity();
+        let mut font = None;
+        for part in self.parts.iter() {
+            if let Some(f) = &part.font {
+                font = Some(f);
+            }
+            if let Some(m) = part.matrix {
+                matrix = m;
+                line = Point::new(0f64, 0f64);
+                cursor = 0f64;
+            }
+            if let Some(pos) = part.pos {
+                line = pos;
+                cursor = 0f64;
+            }
+            let (font, size) = font?;
+            let start = cursor;
+            cursor += font.text_width(&part.text, *size);
+            let rect = Rect::new(
+                line.x() + start,
+                line.y() - size / 5f64,
+                cursor - start,
+                size * 1.2,
+            );
+            points.extend(rect.corners().iter().map(|p| matrix.apply(*p)));
+        }
+        Rect::enclosing(points)
+    }
     fn render(&self, out: &mut GraphicContext) {
+        out.add_unencodable(&self.unencodable);
         out.command(&mut [], "BT");
+        // When the y-axis points down, the text space is flipped back so
+        // the glyphs are upright, which also flips the y of Td
+        let (flip, dir) = if out.flipped {
+            (Matrix::scale(1f64, -1f64), -1f64)
+        } else {
+            (Matrix::identity(), 1f64)
+        };
+        if out.flipped {
+            out.command(&mut [flip.into()], "Tm");
+        }
+        if self.clip {
+            out.command(&mut [7usize.into()], "Tr");
+        }
+        // Td is relative to the start of the previous line
+        let mut line = Point::new(0f64, 0f64);
+        let mut matrix = Matrix::identity();
+        let mut current = None;
         for part in self.parts.iter() {
             if let Some((font, size)) = &part.font {
                 out.add_font(font.clone());
                 out.command(&mut [font.name.clone().into(), (*size).into()], "Tf");
+                current = Some(font);
+            }
+            if let Some(m) = part.matrix {
+                out.command(&mut [flip.then(&m).into()], "Tm");
+                line = Point::new(0f64, 0f64);
+                matrix = m;
             }
             if let Some(pos) = part.pos {
-                out.command(&mut [pos.into()], "Td");
+                let offset = Point::new(pos.x() - line.x(), (pos.y() - line.y()) * dir);
+                out.command(&mut [offset.into()], "Td");
+                line = pos;
+            }
+            if !self.clip {
+                out.add_text_color(self.params.fill(), matrix.apply(line));
             }
-            out.command(&mut [(&part.text).into()], "Tj");
+            // Each character is a code below 256, written as one byte
+            let codes: Vec<u8> = part.text.chars().map(|c| c as u8).collect();
+            if let Some(font) = current {
+                font.mark_used(&codes);
+            }
+            out.command(&mut [codes.as_slice().into()], "Tj");
         }
         out.command(&mut [], "ET");
     }
 }
+/// Text filled with an image, see Text::fill_image
 #[derive(Debug)]
+pub struct GraphicTextClip {
+    params: GraphicParameters,
+    text: GraphicText,
+    image: Rc<GraphicImage>,
+}
+
+impl Graphic for GraphicTextClip {
+    fn get_graphics_parameters(&self) -> &GraphicParameters {
+        &self.params
+    }
+    fn bounds(&self) -> Option<Rect> {
+        self.text.bounds()
+    }
+    fn render(&self, out: &mut GraphicContext) {
+        // The clipping path, and the rendering mode, only last until Q
+        out.save_state();
+        out.draw(&self.text);
+        out.draw(&*self.image);
+        out.restore_state();
+    }
+}
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
 enum FontType {
     Type1,
     MMType1,
+    TrueType,
 }
 impl FontType {
     fn to_name(&self) -> Rc<Name> {
         match self {
             Self::Type1 => Name::new("Type1"),
             Self::MMType1 => Name::new("MMType1"),
+            Self::TrueType => Name::new("TrueType"),
         }
     }
 }
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct FontObject {
     // /Type /Font
@@ -150,20 +491,21 @@ pub struct FontObject {
     first_char: Option<ObjRef<usize>>,
     last_char: Option<ObjRef<usize>>,
     widths: Option<ObjRef<usize>>,
-    font_descriptor: Option<ObjRef<usize>>,
+    font_descriptor: Option<Rc<ObjRef<FontDescriptor>>>,
     // Fully optional
-    encoding: Option<ObjRef<usize>>,
+    encoding: Option<Rc<FontEncoding>>,
     to_unicode: Option<ObjRef<usize>>,
 }
 impl FontObject {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         subtype: FontType,
         base_font: Rc<Name>,
         first_char: Option<ObjRef<usize>>,
         last_char: Option<ObjRef<usize>>,
         widths: Option<ObjRef<usize>>,
-        font_descriptor: Option<ObjRef<usize>>,
-        encoding: Option<ObjRef<usize>>,
+        font_descriptor: Option<Rc<ObjRef<FontDescriptor>>>,
+        encoding: Option<Rc<FontEncoding>>,
         to_unicode: Option<ObjRef<usize>>,
     ) -> Rc<Self> {
         Rc::new(Self {
@@ -180,18 +522,158 @@ impl FontObject {
 }
 impl PDFData for FontObject {
     fn write(&self, o: &mut dyn Write) -> io::Result<()> {
-        Dict::from_vec(vec![
+        let dict = Dict::from_vec(vec![
             ("Type", Name::new("Font")),
             ("Subtype", self.subtype.to_name()),
-            ("BaseFont", self.base_font.clone()),
-        ])
-        .write(o)
+        ]);
+        match &self.font_descriptor {
+            Some(descriptor) => {
+                dict.add_entry("BaseFont", Name::new(descriptor.font().subset_name()?));
+                for (key, value) in descriptor.widths() {
+                    dict.add_entry(key, value);
+                }
+                dict.add_entry("FontDescriptor", descriptor.clone());
+            }
+            None => dict.add_entry("BaseFont", self.base_font.clone()),
+        }
+        dict.add_optional(
+            "Encoding",
+            self.encoding.clone().map(|e| e as Rc<dyn PDFData>),
+        );
+        dict.write(o)
+    }
+    /// The descriptor isn't counted by references, so minimize_objects
+    /// keeps it indirect, as the format requires
+    fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
+        self.font_descriptor
+            .iter()
+            .map(|d| d.clone() as Rc<dyn Object>)
+            .collect()
+    }
+}
+
+/// The predefined encodings of simple fonts
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BaseEncoding {
+    /// Adobe's standard Latin encoding, the built-in encoding of the
+    /// standard text fonts
+    Standard,
+    /// The Windows code page 1252
+    WinAnsi,
+    /// The Mac OS standard Roman encoding
+    MacRoman,
+    /// The encoding of Adobe's expert fonts, such as small capitals
+    MacExpert,
+}
+impl BaseEncoding {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Standard => "StandardEncoding",
+            Self::WinAnsi => "WinAnsiEncoding",
+            Self::MacRoman => "MacRomanEncoding",
+            Self::MacExpert => "MacExpertEncoding",
+        }
+    }
+}
+
+/// The encoding of a simple font (`/Encoding`), mapping character codes
+/// to glyph names
+///
+/// A base encoding with no differences is written as the name of the
+/// encoding, otherwise as an encoding dictionary with a `/Differences`
+/// array. See Font::with_encoding
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontEncoding {
+    base: Option<BaseEncoding>,
+    differences: Vec<(u8, Vec<String>)>,
+}
+
+impl FontEncoding {
+    /// - base: the encoding the differences are applied to, None for
+    ///   the built-in encoding of the font
+    pub fn new(base: Option<BaseEncoding>) -> Self {
+        Self {
+            base,
+            differences: vec![],
+        }
+    }
+    /// Maps consecutive character codes to glyphs
+    ///
+    /// - first: the code of the first glyph
+    /// - glyphs: the glyph names, such as `["Euro", "bullet"]`, for
+    ///   first, first + 1, and so on
+    ///
+    /// # Panics
+    ///
+    /// panics if the glyphs run past code 255
+    pub fn differences(mut self, first: u8, glyphs: &[&str]) -> Self {
+        assert!(
+            first as usize + glyphs.len() <= 256,
+            "Character codes must be below 256"
+        );
+        self.differences
+            .push((first, glyphs.iter().map(|g| g.to_string()).collect()));
+        self
+    }
+    /// A suffix making the resource names of fonts with different
+    /// encodings unique
+    ///
+    /// The differences are spelled out, each run as `-code` followed by
+    /// `_glyph` for its glyphs. Characters that can't appear in a name,
+    /// and the separators themselves, are written as `~xx` in hex.
+    fn key(&self) -> String {
+        let mut key = match self.base {
+            Some(base) => base.name().trim_end_matches("Encoding").to_string(),
+            None => "Builtin".to_string(),
+        };
+        for (first, glyphs) in self.differences.iter() {
+            key.push_str(&format!("-{}", first));
+            for glyph in glyphs {
+                key.push('_');
+                for b in glyph.bytes() {
+                    if b.is_ascii_alphanumeric() || b == b'.' {
+                        key.push(b as char);
+                    } else {
+                        key.push_str(&format!("~{:02x}", b));
+                    }
+                }
+            }
+        }
+        key
+    }
+}
+
+impl PDFData for FontEncoding {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        match self.base {
+            Some(base) if self.differences.is_empty() => Name::new(base.name()).write(o),
+            _ => {
+                let dict = Dict::from_vec(vec![("Type", Name::new("Encoding"))]);
+                if let Some(base) = self.base {
+                    dict.add_entry("BaseEncoding", Name::new(base.name()));
+                }
+                let mut differences: Vec<Rc<dyn PDFData>> = vec![];
+                for (first, glyphs) in self.differences.iter() {
+                    differences.push(Rc::new(*first as usize));
+                    differences.extend(glyphs.iter().map(|g| Name::new(g) as Rc<dyn PDFData>));
+                }
+                dict.add_entry("Differences", Rc::new(differences));
+                dict.write(o)
+            }
+        }
     }
 }
+/// The encoding of the standard text fonts, so the characters of
+/// Windows code page 1252 can be used, such as curly quotes and the euro
+fn win_ansi() -> Option<Rc<FontEncoding>> {
+    Some(Rc::new(FontEncoding::new(Some(BaseEncoding::WinAnsi))))
+}
 #[derive(Debug)]
 pub struct Font {
     name: Rc<Name>,
     object: Rc<ObjRef<FontObject>>,
+    /// The widths of a standard font, or None for an embedded font
+    metrics: Option<&'static Metrics>,
 }
 impl Font {
     /// Internal Object for constructing pdf
@@ -202,10 +684,134 @@ impl Font {
     pub fn object(&self) -> Rc<ObjRef<FontObject>> {
         self.object.clone()
     }
+    /// The same font with a different encoding, see FontEncoding
+    ///
+    /// The font gets its own resource name, so both can be used on the
+    /// same page. Text is written as character codes, so a glyph mapped
+    /// to code 0x80 by the differences is drawn by `'\u{80}'`. The
+    /// widths and the subset of an embedded TrueType font still follow
+    /// WinAnsiEncoding, see from_truetype.
+    pub fn with_encoding(&self, encoding: FontEncoding) -> Rc<Self> {
+        let name = format!(
+            "{}{}",
+            self.name.to_string().trim_start_matches('/'),
+            encoding.key()
+        );
+        let object = &self.object;
+        Rc::new(Self {
+            name: Name::new(name),
+            metrics: self.metrics,
+            object: ObjRef::new(
+                0,
+                FontObject::new(
+                    object.subtype.clone(),
+                    object.base_font.clone(),
+                    None,
+                    None,
+                    None,
+                    object.font_descriptor.clone(),
+                    Some(Rc::new(encoding)),
+                    None,
+                ),
+            ),
+        })
+    }
+    /// Whether the font has a glyph for the character
+    ///
+    /// The standard fonts only have the characters of their encoding:
+    /// WinAnsiEncoding for the text fonts, and Greek letters and some
+    /// mathematical symbols for Symbol. TrueType fonts have the
+    /// characters of WinAnsiEncoding that their cmap has glyphs for.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.encode(c).is_some()
+    }
+    /// The character code for c, as a character
+    pub(super) fn encode(&self, c: char) -> Option<char> {
+        match self.metrics {
+            Some(metrics) => metrics.encode(c),
+            None => self.embedded()?.encode(c),
+        }
+    }
+    /// Width of a character, in thousandths of the font size
+    pub fn char_width(&self, c: char) -> f64 {
+        match self.metrics {
+            Some(metrics) => metrics.width(c) as f64,
+            None => self
+                .embedded()
+                .map_or(0f64, |font| font.width(font.encode(c).unwrap_or(c))),
+        }
+    }
+    /// The TrueType font embedded for the font, see from_truetype
+    fn embedded(&self) -> Option<&Rc<TrueType>> {
+        self.object.font_descriptor.as_ref().map(|d| d.font())
+    }
+    /// Records the codes as drawn in the document, so an embedded
+    /// font's subset has their glyphs
+    pub(super) fn mark_used(&self, codes: &[u8]) {
+        if let Some(font) = self.embedded() {
+            font.mark_used(codes);
+        }
+    }
+    /// Width of the text, in points, when drawn at the given size
+    pub fn text_width(&self, text: &str, size: f64) -> f64 {
+        text.chars().map(|c| self.char_width(c)).sum::<f64>() * size / 1000f64
+    }
+    /// The largest size, up to max_size, at which the text is no wider
+    /// than max_width, see text_width
+    ///
+    /// Text without width, such as an empty string, is given max_size
+    pub fn fit_size(&self, text: &str, max_width: f64, max_size: f64) -> f64 {
+        let width = self.text_width(text, 1f64);
+        if width <= 0f64 {
+            return max_size;
+        }
+        let mut size = (max_width / width).min(max_size);
+        // Rounding may make the width slightly larger than max_width
+        while size > 0f64 && self.text_width(text, size) > max_width {
+            size *= 1f64 - f64::EPSILON;
+        }
+        size
+    }
+    /// A TrueType font, embedded in the documents it is used in
+    ///
+    /// - data: the complete contents of the `.ttf` file
+    ///
+    /// Text is written in WinAnsiEncoding, like the standard fonts, and
+    /// each document embeds a subset of the font, with only the glyphs
+    /// of the characters drawn in it (`/FontFile2`). The name of the
+    /// subset is the PostScript name of the font, with a tag made from
+    /// the glyphs, such as `/ABCDEF+DejaVuSans`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an InvalidData error if the file is not a TrueType font,
+    /// or is missing a table needed to embed it
+    pub fn from_truetype(data: Vec<u8>) -> io::Result<Rc<Self>> {
+        let font = Rc::new(TrueType::parse(data)?);
+        let name = Name::new(font.name());
+        Ok(Rc::new(Self {
+            name: name.clone(),
+            metrics: None,
+            object: ObjRef::new(
+                0,
+                FontObject::new(
+                    FontType::TrueType,
+                    name,
+                    None,
+                    None,
+                    None,
+                    Some(FontDescriptor::new(font)),
+                    win_ansi(),
+                    None,
+                ),
+            ),
+        }))
+    }
     /// One of the 14 standard fonts
     pub fn times_new_roman() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("timesroman"),
+            metrics: Some(&metrics::TIMES_ROMAN),
             object: ObjRef::new(
                 0,
                 FontObject::new(
@@ -215,7 +821,7 @@ impl Font {
                     None,
                     None,
                     None,
-                    None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -225,6 +831,7 @@ impl Font {
     pub fn helvetica() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("helvetica"),
+            metrics: Some(&metrics::HELVETICA),
             object: ObjRef::new(
                 0,
                 FontObject::new(
@@ -234,7 +841,7 @@ impl Font {
                     None,
                     None,
                     None,
-                    None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -244,6 +851,7 @@ impl Font {
     pub fn courier() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("courier"),
+            metrics: Some(&metrics::COURIER),
             object: ObjRef::new(
                 0,
                 FontObject::new(
@@ -253,7 +861,7 @@ impl Font {
                     None,
                     None,
                     None,
-                    None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -263,6 +871,7 @@ impl Font {
     pub fn symbol() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("symbol"),
+            metrics: Some(&metrics::SYMBOL),
             object: ObjRef::new(
                 0,
                 FontObject::new(
@@ -282,16 +891,17 @@ impl Font {
     pub fn times_bold() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("timesbold"),
+            metrics: Some(&metrics::TIMES_BOLD),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Times−Bold"),
-                    None,
+                    Name::new("Times-Bold"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -301,16 +911,17 @@ impl Font {
     pub fn helvetica_bold() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("helveticabold"),
+            metrics: Some(&metrics::HELVETICA_BOLD),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("helveticabold"),
-                    None,
+                    Name::new("Helvetica-Bold"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -320,16 +931,17 @@ impl Font {
     pub fn courier_bold() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("courierbold"),
+            metrics: Some(&metrics::COURIER),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Courier−Bold"),
-                    None,
+                    Name::new("Courier-Bold"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -339,6 +951,7 @@ impl Font {
     pub fn zapf_dingbats() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("zapfdingbats"),
+            metrics: Some(&metrics::ZAPF_DINGBATS),
             object: ObjRef::new(
                 0,
                 FontObject::new(
@@ -358,16 +971,17 @@ impl Font {
     pub fn times_italic() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("timesitalic"),
+            metrics: Some(&metrics::TIMES_ITALIC),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Times−Italic"),
-                    None,
+                    Name::new("Times-Italic"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -377,16 +991,17 @@ impl Font {
     pub fn helvetica_oblique() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("helveticaoblique"),
+            metrics: Some(&metrics::HELVETICA),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("helveticaoblique"),
-                    None,
+                    Name::new("Helvetica-Oblique"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -396,16 +1011,17 @@ impl Font {
     pub fn courier_oblique() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("courieroblique"),
+            metrics: Some(&metrics::COURIER),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Courier−Oblique"),
-                    None,
+                    Name::new("Courier-Oblique"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -415,16 +1031,17 @@ impl Font {
     pub fn times_bold_italic() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("timesbolditalic"),
+            metrics: Some(&metrics::TIMES_BOLD_ITALIC),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Times−BoldItalic"),
-                    None,
+                    Name::new("Times-BoldItalic"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -434,16 +1051,17 @@ impl Font {
     pub fn helvetica_bold_oblique() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("helveticaboldoblique"),
+            metrics: Some(&metrics::HELVETICA_BOLD),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Helvetica−BoldOblique"),
-                    None,
+                    Name::new("Helvetica-BoldOblique"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -453,16 +1071,17 @@ impl Font {
     pub fn courier_bold_oblique() -> Rc<Self> {
         Rc::new(Self {
             name: Name::new("courierboldoblique"),
+            metrics: Some(&metrics::COURIER),
             object: ObjRef::new(
                 0,
                 FontObject::new(
                     FontType::Type1,
-                    Name::new("Courier−BoldOblique"),
-                    None,
+                    Name::new("Courier-BoldOblique"),
                     None,
                     None,
                     None,
                     None,
+                    win_ansi(),
                     None,
                 ),
             ),
@@ -738,3 +1357,252 @@ mod pdf_doc_encode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::tests::rendered;
+    use crate::graphics::Image;
+    use crate::pdf::Object;
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    /// The content stream of the graphic
+    fn content(g: GraphicText) -> Vec<u8> {
+        let mut ctx = GraphicContext::new();
+        ctx.render(Rc::new(g));
+        ctx.into_stream().0.data().to_vec()
+    }
+
+    #[test]
+    fn win_ansi_bytes() {
+        let g = Text::new(Font::helvetica(), 12f64)
+            .text("café €")
+            .fill(Color::DeviceGray(0f64));
+        let content = content(g);
+        assert!(content.windows(8).any(|w| w == b"(caf\xE9 \x80)"));
+    }
+
+    #[test]
+    fn win_ansi_encoding() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.text_at((10f64, 10f64), "café €");
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/Encoding /WinAnsiEncoding"));
+    }
+
+    #[test]
+    fn try_text_unencodable() {
+        let text = Text::new(Font::helvetica(), 12f64).try_text("中 Ā 中");
+        assert_eq!(text, Err(EncodingError(vec!['中', 'Ā'])));
+        assert!(Text::new(Font::helvetica(), 12f64)
+            .try_text("café €")
+            .is_ok());
+    }
+
+    #[test]
+    fn unencodable_text_is_a_write_error() {
+        let mut pdf = crate::PDF::new(Box::new(std::io::sink()));
+        let mut page = Page::new();
+        page.text_at((10f64, 10f64), "1 中");
+        pdf.add_page(page);
+        let err = pdf.write().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("'中'"));
+    }
+
+    #[test]
+    fn fallback_font() {
+        let text = Text::new(Font::helvetica(), 12f64)
+            .fallback(vec![Font::symbol()])
+            .text("ok \u{3B1}")
+            .fill(Color::DeviceGray(0f64));
+        // The alpha is only in the Symbol font, as code `a`
+        assert_eq!(
+            content(text),
+            b" BT /helvetica 12 Tf 0 0 Td (ok ) Tj /symbol 12 Tf (a) Tj ET"
+        );
+    }
+
+    #[test]
+    fn centered_on() {
+        let font = Font::helvetica();
+        let width = font.text_width("Hello", 12f64);
+        let text = Text::new(font, 12f64)
+            .move_to((0f64, 100f64))
+            .text("Hello")
+            .centered_on(612f64)
+            .fill(Color::DeviceGray(0f64));
+        let expected = format!(
+            " BT /helvetica 12 Tf {} 100 Td (Hello) Tj ET",
+            (612f64 - width) / 2f64
+        );
+        assert_eq!(content(text), expected.as_bytes());
+    }
+
+    #[test]
+    fn fill_image() {
+        let image = Image::from_gray(1, 1, vec![0]).draw(Rect::new(0f64, 0f64, 40f64, 12f64));
+        let clip = Text::new(Font::helvetica(), 12f64)
+            .text("Hi")
+            .fill_image(image);
+        assert_eq!(
+            rendered(Rc::new(clip)),
+            " q BT 7 Tr /helvetica 12 Tf 0 0 Td (Hi) Tj ET q 40 0 0 12 0 0 cm /Im1 Do Q Q"
+        );
+    }
+
+    #[test]
+    fn on_path() {
+        // A quarter circle around the origin, counterclockwise
+        let k = 100f64 * 0.5523f64;
+        let arc = Path::from((100f64, 0f64)).curve_to((100f64, k), (k, 100f64), (0f64, 100f64));
+        let g = Text::new(Font::helvetica(), 12f64)
+            .on_path(&arc, "ABC")
+            .fill(Color::DeviceGray(0f64));
+        let content = String::from_utf8(content(g)).unwrap();
+        // The first four operands of each Tm, the rotation
+        let mut rotations: Vec<Vec<&str>> = content
+            .split(" Tm ")
+            .take(3)
+            .map(|part| part.split(' ').rev().skip(2).take(4).collect())
+            .collect();
+        assert_eq!(content.matches(" Tm ").count(), 3);
+        rotations.sort();
+        rotations.dedup();
+        assert_eq!(rotations.len(), 3);
+    }
+
+    #[test]
+    fn encoding_differences() {
+        let file = text(&written(|pdf| {
+            let font = Font::helvetica().with_encoding(
+                FontEncoding::new(Some(BaseEncoding::WinAnsi)).differences(128, &["bullet"]),
+            );
+            let mut page = Page::new();
+            page.add(Rc::new(
+                Text::new(font, 12f64)
+                    .text("\u{80}")
+                    .fill(Color::DeviceGray(0f64)),
+            ));
+            pdf.add_page(page);
+        }));
+        assert!(file.contains(
+            "/Encoding <<\n/Type /Encoding\n/BaseEncoding /WinAnsiEncoding\n/Differences [128 /bullet]\n>>"
+        ));
+    }
+
+    #[test]
+    fn encoding_key() {
+        let key = |encoding: FontEncoding| encoding.key();
+        let win_ansi = || FontEncoding::new(Some(BaseEncoding::WinAnsi));
+        assert_eq!(key(win_ansi()), "WinAnsi");
+        assert_eq!(
+            key(win_ansi().differences(128, &["bullet", "a_b"])),
+            "WinAnsi-128_bullet_a~5fb"
+        );
+        // Runs and glyphs can't be confused with each other
+        assert_ne!(
+            key(win_ansi().differences(1, &[]).differences(2, &["x"])),
+            key(win_ansi().differences(1, &["2", "x"]))
+        );
+        assert_ne!(
+            key(win_ansi().differences(1, &["a_b"])),
+            key(win_ansi().differences(1, &["a", "b"]))
+        );
+    }
+
+    #[test]
+    fn fit_size() {
+        let font = Font::helvetica();
+        let heading = "A heading much too long for its column";
+        let size = font.fit_size(heading, 120f64, 24f64);
+        assert!(size < 24f64);
+        assert!(font.text_width(heading, size) <= 120f64);
+        assert!(font.text_width(heading, size) > 119f64);
+        assert_eq!(font.fit_size("Hi", 120f64, 24f64), 24f64);
+    }
+
+    #[test]
+    fn standard_font_names() {
+        let fonts = [
+            (Font::times_new_roman(), "Times-Roman"),
+            (Font::times_bold(), "Times-Bold"),
+            (Font::times_italic(), "Times-Italic"),
+            (Font::times_bold_italic(), "Times-BoldItalic"),
+            (Font::helvetica(), "Helvetica"),
+            (Font::helvetica_bold(), "Helvetica-Bold"),
+            (Font::helvetica_oblique(), "Helvetica-Oblique"),
+            (Font::helvetica_bold_oblique(), "Helvetica-BoldOblique"),
+            (Font::courier(), "Courier"),
+            (Font::courier_bold(), "Courier-Bold"),
+            (Font::courier_oblique(), "Courier-Oblique"),
+            (Font::courier_bold_oblique(), "Courier-BoldOblique"),
+            (Font::symbol(), "Symbol"),
+            (Font::zapf_dingbats(), "ZapfDingbats"),
+        ];
+        for (font, name) in fonts {
+            let mut data = vec![];
+            font.object().write_data(&mut data).unwrap();
+            let expected = format!("/BaseFont /{}\n", name);
+            assert!(
+                data.windows(expected.len())
+                    .any(|w| w == expected.as_bytes()),
+                "{}",
+                name
+            );
+        }
+    }
+    #[test]
+    fn truetype_subset() {
+        let full = crate::graphics::truetype::tests::font_file();
+        let font = Font::from_truetype(full.clone()).unwrap();
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(Rc::new(
+                Text::new(font.clone(), 12f64)
+                    .text("BEADCAB")
+                    .fill(Color::DeviceGray(0f64)),
+            ));
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/Subtype /TrueType\n"));
+        let name = &file[file.find("/BaseFont /").unwrap() + "/BaseFont /".len()..];
+        let name = &name[..name.find('\n').unwrap()];
+        assert_eq!(name.len(), "ABCDEF+TestSans".len());
+        assert!(name[..6].bytes().all(|b| b.is_ascii_uppercase()));
+        assert!(name.ends_with("+TestSans"));
+        assert!(file.contains(&format!("/FontName /{}\n", name)));
+        // Five glyphs are drawn, A to E
+        assert!(file.contains("/FirstChar 65\n/LastChar 69\n/Widths [510 520 530 540 550]\n"));
+        assert!(file.contains("/FontFile2 "));
+        let length = &file[file.find("/Length1 ").unwrap() + "/Length1 ".len()..];
+        let length: usize = length[..length.find('\n').unwrap()].parse().unwrap();
+        assert!(length < full.len());
+    }
+
+    #[test]
+    fn truetype_subset_per_document() {
+        let font = Font::from_truetype(crate::graphics::truetype::tests::font_file()).unwrap();
+        let document = |s: &str| {
+            let file = text(&written(|pdf| {
+                let mut page = Page::new();
+                page.add(Rc::new(
+                    Text::new(font.clone(), 12f64)
+                        .text(s)
+                        .fill(Color::DeviceGray(0f64)),
+                ));
+                pdf.add_page(page);
+            }));
+            let name = &file[file.find("/BaseFont /").unwrap()..];
+            (name[..name.find('\n').unwrap()].to_string(), file)
+        };
+        let (first, _) = document("ABCDE");
+        let (second, file) = document("Z");
+        // The second document only embeds the glyph it draws
+        assert_ne!(first, second);
+        assert!(file.contains("/FirstChar 90\n/LastChar 90\n/Widths [760]\n"));
+    }
+}
@@ -0,0 +1,647 @@
+//! TrueType fonts, embedded as a subset in a `/FontFile2` stream
+//!
+//! The subset keeps the glyph ids of the font, so its cmap still maps
+//! characters to the right glyphs, but the outlines of the glyphs that
+//! weren't drawn are left empty, and the tables a PDF reader doesn't
+//! need, such as the names, are left out.
+
+use super::metrics;
+use crate::pdf::{flate, md5, Dict, Name, ObjRef, Object, PDFData, Stream};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::rc::Rc;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn u16_at(data: &[u8], i: usize) -> io::Result<u16> {
+    data.get(i..i + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("Truncated TrueType table"))
+}
+
+fn i16_at(data: &[u8], i: usize) -> io::Result<i16> {
+    u16_at(data, i).map(|v| v as i16)
+}
+
+fn u32_at(data: &[u8], i: usize) -> io::Result<u32> {
+    data.get(i..i + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("Truncated TrueType table"))
+}
+
+/// The sum of the data as big-endian 32-bit words, padded with zeros
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, word| {
+        let mut bytes = [0u8; 4];
+        bytes[..word.len()].copy_from_slice(word);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+/// Writes a font file with the tables, which must be sorted by tag
+///
+/// The checksum adjustment of the `head` table is filled in, so the
+/// checksum of the whole file is the one the format asks for.
+pub fn write_font(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let count = tables.len();
+    let selector = (usize::BITS - 1 - count.max(1).leading_zeros()) as u16;
+    let range = 16u16 << selector;
+    let mut file = vec![];
+    file.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for v in [count as u16, range, selector, count as u16 * 16 - range] {
+        file.extend_from_slice(&v.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * count;
+    for (tag, data) in tables {
+        file.extend_from_slice(tag);
+        file.extend_from_slice(&checksum(data).to_be_bytes());
+        file.extend_from_slice(&(offset as u32).to_be_bytes());
+        file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    let mut head = None;
+    for (tag, data) in tables {
+        if tag == b"head" {
+            head = Some(file.len());
+        }
+        file.extend_from_slice(data);
+        file.resize((file.len() + 3) & !3, 0);
+    }
+    if let Some(head) = head.filter(|h| h + 12 <= file.len()) {
+        file[head + 8..head + 12].fill(0);
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&file));
+        file[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    file
+}
+
+/// The tag of each table of a font file, and where it is in the file
+fn tables(data: &[u8]) -> io::Result<Vec<([u8; 4], Range<usize>)>> {
+    let mut tables = vec![];
+    for i in 0..u16_at(data, 4)? as usize {
+        let record = 12 + 16 * i;
+        let mut tag = [0u8; 4];
+        tag.copy_from_slice(
+            data.get(record..record + 4)
+                .ok_or_else(|| invalid("Truncated TrueType font"))?,
+        );
+        let offset = u32_at(data, record + 8)? as usize;
+        let end = offset + u32_at(data, record + 12)? as usize;
+        if end > data.len() {
+            return Err(invalid("TrueType table out of bounds"));
+        }
+        tables.push((tag, offset..end));
+    }
+    Ok(tables)
+}
+
+/// The glyph of the character in a cmap subtable of format 4 or 12, or
+/// 0, the missing glyph, if the font has none
+fn lookup(cmap: &[u8], c: u32) -> io::Result<u16> {
+    match u16_at(cmap, 0)? {
+        4 => {
+            let segments = u16_at(cmap, 6)? as usize / 2;
+            for i in 0..segments {
+                if c > u16_at(cmap, 14 + 2 * i)? as u32 {
+                    continue;
+                }
+                let start = u16_at(cmap, 16 + 2 * segments + 2 * i)? as u32;
+                if c < start {
+                    return Ok(0);
+                }
+                let delta = u16_at(cmap, 16 + 4 * segments + 2 * i)?;
+                let range_at = 16 + 6 * segments + 2 * i;
+                let range = u16_at(cmap, range_at)? as usize;
+                if range == 0 {
+                    return Ok((c as u16).wrapping_add(delta));
+                }
+                let glyph = u16_at(cmap, range_at + range + 2 * (c - start) as usize)?;
+                return Ok(if glyph == 0 {
+                    0
+                } else {
+                    glyph.wrapping_add(delta)
+                });
+            }
+            Ok(0)
+        }
+        12 => {
+            for i in 0..u32_at(cmap, 12)? as usize {
+                let at = 16 + 12 * i;
+                let (start, end) = (u32_at(cmap, at)?, u32_at(cmap, at + 4)?);
+                if (start..=end).contains(&c) {
+                    let glyph = u32_at(cmap, at + 8)? + (c - start);
+                    return Ok(if glyph > 0xFFFF { 0 } else { glyph as u16 });
+                }
+            }
+            Ok(0)
+        }
+        _ => Err(invalid("Unsupported cmap format")),
+    }
+}
+
+/// The PostScript name of the font, without the characters that can't
+/// be written in a name
+fn postscript_name(name: &[u8]) -> io::Result<String> {
+    let count = u16_at(name, 2)? as usize;
+    let strings = u16_at(name, 4)? as usize;
+    for i in 0..count {
+        let record = 6 + 12 * i;
+        if u16_at(name, record + 6)? != 6 {
+            continue;
+        }
+        let platform = u16_at(name, record)?;
+        let len = u16_at(name, record + 8)? as usize;
+        let at = strings + u16_at(name, record + 10)? as usize;
+        let bytes = name
+            .get(at..at + len)
+            .ok_or_else(|| invalid("Truncated TrueType table"))?;
+        let text: String = match platform {
+            // Windows names are UTF-16BE
+            0 | 3 => char::decode_utf16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]])),
+            )
+            .filter_map(Result::ok)
+            .collect(),
+            _ => bytes.iter().map(|b| *b as char).collect(),
+        };
+        let text: String = text
+            .chars()
+            .filter(|c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(*c))
+            .collect();
+        if !text.is_empty() {
+            return Ok(text);
+        }
+    }
+    Err(invalid("The font has no PostScript name"))
+}
+
+/// A TrueType font, and the character codes drawn with it in the
+/// document being written
+#[derive(Debug)]
+pub struct TrueType {
+    data: Vec<u8>,
+    /// The tag of each table, and where it is in the data
+    tables: Vec<([u8; 4], Range<usize>)>,
+    name: String,
+    units_per_em: f64,
+    bbox: [i16; 4],
+    ascent: i16,
+    descent: i16,
+    cap_height: i16,
+    italic_angle: f64,
+    fixed_pitch: bool,
+    /// The advance width of each glyph, in font units
+    advances: Vec<u16>,
+    /// Where each glyph is in the glyf table, and where the last ends
+    loca: Vec<usize>,
+    /// The glyph of each WinAnsiEncoding code, 0 for codes without one
+    codes: [u16; 256],
+    used: RefCell<BTreeSet<u8>>,
+}
+
+impl TrueType {
+    /// Reads the tables of the font
+    ///
+    /// # Errors
+    ///
+    /// Returns an InvalidData error if the data is not a TrueType font,
+    /// is missing a table, or has no Unicode cmap
+    pub fn parse(data: Vec<u8>) -> io::Result<Self> {
+        if !matches!(data.get(0..4), Some([0, 1, 0, 0]) | Some(b"true")) {
+            return Err(invalid("Not a TrueType font"));
+        }
+        let tables = tables(&data)?;
+        let find = |tag: &[u8; 4]| {
+            tables
+                .iter()
+                .find(|(t, _)| t == tag)
+                .map(|(_, range)| &data[range.clone()])
+        };
+        let table = |tag: &[u8; 4]| {
+            find(tag).ok_or_else(|| {
+                invalid(&format!(
+                    "The font has no {} table",
+                    String::from_utf8_lossy(tag)
+                ))
+            })
+        };
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let glyph_count = u16_at(table(b"maxp")?, 4)? as usize;
+
+        let hmtx = table(b"hmtx")?;
+        let metric_count = (u16_at(hhea, 34)? as usize).clamp(1, glyph_count.max(1));
+        let mut advances = Vec::with_capacity(glyph_count);
+        for glyph in 0..glyph_count {
+            advances.push(u16_at(hmtx, 4 * glyph.min(metric_count - 1))?);
+        }
+
+        let loca_table = table(b"loca")?;
+        let long_loca = i16_at(head, 50)? == 1;
+        let mut loca = Vec::with_capacity(glyph_count + 1);
+        for i in 0..=glyph_count {
+            loca.push(if long_loca {
+                u32_at(loca_table, 4 * i)? as usize
+            } else {
+                u16_at(loca_table, 2 * i)? as usize * 2
+            });
+        }
+        let glyf_len = table(b"glyf")?.len();
+        if loca.windows(2).any(|w| w[0] > w[1]) || loca[glyph_count] > glyf_len {
+            return Err(invalid("Malformed loca table"));
+        }
+
+        let cmap_table = table(b"cmap")?;
+        let mut cmap = None;
+        for i in 0..u16_at(cmap_table, 2)? as usize {
+            let platform = u16_at(cmap_table, 4 + 8 * i)?;
+            let encoding = u16_at(cmap_table, 6 + 8 * i)?;
+            let offset = u32_at(cmap_table, 8 + 8 * i)? as usize;
+            if platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10)) {
+                let subtable = &cmap_table[offset.min(cmap_table.len())..];
+                if matches!(u16_at(subtable, 0)?, 4 | 12) {
+                    cmap = Some(subtable);
+                    break;
+                }
+            }
+        }
+        let cmap = cmap.ok_or_else(|| invalid("The font has no Unicode cmap"))?;
+        let mut codes = [0u16; 256];
+        for (code, glyph) in codes.iter_mut().enumerate() {
+            if let Some(c) = metrics::win_ansi_decode(code as u8) {
+                *glyph = Some(lookup(cmap, c as u32)?)
+                    .filter(|g| (*g as usize) < glyph_count)
+                    .unwrap_or(0);
+            }
+        }
+
+        let (italic_angle, fixed_pitch) = match find(b"post") {
+            Some(post) => (
+                u32_at(post, 4)? as i32 as f64 / 65536f64,
+                u32_at(post, 12)? != 0,
+            ),
+            None => (0f64, false),
+        };
+        let ascent = i16_at(hhea, 4)?;
+        let cap_height = match find(b"OS/2") {
+            Some(os2) if u16_at(os2, 0)? >= 2 => i16_at(os2, 88)?,
+            _ => ascent,
+        };
+        Ok(Self {
+            name: postscript_name(table(b"name")?)?,
+            units_per_em: u16_at(head, 18)?.max(1) as f64,
+            bbox: [
+                i16_at(head, 36)?,
+                i16_at(head, 38)?,
+                i16_at(head, 40)?,
+                i16_at(head, 42)?,
+            ],
+            ascent,
+            descent: i16_at(hhea, 6)?,
+            cap_height,
+            italic_angle,
+            fixed_pitch,
+            advances,
+            loca,
+            codes,
+            used: RefCell::new(BTreeSet::new()),
+            tables,
+            data,
+        })
+    }
+    /// The PostScript name of the font
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    fn table(&self, tag: &[u8; 4]) -> Option<&[u8]> {
+        self.tables
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, range)| &self.data[range.clone()])
+    }
+    /// The WinAnsiEncoding code for c, as a character, if the font has
+    /// a glyph for it
+    pub fn encode(&self, c: char) -> Option<char> {
+        metrics::win_ansi_encode(c).filter(|code| self.codes[*code as usize] != 0)
+    }
+    /// Width of the glyph of a code, in thousandths of the font size
+    pub fn width(&self, code: char) -> f64 {
+        let glyph = self.codes.get(code as usize).copied().unwrap_or(0);
+        self.advances.get(glyph as usize).copied().unwrap_or(0) as f64 * 1000f64 / self.units_per_em
+    }
+    /// Records the codes as drawn, so their glyphs are in the subset
+    pub fn mark_used(&self, codes: &[u8]) {
+        self.used.borrow_mut().extend(codes.iter().copied());
+    }
+    /// The codes drawn so far, from the first to the last
+    fn used_range(&self) -> Option<(u8, u8)> {
+        let used = self.used.borrow();
+        Some((*used.first()?, *used.last()?))
+    }
+    fn outline(&self, glyph: usize) -> &[u8] {
+        let glyf = self.table(b"glyf").unwrap_or(&[]);
+        &glyf[self.loca[glyph]..self.loca[glyph + 1]]
+    }
+    /// The glyphs a composite glyph is made of
+    fn components(&self, glyph: usize) -> io::Result<Vec<u16>> {
+        let outline = self.outline(glyph);
+        if outline.is_empty() || i16_at(outline, 0)? >= 0 {
+            return Ok(vec![]);
+        }
+        let mut components = vec![];
+        let mut at = 10;
+        loop {
+            let flags = u16_at(outline, at)?;
+            components.push(u16_at(outline, at + 2)?);
+            // The offsets are words or bytes, followed by the transform
+            at += if flags & 0x0001 != 0 { 8 } else { 6 };
+            at += match flags {
+                f if f & 0x0008 != 0 => 2,
+                f if f & 0x0040 != 0 => 4,
+                f if f & 0x0080 != 0 => 8,
+                _ => 0,
+            };
+            if flags & 0x0020 == 0 {
+                break;
+            }
+        }
+        Ok(components)
+    }
+    /// The glyphs of the codes drawn, the missing glyph, and the
+    /// glyphs composite glyphs are made of
+    fn glyphs(&self) -> io::Result<BTreeSet<u16>> {
+        let mut glyphs: BTreeSet<u16> = std::iter::once(0)
+            .chain(self.used.borrow().iter().map(|c| self.codes[*c as usize]))
+            .collect();
+        let mut pending: Vec<u16> = glyphs.iter().copied().collect();
+        while let Some(glyph) = pending.pop() {
+            for component in self.components(glyph as usize)? {
+                if (component as usize) < self.advances.len() && glyphs.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+        Ok(glyphs)
+    }
+    /// The name of the subset, the PostScript name with a tag of six
+    /// capital letters made from the glyphs in it, such as
+    /// `ABCDEF+DejaVuSans`
+    pub fn subset_name(&self) -> io::Result<String> {
+        let ids: Vec<u8> = self
+            .glyphs()?
+            .iter()
+            .flat_map(|g| g.to_be_bytes())
+            .collect();
+        let tag: String = md5::digest(&ids)[..6]
+            .iter()
+            .map(|b| (b'A' + b % 26) as char)
+            .collect();
+        Ok(format!("{}+{}", tag, self.name))
+    }
+    /// A font file with only the glyphs of the codes drawn
+    pub fn subset(&self) -> io::Result<Vec<u8>> {
+        let glyphs = self.glyphs()?;
+        let (mut glyf, mut loca) = (vec![], vec![]);
+        for glyph in 0..self.advances.len() {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            if glyphs.contains(&(glyph as u16)) {
+                glyf.extend_from_slice(self.outline(glyph));
+                glyf.resize((glyf.len() + 3) & !3, 0);
+            }
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        let mut head = self.table(b"head").unwrap_or(&[]).to_vec();
+        // The new loca table uses 32-bit offsets
+        head[50..52].copy_from_slice(&1u16.to_be_bytes());
+        let mut tables = vec![(*b"glyf", glyf), (*b"head", head), (*b"loca", loca)];
+        for tag in [
+            b"cmap", b"cvt ", b"fpgm", b"hhea", b"hmtx", b"maxp", b"prep",
+        ] {
+            if let Some(table) = self.table(tag) {
+                tables.push((*tag, table.to_vec()));
+            }
+        }
+        tables.sort();
+        Ok(write_font(&tables))
+    }
+}
+
+/// The subset font file, made when it is written, see TrueType::subset
+#[derive(Debug)]
+struct FontFile(Rc<TrueType>);
+
+impl PDFData for FontFile {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        let data = self.0.subset()?;
+        let meta = Dict::from_vec(vec![("Length1", Rc::new(data.len()))]);
+        Stream::flate(meta, &data, None, flate::DEFAULT_LEVEL).write(o)
+    }
+    fn is_stream(&self) -> bool {
+        true
+    }
+}
+
+/// The metrics of a TrueType font, and its font file
+#[derive(Debug)]
+pub struct FontDescriptor {
+    font: Rc<TrueType>,
+    file: Rc<ObjRef<FontFile>>,
+}
+
+impl FontDescriptor {
+    pub fn new(font: Rc<TrueType>) -> Rc<ObjRef<Self>> {
+        let file = ObjRef::new(0, Rc::new(FontFile(font.clone())));
+        ObjRef::new(0, Rc::new(Self { font, file }))
+    }
+    pub fn font(&self) -> &Rc<TrueType> {
+        &self.font
+    }
+    /// The `/FirstChar`, `/LastChar` and `/Widths` of the font dict,
+    /// for the codes drawn
+    pub fn widths(&self) -> Vec<(&'static str, Rc<dyn PDFData>)> {
+        let (first, last) = self.font.used_range().unwrap_or((b' ', b' '));
+        let widths: Vec<Rc<f64>> = (first..=last)
+            .map(|code| Rc::new(self.font.width(code as char).round()))
+            .collect();
+        vec![
+            ("FirstChar", Rc::new(first as usize)),
+            ("LastChar", Rc::new(last as usize)),
+            ("Widths", Rc::new(widths)),
+        ]
+    }
+}
+
+impl PDFData for FontDescriptor {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        let font = &self.font;
+        let scale = |v: i16| Rc::new((v as f64 * 1000f64 / font.units_per_em).round());
+        // Nonsymbolic, as the glyphs are chosen by WinAnsiEncoding
+        let mut flags = 32usize;
+        if font.fixed_pitch {
+            flags |= 1;
+        }
+        if font.italic_angle != 0f64 {
+            flags |= 64;
+        }
+        let bbox: Vec<Rc<f64>> = font.bbox.iter().map(|v| scale(*v)).collect();
+        Dict::from_vec(vec![
+            ("Type", Name::new("FontDescriptor") as Rc<dyn PDFData>),
+            ("FontName", Name::new(font.subset_name()?)),
+            ("Flags", Rc::new(flags)),
+            ("FontBBox", Rc::new(bbox)),
+            ("ItalicAngle", Rc::new(font.italic_angle)),
+            ("Ascent", scale(font.ascent)),
+            ("Descent", scale(font.descent)),
+            ("CapHeight", scale(font.cap_height)),
+            ("StemV", Rc::new(80usize)),
+            ("FontFile2", self.file.clone()),
+        ])
+        .write(o)
+    }
+    fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
+        vec![self.file.clone()]
+    }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        self.file.references(refs);
+    }
+    /// Forgets the codes drawn once the document is written, so the
+    /// next document sharing the font embeds its own subset
+    fn forget(&self) {
+        self.font.used.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A simple glyph, a single contour of twelve points
+    fn simple_glyph(size: i16) -> Vec<u8> {
+        let mut glyph = vec![];
+        for v in [1i16, 0, 0, size, size, 11, 0] {
+            glyph.extend_from_slice(&v.to_be_bytes());
+        }
+        glyph.extend_from_slice(&[1; 12]);
+        for i in 0..24 {
+            glyph.extend_from_slice(&(i * size / 24).to_be_bytes());
+        }
+        glyph
+    }
+
+    /// A font with glyphs for `A` to `Z`, and `Ä`, a composite glyph of
+    /// `A` and an unmapped dieresis, named TestSans
+    pub(crate) fn font_file() -> Vec<u8> {
+        let be = |values: &[i32], bytes: &mut Vec<u8>| {
+            for v in values {
+                bytes.extend_from_slice(&(*v as u16).to_be_bytes());
+            }
+        };
+        let count = 29;
+        let mut glyphs: Vec<Vec<u8>> = (0..count).map(|g| simple_glyph(100 + g)).collect();
+        let mut composite = vec![];
+        be(&[-1, 0, 0, 100, 140], &mut composite);
+        // A, with more components, then the dieresis above it
+        be(&[0x0021, 1, 0, 0, 0x0001, 28, 0, 100], &mut composite);
+        glyphs[27] = composite;
+
+        let (mut glyf, mut loca) = (vec![], vec![]);
+        for glyph in glyphs.iter() {
+            be(&[glyf.len() as i32 / 2], &mut loca);
+            glyf.extend_from_slice(glyph);
+        }
+        be(&[glyf.len() as i32 / 2], &mut loca);
+
+        let mut head = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0x5F, 0x0F, 0x3C, 0xF5];
+        be(&[0, 1000], &mut head);
+        head.resize(36, 0);
+        be(&[0, -200, 1000, 800, 0, 8, 2, 0, 0], &mut head);
+        let mut hhea = vec![0, 1, 0, 0];
+        be(&[800, -200], &mut hhea);
+        hhea.resize(34, 0);
+        be(&[count as i32], &mut hhea);
+        let mut maxp = vec![0, 0, 0x50, 0];
+        be(&[count as i32], &mut maxp);
+        let mut hmtx = vec![];
+        for g in 0..count as i32 {
+            be(&[500 + 10 * g, 0], &mut hmtx);
+        }
+
+        // Format 4, with segments for A to Z, Ä, and the final segment
+        let mut cmap = vec![];
+        be(&[0, 1, 3, 1, 0, 12], &mut cmap);
+        be(&[4, 16 + 8 * 3, 0, 6, 4, 1, 2], &mut cmap);
+        be(&[90, 0xC4, 0xFFFF, 0], &mut cmap);
+        be(&[65, 0xC4, 0xFFFF], &mut cmap);
+        be(&[1 - 65, 27 - 0xC4, 1], &mut cmap);
+        be(&[0, 0, 0], &mut cmap);
+
+        let postscript: Vec<u8> = "TestSans"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let mut name = vec![];
+        be(
+            &[0, 1, 18, 3, 1, 0x409, 6, postscript.len() as i32, 0],
+            &mut name,
+        );
+        name.extend_from_slice(&postscript);
+
+        write_font(&[
+            (*b"cmap", cmap),
+            (*b"glyf", glyf),
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", hmtx),
+            (*b"loca", loca),
+            (*b"maxp", maxp),
+            (*b"name", name),
+        ])
+    }
+
+    #[test]
+    fn parse() {
+        let font = TrueType::parse(font_file()).unwrap();
+        assert_eq!(font.name(), "TestSans");
+        assert_eq!(font.encode('B'), Some('B'));
+        assert_eq!(font.encode('b'), None);
+        assert_eq!(font.width('B'), 520f64);
+        assert!(TrueType::parse(b"not a font".to_vec()).is_err());
+    }
+
+    #[test]
+    fn subset() {
+        let font = TrueType::parse(font_file()).unwrap();
+        font.mark_used(&[0xC4]);
+        let subset = font.subset().unwrap();
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+        let tables = tables(&subset).unwrap();
+        let tags: Vec<&[u8]> = tables.iter().map(|(tag, _)| &tag[..]).collect();
+        assert_eq!(
+            tags,
+            vec![
+                &b"cmap"[..],
+                b"glyf",
+                b"head",
+                b"hhea",
+                b"hmtx",
+                b"loca",
+                b"maxp"
+            ]
+        );
+        let loca = &subset[tables[5].1.clone()];
+        let len =
+            |glyph: usize| u32_at(loca, 4 * glyph + 4).unwrap() - u32_at(loca, 4 * glyph).unwrap();
+        // The missing glyph, Ä, and the glyphs it is made of are kept
+        let kept: Vec<usize> = (0..29).filter(|g| len(*g) > 0).collect();
+        assert_eq!(kept, vec![0, 1, 27, 28]);
+        assert!(subset.len() < font_file().len());
+        assert_eq!(font.subset_name().unwrap().len(), "ABCDEF+TestSans".len());
+        assert!(font.subset_name().unwrap().ends_with("+TestSans"));
+    }
+}
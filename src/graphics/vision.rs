@@ -0,0 +1,194 @@
+//! Simulation of color vision deficiencies
+//!
+//! Colors are mapped with the matrices from Machado, Oliveira and
+//! Fernandes (2009), at full severity, applied directly to the color
+//! values. Only colors in DeviceRGB are changed: gray is unaffected by
+//! these deficiencies, and CMYK, ICC based colors, and images are left
+//! as they are.
+
+use super::Color;
+
+/// A color vision deficiency
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorVision {
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+}
+
+impl ColorVision {
+    fn matrix(&self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+    /// How an RGB color appears with this deficiency
+    ///
+    /// The components are clamped to 0 to 1
+    pub fn simulate_rgb(&self, r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+        let m = self.matrix();
+        let row = |i: usize| (m[i][0] * r + m[i][1] * g + m[i][2] * b).clamp(0f64, 1f64);
+        (row(0), row(1), row(2))
+    }
+    /// How a color appears with this deficiency, see simulate_rgb
+    pub fn simulate(&self, color: &Color) -> Color {
+        match color {
+            Color::DeviceRGB(r, g, b) => {
+                let (r, g, b) = self.simulate_rgb(*r, *g, *b);
+                Color::DeviceRGB(r, g, b)
+            }
+            c => c.clone(),
+        }
+    }
+}
+
+/// Splits a content stream into tokens, as byte ranges
+///
+/// Strings, names, numbers, and operators are single tokens, as are
+/// the delimiters `[`, `]`, `<<`, and `>>`
+pub(super) fn tokens(stream: &[u8]) -> Vec<(usize, usize)> {
+    let delimiter = |b: u8| b"()<>[]{}/%".contains(&b);
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < stream.len() {
+        let start = i;
+        match stream[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'(' => {
+                let mut depth = 0;
+                while i < stream.len() {
+                    match stream[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => depth -= 1,
+                        _ => (),
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            b'<' | b'>' if stream.get(i + 1) == Some(&stream[i]) => i += 2,
+            b'<' => {
+                while i < stream.len() && stream[i] != b'>' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'[' | b']' | b'{' | b'}' => i += 1,
+            _ => {
+                i += 1;
+                while i < stream.len() && !stream[i].is_ascii_whitespace() && !delimiter(stream[i])
+                {
+                    i += 1;
+                }
+            }
+        }
+        tokens.push((start, i.min(stream.len())));
+    }
+    tokens
+}
+
+/// Rewrites the DeviceRGB colors set in a content stream
+pub(super) fn transform_stream(stream: &[u8], vision: ColorVision) -> Vec<u8> {
+    let mut out = Vec::with_capacity(stream.len());
+    let mut copied = 0;
+    // The color space for filling and stroking, and the saved spaces
+    let mut spaces = (b"/DeviceGray".to_vec(), b"/DeviceGray".to_vec());
+    let mut saved = vec![];
+    let mut operands: Vec<(usize, usize)> = vec![];
+    for (start, end) in tokens(stream) {
+        let token = &stream[start..end];
+        if !(token[0].is_ascii_alphabetic() || token[0] == b'\'' || token[0] == b'"') {
+            operands.push((start, end));
+            continue;
+        }
+        let stroke = token[0].is_ascii_uppercase();
+        let rgb = match token {
+            b"q" => {
+                saved.push(spaces.clone());
+                false
+            }
+            b"Q" => {
+                if let Some(s) = saved.pop() {
+                    spaces = s;
+                }
+                false
+            }
+            b"cs" | b"CS" => {
+                if let Some((s, e)) = operands.last() {
+                    let space = stream[*s..*e].to_vec();
+                    if stroke {
+                        spaces.1 = space;
+                    } else {
+                        spaces.0 = space;
+                    }
+                }
+                false
+            }
+            b"rg" | b"RG" => true,
+            b"sc" | b"scn" => spaces.0 == b"/DeviceRGB",
+            b"SC" | b"SCN" => spaces.1 == b"/DeviceRGB",
+            _ => false,
+        };
+        let values: Vec<f64> = operands
+            .iter()
+            .filter_map(|(s, e)| std::str::from_utf8(&stream[*s..*e]).ok()?.parse().ok())
+            .collect();
+        if rgb && operands.len() == 3 && values.len() == 3 {
+            let (r, g, b) = vision.simulate_rgb(values[0], values[1], values[2]);
+            for ((s, e), v) in operands.iter().zip([r, g, b].iter()) {
+                out.extend_from_slice(&stream[copied..*s]);
+                out.extend(v.to_string().bytes());
+                copied = *e;
+            }
+        }
+        operands.clear();
+    }
+    out.extend_from_slice(&stream[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deuteranopia_red() {
+        let (r, g, b) = ColorVision::Deuteranopia.simulate_rgb(1f64, 0f64, 0f64);
+        assert_eq!((r, g, b), (0.367322, 0.280085, 0f64));
+    }
+
+    #[test]
+    fn transform_red_fill() {
+        let stream = transform_stream(
+            b" /DeviceRGB cs 1 0 0 scn 0 0 10 10 re f 1 0 0 RG",
+            ColorVision::Deuteranopia,
+        );
+        assert_eq!(
+            stream,
+            b" /DeviceRGB cs 0.367322 0.280085 0 scn 0 0 10 10 re f 0.367322 0.280085 0 RG"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::pdf::{Dict, Name, ObjRef, PDFData, PDFString};
+use std::rc::Rc;
+
+/// A layer of optional content (`/OCG`), which viewers can show or hide,
+/// see PDF::add_layer and Page::add_to_layer
+///
+/// Layers are shown by default. The usage states override the default
+/// when the document is viewed, printed, or exported, such as a
+/// watermark that is only printed.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    name: String,
+    visible: bool,
+    view: Option<bool>,
+    print: Option<bool>,
+    export: Option<bool>,
+    /// The OCG, and its name in the resources, once it has been added
+    object: Option<(Rc<ObjRef<Dict>>, Rc<Name>)>,
+}
+
+/// The events the usage states apply to, their key in `/Usage`, and
+/// the key of the state
+const EVENTS: [(&str, &str); 3] = [
+    ("View", "ViewState"),
+    ("Print", "PrintState"),
+    ("Export", "ExportState"),
+];
+
+impl Layer {
+    /// - name: the name shown in the viewer's list of layers
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            view: None,
+            print: None,
+            export: None,
+            object: None,
+        }
+    }
+    /// A layer that is printed, but not shown on screen
+    pub fn print_only(name: &str) -> Self {
+        Self::new(name).visible(false).view(false).print(true)
+    }
+    /// A layer that is shown on screen, but not printed
+    pub fn screen_only(name: &str) -> Self {
+        Self::new(name).view(true).print(false)
+    }
+    /// Sets whether the layer is shown when the document is opened
+    /// (`/ON` or `/OFF` of the default configuration)
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+    /// Sets whether the layer is shown on screen (`/ViewState`)
+    pub fn view(mut self, on: bool) -> Self {
+        self.view = Some(on);
+        self
+    }
+    /// Sets whether the layer is printed (`/PrintState`)
+    pub fn print(mut self, on: bool) -> Self {
+        self.print = Some(on);
+        self
+    }
+    /// Sets whether the layer is included when the document is
+    /// exported to another format (`/ExportState`)
+    pub fn export(mut self, on: bool) -> Self {
+        self.export = Some(on);
+        self
+    }
+    fn states(&self) -> [Option<bool>; 3] {
+        [self.view, self.print, self.export]
+    }
+    /// The optional content group dictionary of the layer
+    pub(crate) fn data(&self) -> Rc<Dict> {
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("OCG") as Rc<dyn PDFData>),
+            ("Name", PDFString::new(self.name.as_str())),
+        ]);
+        let usage = Dict::new();
+        for ((event, key), state) in EVENTS.iter().zip(self.states()) {
+            if let Some(on) = state {
+                let state = Name::new(if on { "ON" } else { "OFF" });
+                usage.add_entry(*event, Dict::from_vec(vec![(*key, state)]));
+            }
+        }
+        if !usage.is_empty() {
+            dict.add_entry("Usage", usage);
+        }
+        dict
+    }
+    /// Sets the OCG of the layer, and its name in the resources
+    pub(crate) fn added(mut self, object: Rc<ObjRef<Dict>>, name: Rc<Name>) -> Rc<Self> {
+        self.object = Some((object, name));
+        Rc::new(self)
+    }
+    /// The OCG of the layer, and its name in the resources
+    ///
+    /// # Panics
+    ///
+    /// panics if the layer wasn't added with PDF::add_layer
+    pub(crate) fn object(&self) -> (Rc<ObjRef<Dict>>, Rc<Name>) {
+        self.object
+            .clone()
+            .expect("The layer must be added with PDF::add_layer")
+    }
+}
+
+/// The optional content properties of the document (`/OCProperties`)
+///
+/// The usage states only apply through the `/AS` array of the default
+/// configuration, which has an entry for each event that a layer has a
+/// state for.
+pub(crate) fn properties(layers: &[Rc<Layer>]) -> Rc<Dict> {
+    let refs = |filter: &dyn Fn(&Layer) -> bool| -> Rc<dyn PDFData> {
+        Rc::new(
+            layers
+                .iter()
+                .filter(|l| filter(l))
+                .map(|l| l.object().0 as Rc<dyn PDFData>)
+                .collect::<Vec<_>>(),
+        )
+    };
+    let config = Dict::from_vec(vec![
+        ("Order", refs(&|_| true)),
+        ("ON", refs(&|l| l.visible)),
+        ("OFF", refs(&|l| !l.visible)),
+    ]);
+    let mut auto: Vec<Rc<dyn PDFData>> = vec![];
+    for (i, (event, _)) in EVENTS.iter().enumerate() {
+        if layers.iter().any(|l| l.states()[i].is_some()) {
+            let category: Vec<Rc<dyn PDFData>> = vec![Name::new(*event)];
+            auto.push(Dict::from_vec(vec![
+                ("Event", Name::new(*event) as Rc<dyn PDFData>),
+                ("Category", Rc::new(category)),
+                ("OCGs", refs(&|l| l.states()[i].is_some())),
+            ]));
+        }
+    }
+    if !auto.is_empty() {
+        config.add_entry("AS", Rc::new(auto));
+    }
+    Dict::from_vec(vec![
+        ("OCGs", refs(&|_| true)),
+        ("D", config as Rc<dyn PDFData>),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Color, Path};
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn print_only() {
+        let file = text(&written(|pdf| {
+            let layer = pdf.add_layer(Layer::print_only("Watermark"));
+            let mut page = Page::new();
+            page.add_to_layer(
+                &layer,
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            pdf.add_page(page);
+        }));
+        assert!(file.contains(
+            "/Usage <<\n/View <<\n/ViewState /OFF\n>>\n\n/Print <<\n/PrintState /ON\n>>\n"
+        ));
+        assert!(file.contains("/ON []\n/OFF [4 0 R]\n"));
+        assert!(file.contains("/Event /Print\n/Category [/Print]\n/OCGs [4 0 R]\n"));
+        assert!(file.contains("stream\n /OC /OC1 BDC 0 0 10 10 re f EMC\nendstream"));
+    }
+}
@@ -1,30 +1,271 @@
 use std::boxed::Box;
 use std::rc::Rc;
 
+pub mod annotation;
+use annotation::Link;
 pub mod graphics;
-use graphics::{Graphic, GraphicContext};
-mod pdf;
-use pdf::{Dict, Name, ObjRef, Object, PDFData};
+use graphics::{
+    Color, ColorSpace, ColorVision, CompileError, Font, Graphic, GraphicContext, Matrix, Path,
+    Point, Position, Rect, Stamp, Text, TextFlow,
+};
+pub mod collection;
+use collection::Collection;
+pub mod layer;
+use layer::Layer;
+pub mod pool;
+use pool::{PoolLease, ResourcePool};
+pub mod pdf;
+use pdf::{types::Stream, Dict, Name, ObjRef, Object, PDFData, PDFString, Raw};
+pub mod signature;
+mod structure;
+use signature::{Perms, SignatureField};
+pub mod transition;
+use transition::{Transition, TransitionStyle};
+pub mod verify;
+use verify::VerifyError;
+mod watermark;
+use watermark::Watermark;
+
+/// Placeholder for the number of pages in the document
+///
+/// Text containing it, like `format!("Page 1 of {}", TOTAL_PAGES)`, has
+/// the placeholder replaced with the page count when the document is
+/// written.
+pub const TOTAL_PAGES: &str = "{total-pages}";
+
+/// Common paper sizes, for PDF::set_media_box and Page::set_media_box
+///
+/// The ISO sizes are rounded to whole points, as most software does.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PageSize {
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+    A5,
+    A6,
+    /// US Letter, 8.5 by 11 inches, the default
+    Letter,
+    /// US Legal, 8.5 by 14 inches
+    Legal,
+    /// 11 by 17 inches
+    Tabloid,
+}
+impl PageSize {
+    /// The page in portrait orientation, from (0, 0), in points
+    pub fn dimensions(&self) -> Rect {
+        let (width, height) = match self {
+            Self::A0 => (2384f64, 3370f64),
+            Self::A1 => (1684f64, 2384f64),
+            Self::A2 => (1191f64, 1684f64),
+            Self::A3 => (842f64, 1191f64),
+            Self::A4 => (595f64, 842f64),
+            Self::A5 => (420f64, 595f64),
+            Self::A6 => (298f64, 420f64),
+            Self::Letter => (612f64, 792f64),
+            Self::Legal => (612f64, 1008f64),
+            Self::Tabloid => (792f64, 1224f64),
+        };
+        Rect::new(0f64, 0f64, width, height)
+    }
+}
+impl From<PageSize> for Rect {
+    fn from(size: PageSize) -> Self {
+        size.dimensions()
+    }
+}
+
+/// How the `/Contents` of a page is written, see Page::contents_mode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ContentsMode {
+    /// A reference to the content stream, or an array if there are
+    /// several
+    Single,
+    /// An array of references, even if there is a single stream, so
+    /// streams can be appended by a later incremental update
+    Array,
+}
+
+/// What a viewer does when the document is opened (`/OpenAction`), see
+/// PDF::set_open_action
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpenAction {
+    /// Shows the page, by index starting at 0, fitted to the window
+    Page(usize),
+    /// Shows the page returned by PDF::add_page, fitted to the window
+    PageRef(PageRef),
+    /// Runs JavaScript, such as `"this.print();"` to print the document
+    /// on open. Viewers may ignore it, or ask the user first
+    JavaScript(String),
+}
+
+/// The document information dictionary (`/Info`), see PDF::set_metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    title: Option<String>,
+    author: Option<String>,
+    subject: Option<String>,
+    keywords: Option<String>,
+    creator: Option<String>,
+    producer: Option<String>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the title of the document (`/Title`)
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+    /// Sets the person who created the document (`/Author`)
+    pub fn author(mut self, author: &str) -> Self {
+        self.author = Some(author.to_string());
+        self
+    }
+    /// Sets the subject of the document (`/Subject`)
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+    /// Sets the keywords of the document (`/Keywords`)
+    pub fn keywords(mut self, keywords: &str) -> Self {
+        self.keywords = Some(keywords.to_string());
+        self
+    }
+    /// Sets the application the document was created with, before it
+    /// was converted to PDF (`/Creator`)
+    pub fn creator(mut self, creator: &str) -> Self {
+        self.creator = Some(creator.to_string());
+        self
+    }
+    /// Sets the application that converted the document to PDF
+    /// (`/Producer`), which defaults to the name and version of this
+    /// crate
+    pub fn producer(mut self, producer: &str) -> Self {
+        self.producer = Some(producer.to_string());
+        self
+    }
+    fn data(&self) -> Rc<Dict> {
+        let producer = self
+            .producer
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")));
+        let dict = Dict::from_vec(vec![("Producer", PDFString::new(producer))]);
+        for (key, value) in [
+            ("Title", &self.title),
+            ("Author", &self.author),
+            ("Subject", &self.subject),
+            ("Keywords", &self.keywords),
+            ("Creator", &self.creator),
+        ] {
+            dict.add_optional(
+                key,
+                value
+                    .as_deref()
+                    .map(|v| PDFString::new(v) as Rc<dyn PDFData>),
+            );
+        }
+        dict
+    }
+}
+
+/// Text with too little contrast against the page background,
+/// see PDF::check_contrast
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastIssue {
+    /// Index of the page, starting at 0
+    pub page: usize,
+    /// Where the text starts
+    pub position: Point,
+    /// The WCAG contrast ratio of the text and the background
+    pub ratio: f64,
+}
+
+/// A page added to a document, returned by PDF::add_page
+///
+/// The handle keeps referring to the same page when other pages are
+/// added, and is resolved to the page object when the document is
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageRef(usize);
+
+impl PageRef {
+    /// A handle that isn't used by any other page, in any document
+    fn next() -> Self {
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        Self(NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    }
+}
 
 pub struct PDF {
-    pages: Vec<Page>,
+    pages: Vec<(PageRef, Page)>,
     writer: pdf::PDFWrite,
     catalog: Rc<ObjRef<Dict>>,
+    #[allow(dead_code)]
     outlines: Rc<ObjRef<Dict>>,
     pages_obj: Rc<ObjRef<Dict>>,
+    icc_profiles: Vec<Rc<ColorSpace>>,
+    watermark: Option<Watermark>,
+    header: Option<PageCallback>,
+    footer: Option<PageCallback>,
+    /// Attributes inherited by every page from the page tree
+    media_box: Rect,
+    rotate: i64,
+    color_vision: Option<ColorVision>,
+    /// The file specification of each attached file, by name
+    attachments: Vec<(String, Rc<dyn PDFData>)>,
+    open_action: Option<OpenAction>,
+    metadata: Option<Metadata>,
+    layers: Vec<Rc<Layer>>,
+    /// The pool the document shares fonts from, see new_with_pool
+    pool: Option<PoolLease>,
 }
 
+/// Draws on a page, given the page index and page count
+type PageCallback = Box<dyn Fn(&mut Page, usize, usize)>;
+
 impl PDF {
     /// Creates a new PDF file with the given output writer
     pub fn new(out: Box<dyn std::io::Write>) -> Self {
+        Self::with_writer(pdf::PDFWrite::new(out))
+    }
+    /// Creates a new PDF file, for content drawn with the fonts of the
+    /// pool, see ResourcePool
+    ///
+    /// # Panics
+    ///
+    /// panics if another document using the pool hasn't been written
+    /// or dropped
+    pub fn new_with_pool(out: Box<dyn std::io::Write>, pool: &Rc<ResourcePool>) -> Self {
+        let mut pdf = Self::new(out);
+        pdf.pool = Some(PoolLease::new(pool));
+        pdf
+    }
+    /// Creates a new PDF file, numbering the objects starting at first
+    ///
+    /// Along with PDF::reserve_numbers, this avoids collisions with the
+    /// object numbers of another document, when the two are merged. The
+    /// Outlines, page tree, and Catalog are given the numbers first,
+    /// first + 1, and first + 2.
+    ///
+    /// # Panics
+    ///
+    /// panics if first is 0, which is reserved
+    pub fn with_first_number(out: Box<dyn std::io::Write>, first: usize) -> Self {
+        assert!(first > 0, "Object number 0 is reserved");
         let mut writer = pdf::PDFWrite::new(out);
-        let outlines = ObjRef::new(
-            0,
-            Dict::from_vec(vec![("Type", Name::new("Outlines")), ("Count", Rc::new(0))]),
-        );
-        writer.add_object(outlines.clone());
-        let pages_obj = ObjRef::new(0, Dict::from_vec(vec![("Type", Name::new("Pages"))]));
-        writer.add_object(pages_obj.clone());
+        writer.set_next_number(first);
+        Self::with_writer(writer)
+    }
+    fn with_writer(mut writer: pdf::PDFWrite) -> Self {
+        let outlines = writer.allocate(Dict::from_vec(vec![
+            ("Type", Name::new("Outlines")),
+            ("Count", Rc::new(0)),
+        ]));
+        let pages_obj = writer.allocate(Dict::from_vec(vec![("Type", Name::new("Pages"))]));
         Self {
             pages: vec![],
             catalog: writer.create_root(Dict::from_vec(vec![
@@ -34,6 +275,18 @@ impl PDF {
             ])),
             outlines,
             pages_obj,
+            icc_profiles: vec![],
+            watermark: None,
+            header: None,
+            footer: None,
+            media_box: Rect::new(0f64, 0f64, 612f64, 792f64),
+            rotate: 0,
+            color_vision: None,
+            attachments: vec![],
+            open_action: None,
+            metadata: None,
+            layers: vec![],
+            pool: None,
             writer,
         }
     }
@@ -41,25 +294,602 @@ impl PDF {
     pub fn from_file(file: std::fs::File) -> Self {
         Self::new(Box::new(file))
     }
+    /// Sets the default size of the pages (`/MediaBox`), which
+    /// defaults to US Letter
+    ///
+    /// The size is set on the page tree, and only pages with a
+    /// different size have their own MediaBox.
+    pub fn set_media_box(&mut self, rect: impl Into<Rect>) {
+        self.media_box = rect.into();
+    }
+    /// Sets the default rotation of the pages (`/Rotate`), see
+    /// Page::set_rotate
+    ///
+    /// # Panics
+    ///
+    /// panics if degrees is not a multiple of 90
+    pub fn set_rotate(&mut self, degrees: i64) {
+        assert!(degrees % 90 == 0, "Rotation must be a multiple of 90");
+        self.rotate = degrees;
+    }
+    /// Renders the document as it appears with a color vision
+    /// deficiency, for checking the colors are still distinguishable
+    ///
+    /// The colors of all content, including backgrounds and watermarks,
+    /// are transformed when the document is written. See ColorVision
+    pub fn simulate_color_vision(&mut self, vision: ColorVision) {
+        self.color_vision = Some(vision);
+    }
+    /// Checks the contrast of all text against the background of
+    /// the page it is on
+    ///
+    /// - min_ratio: the lowest acceptable WCAG contrast ratio, 4.5 for
+    ///   normal text at level AA
+    ///
+    /// Pages without a background are checked against white. Colors
+    /// without a known luminance are skipped, as is content added when
+    /// the document is written, such as headers and footers.
+    pub fn check_contrast(&self, min_ratio: f64) -> Vec<ContrastIssue> {
+        let white = Color::DeviceGray(1f64);
+        let mut issues = vec![];
+        for (i, (_, page)) in self.pages.iter().enumerate() {
+            let background = page.background.as_ref().unwrap_or(&white);
+            for (color, position) in page.graphics.text_colors() {
+                match color.contrast_ratio(background) {
+                    Some(ratio) if ratio < min_ratio => issues.push(ContrastIssue {
+                        page: i,
+                        position: *position,
+                        ratio,
+                    }),
+                    _ => (),
+                }
+            }
+        }
+        issues
+    }
+    /// Prevents the object numbers from being used by objects added
+    /// after this call
+    ///
+    /// The Outlines, page tree, and Catalog are numbered when the
+    /// document is created, so they use the first three numbers, see
+    /// PDF::with_first_number. Only numbers after those can be reserved.
+    ///
+    /// # Panics
+    ///
+    /// panics if a number may already be in use, such as a number of
+    /// the Outlines, page tree, or Catalog
+    pub fn reserve_numbers(&mut self, nums: impl IntoIterator<Item = usize>) {
+        self.writer.reserve_numbers(nums);
+    }
+    /// Sets the four bytes of the comment on the second line of the
+    /// file, or omits the comment if None
+    ///
+    /// The comment tells tools that the file contains binary data, and
+    /// defaults to `%âãÏÓ`. Only omit it for files without binary data.
+    ///
+    /// # Panics
+    ///
+    /// panics if any of the bytes is below 128, or the header has
+    /// already been written by flush
+    pub fn set_binary_marker(&mut self, marker: Option<[u8; 4]>) {
+        if let Some(bytes) = marker {
+            assert!(
+                bytes.iter().all(|b| *b >= 128),
+                "The binary marker must only contain bytes of 128 or more"
+            );
+        }
+        self.writer.set_binary_marker(marker);
+    }
+    /// Sets the document information dictionary (`/Info`), see Metadata
+    ///
+    /// The dictionary is left out if omit_metadata is called.
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = Some(metadata);
+    }
+    /// Writes a minimal file without document metadata
+    ///
+    /// The trailer has no `/Info` dictionary, so nothing is added
+    /// automatically either, such as the `/Producer` or `/CreationDate`,
+    /// and the file identifier doesn't depend on the time, see
+    /// set_reproducible.
+    pub fn omit_metadata(&mut self) {
+        self.writer.omit_info();
+    }
+    /// Sets the file identifier (`/ID`), instead of computing it when
+    /// the file is written
+    pub fn set_id(&mut self, id: [u8; 16]) {
+        self.writer.set_id(id);
+    }
+    /// Makes the output depend only on the document, so writing the same
+    /// document twice gives the same bytes
+    ///
+    /// The file identifier is an MD5 digest of the file, instead of
+    /// including the current time.
+    pub fn set_reproducible(&mut self) {
+        self.writer.set_reproducible();
+    }
+    /// Makes PDF::write return an error, instead of writing a file with
+    /// more than the number of objects
+    ///
+    /// This guards against content from untrusted input growing without
+    /// bound. The limit is checked before anything is written, and only
+    /// counts numbered objects, not the ones written inline by
+    /// minimize_objects.
+    pub fn set_max_objects(&mut self, max: usize) {
+        self.writer.set_max_objects(max);
+    }
+    /// Makes PDF::write return an error, instead of writing a file
+    /// larger than the number of bytes
+    ///
+    /// The output may already contain part of the file when the error
+    /// is returned, so it should be discarded.
+    pub fn set_max_size(&mut self, max: usize) {
+        self.writer.set_max_size(max);
+    }
+    /// Writes objects that are only used once, such as the fonts and
+    /// color spaces of a single page, inline where they are used,
+    /// instead of as separate numbered objects
+    ///
+    /// This reduces the number of objects and the size of the
+    /// cross-reference table. Streams, pages, and other objects that
+    /// must be indirect aren't changed. Object numbers aren't reused,
+    /// so the numbers written may have gaps.
+    pub fn minimize_objects(&mut self) {
+        self.writer.minimize_objects();
+    }
+    /// A readable listing of the document, for debugging a file that
+    /// won't open
+    ///
+    /// Lists the objects created so far, such as the Catalog and the
+    /// page tree, with their numbers and entries, followed by the pages
+    /// added. Pages are only turned into objects by PDF::write, so only
+    /// their size and amount of content is listed.
+    pub fn debug_dump(&self) -> String {
+        let mut dump = self.writer.dump();
+        for (i, (_, page)) in self.pages.iter().enumerate() {
+            let media_box = page.media_box.unwrap_or(self.media_box);
+            dump.push_str(&format!(
+                "Page {} (not yet written)\n  /MediaBox [{} {} {} {}]\n  {} bytes of content\n",
+                i + 1,
+                media_box.x(),
+                media_box.y(),
+                media_box.x() + media_box.width(),
+                media_box.y() + media_box.height(),
+                page.graphics.content_len()
+            ));
+            if !page.signature_fields.is_empty() || !page.links.is_empty() {
+                dump.push_str(&format!(
+                    "  {} annotations\n",
+                    page.signature_fields.len() + page.links.len()
+                ));
+            }
+        }
+        dump
+    }
+    /// Checks the structure of a PDF file, such as one written by PDF::write
+    ///
+    /// Checks the header, that `startxref` points at the cross-reference
+    /// table, that the trailer `/Size` and `/Root` agree with the table,
+    /// and that every object is at the offset the table gives for it.
+    /// The content of the objects isn't checked.
+    pub fn verify_bytes(bytes: &[u8]) -> Result<(), VerifyError> {
+        verify::verify(bytes)
+    }
+    /// Sets what a viewer does when the document is opened, see
+    /// OpenAction
+    pub fn set_open_action(&mut self, action: OpenAction) {
+        self.open_action = Some(action);
+    }
+    /// Sets the natural language of the document (`/Lang`)
+    ///
+    /// - lang: a language tag, such as `"en-US"`
+    ///
+    /// Screen readers and other accessibility tools use this to
+    /// choose how the text is read
+    pub fn set_language(&mut self, lang: &str) {
+        self.catalog.add_entry("Lang", PDFString::new(lang));
+    }
+    /// Sets an entry of the document catalog, written exactly as given
+    ///
+    /// - key: the key, without the `/`, such as `"PageLayout"`
+    /// - value: the PDF syntax of the value, such as `"/TwoColumnLeft"`
+    ///
+    /// This is meant for catalog entries the library doesn't support.
+    /// Entries the library sets itself, like `/Pages`, shouldn't be
+    /// replaced.
+    pub fn set_catalog_entry(&mut self, key: &str, value: &str) {
+        self.catalog.add_entry(key, Raw::new(value));
+    }
+    /// Draws content once, so it can be placed on any number of pages
+    ///
+    /// - bbox: the bounding box of the content, which it is clipped to
+    /// - draw: draws the content, in the coordinates of the stamp
+    ///
+    /// See Stamp::place and Page::stamp
+    ///
+    /// # Panics
+    ///
+    /// panics if the bbox has no area
+    pub fn define_stamp(
+        &mut self,
+        bbox: impl Into<Rect>,
+        draw: impl FnOnce(&mut GraphicContext),
+    ) -> Rc<Stamp> {
+        let bbox = bbox.into();
+        assert!(
+            bbox.width() != 0f64 && bbox.height() != 0f64,
+            "The bbox of a stamp must have an area"
+        );
+        let mut ctx = GraphicContext::new();
+        draw(&mut ctx);
+        Stamp::new(ctx.compile_form(bbox, &mut self.writer), bbox)
+    }
+    /// Attaches a file to the document (`/EmbeddedFiles`)
+    ///
+    /// - name: the file name shown to the user
+    /// - data: the contents of the file
+    ///
+    /// Viewers list attached files in their attachments panel, or as
+    /// a package, see PDF::set_collection.
+    pub fn attach_file(&mut self, name: &str, data: Vec<u8>) {
+        let size = data.len();
+        let file = self.writer.allocate(Stream::new(
+            Dict::from_vec(vec![
+                ("Type", Name::new("EmbeddedFile") as Rc<dyn PDFData>),
+                ("Params", Dict::from_vec(vec![("Size", Rc::new(size))])),
+            ]),
+            data,
+        ));
+        let spec = self.writer.allocate(Dict::from_vec(vec![
+            ("Type", Name::new("Filespec") as Rc<dyn PDFData>),
+            ("F", PDFString::new(name)),
+            ("UF", PDFString::new(name)),
+            ("EF", Dict::from_vec(vec![("F", file as Rc<dyn PDFData>)])),
+        ]));
+        self.attachments.push((name.to_string(), spec));
+    }
+    /// Adds a layer of optional content to the document, see Layer
+    ///
+    /// The returned layer is used to add content to it, with
+    /// Page::add_to_layer. Viewers list the layers in the order they
+    /// are added.
+    pub fn add_layer(&mut self, layer: Layer) -> Rc<Layer> {
+        let object = self.writer.allocate(layer.data());
+        let name = Name::new(format!("OC{}", self.layers.len() + 1));
+        let layer = layer.added(object, name);
+        self.layers.push(layer.clone());
+        layer
+    }
+    /// Makes the document a portable collection, so viewers present the
+    /// attached files as a package (`/Collection`)
+    ///
+    /// - collection: see Collection
+    pub fn set_collection(&mut self, collection: Collection) {
+        self.catalog.add_entry("Collection", collection.data());
+    }
+    /// Marks the document as a tagged PDF (`/MarkInfo`)
+    ///
+    /// Viewers and screen readers only use the structure tree built by
+    /// Page::add_tagged if the document is marked as tagged.
+    pub fn set_tagged(&mut self, tagged: bool) {
+        self.catalog.add_entry(
+            "MarkInfo",
+            Dict::from_vec(vec![("Marked", Rc::new(tagged))]),
+        );
+    }
+    /// Sets the permissions of the document (`/Perms`)
+    ///
+    /// See Perms: the signature dictionaries are added without a
+    /// signature, for an external signer to complete.
+    pub fn set_perms(&mut self, perms: Perms) {
+        let perms = perms.add(&mut self.writer);
+        self.catalog.add_entry("Perms", perms);
+    }
+    /// Gets an ICC profile based color space, embedding the profile
+    /// only once per document
+    ///
+    /// - profile: the ICC profile data
+    /// - components: the number of color components, 1, 3, or 4
+    ///
+    /// Requesting the same profile again returns the existing color
+    /// space, so every page using it references the same profile stream.
+    /// See ColorSpace::icc_based
+    pub fn icc_profile(&mut self, profile: Vec<u8>, components: usize) -> Rc<ColorSpace> {
+        if let Some(space) = self
+            .icc_profiles
+            .iter()
+            .find(|s| s.components() == components && s.profile() == Some(&profile[..]))
+        {
+            return space.clone();
+        }
+        let space = ColorSpace::icc_based(profile, components);
+        self.icc_profiles.push(space.clone());
+        space
+    }
+    /// Draws semi-transparent text across the middle of every page,
+    /// on top of the rest of the content
+    ///
+    /// - text: the watermark, such as `"DRAFT"`
+    /// - angle: counter-clockwise rotation of the text, in degrees
+    /// - alpha: from 0 (transparent) to 1 (opaque)
+    ///
+    /// The watermark is added when the document is written, so it is
+    /// also drawn on pages added after this call.
+    pub fn add_watermark(&mut self, text: &str, angle: f64, alpha: f64) {
+        self.watermark = Some(Watermark::new(text, angle, alpha));
+    }
+    /// Sets a header, drawn on every page
+    ///
+    /// The callback is called with the index of the page (starting at
+    /// 0), the number of pages, and the MediaBox of the page, when the
+    /// document is written. The graphic it returns is drawn after the
+    /// rest of the content on the page.
+    pub fn set_header<G: Graphic + 'static>(
+        &mut self,
+        f: impl Fn(usize, usize, Rect) -> Rc<G> + 'static,
+    ) {
+        self.header = Some(Self::page_callback(f));
+    }
+    /// Sets a footer, drawn on every page
+    ///
+    /// See PDF::set_header, the two only differ in which is drawn first
+    pub fn set_footer<G: Graphic + 'static>(
+        &mut self,
+        f: impl Fn(usize, usize, Rect) -> Rc<G> + 'static,
+    ) {
+        self.footer = Some(Self::page_callback(f));
+    }
+    fn page_callback<G: Graphic + 'static>(
+        f: impl Fn(usize, usize, Rect) -> Rc<G> + 'static,
+    ) -> PageCallback {
+        Box::new(move |page, index, total| {
+            let g = f(index, total, page.media_box());
+            page.add(g);
+        })
+    }
     /// Adds a page to the PDF
     ///
     /// The page is consumed, and may (or may not)
-    /// be written to the output right away.
-    pub fn add_page(&mut self, page: Page) {
-        self.pages.push(page);
+    /// be written to the output right away. The returned handle can
+    /// be shown by links and the open action, see Link::page.
+    pub fn add_page(&mut self, page: Page) -> PageRef {
+        let r = PageRef::next();
+        self.pages.push((r, page));
+        r
+    }
+    /// Inserts the page at the index, starting at 0, moving the pages
+    /// after it back, see add_page
+    ///
+    /// # Panics
+    ///
+    /// panics if the index is greater than the number of pages
+    pub fn insert_page(&mut self, index: usize, page: Page) -> PageRef {
+        assert!(
+            index <= self.pages.len(),
+            "The page is inserted at {}, but there are {} pages",
+            index,
+            self.pages.len()
+        );
+        let r = PageRef::next();
+        self.pages.insert(index, (r, page));
+        r
+    }
+    /// Removes the page at the index, starting at 0, moving the pages
+    /// after it forward
+    ///
+    /// Handles of the other pages still refer to the same pages.
+    /// PDF::write returns an error if a link or the open action shows
+    /// the removed page.
+    ///
+    /// # Panics
+    ///
+    /// panics if there is no page at the index
+    pub fn remove_page(&mut self, index: usize) -> Page {
+        assert!(
+            index < self.pages.len(),
+            "Page {} is removed, but there are {} pages",
+            index,
+            self.pages.len()
+        );
+        self.pages.remove(index).1
+    }
+    /// Adds pages containing the text of the flow, until all of
+    /// the text has been placed
+    ///
+    /// - rect: the area of each page the text is placed in
+    pub fn add_flow(&mut self, flow: TextFlow, rect: impl Into<Rect>) {
+        let rect = rect.into();
+        let mut rest = Some(flow);
+        while let Some(flow) = rest.take() {
+            let (text, next) = flow.layout(rect);
+            let mut page = Page::new();
+            page.add(text);
+            self.add_page(page);
+            rest = next;
+        }
+    }
+    /// Writes the start of the file, and flushes the output, such as
+    /// a socket, without finishing the document
+    ///
+    /// The objects of the document can still change until it is
+    /// written, so only the header is written before write. Write
+    /// still produces the complete file, with the same bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing or flushing the output fails
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
     }
     /// Completes the writing process
     ///
+    /// A document without pages is written with one blank page, since
+    /// the page tree must have at least one page.
+    ///
+    /// Lines always end with LF, whatever the platform, so the same
+    /// document is written as the same bytes everywhere. Carriage returns
+    /// only appear in stream data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails, a page selects a font missing
+    /// from its resources, or the open action shows a page that doesn't
+    /// exist
+    ///
     /// TODO: this may be added to a drop implementation
     pub fn write(mut self) -> std::io::Result<()> {
+        if self.pages.is_empty() {
+            self.add_page(Page::new());
+        }
         let (pg_obj, tmp) = (&mut self.pages_obj, &mut self.writer);
-        let p: Vec<Rc<dyn Object>> = self
+        let mut tree = structure::StructTree::new();
+        let watermark = self.watermark.take();
+        let (header, footer) = (self.header.take(), self.footer.take());
+        let total = self.pages.len();
+        let (media_box, rotate) = (self.media_box, self.rotate);
+        let color_vision = self.color_vision;
+        let mut fields = vec![];
+        let mut images = graphics::ImageCache::default();
+        let p = self
             .pages
             .into_iter()
-            .map(|p| tmp.add_object(p.render(pg_obj.clone())))
+            .enumerate()
+            .map(|(i, (r, mut p))| {
+                p.inherited = (media_box, rotate);
+                for f in header.iter().chain(footer.iter()) {
+                    f(&mut p, i, total);
+                }
+                p.graphics
+                    .replace_placeholder(TOTAL_PAGES, &total.to_string());
+                if let Some(w) = &watermark {
+                    w.draw(&mut p);
+                }
+                p.graphics.share_images(&mut images);
+                let tags = std::mem::take(&mut p.tags);
+                let signature_fields = std::mem::take(&mut p.signature_fields);
+                let links = std::mem::take(&mut p.links);
+                let page = p
+                    .render(pg_obj.clone(), color_vision, tmp)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                tmp.add_object(page.clone());
+                // Pages and annotations must be indirect
+                tmp.keep_indirect(page.clone());
+                let widgets: Vec<_> = signature_fields
+                    .iter()
+                    .map(|f| f.add(page.clone(), tmp))
+                    .collect();
+                fields.extend(widgets.iter().cloned());
+                if !tags.is_empty() {
+                    let key = tree.add_page(page.clone(), &tags, tmp);
+                    page.add_entry("StructParents", Rc::new(key));
+                }
+                Ok((r, page, widgets, links))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        // Links may show pages after their own, so they are added once
+        // every page is an object
+        let dest = |r: PageRef| -> std::io::Result<Rc<dyn PDFData>> {
+            let (_, page, ..) = p.iter().find(|(p, ..)| *p == r).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "A link or the open action shows a page that isn't in the document",
+                )
+            })?;
+            let dest: Vec<Rc<dyn PDFData>> = vec![page.clone(), Name::new("Fit")];
+            Ok(Rc::new(dest))
+        };
+        for (_, page, widgets, links) in &p {
+            let mut annots = widgets.clone();
+            for link in links {
+                let annot = link.data(page.clone(), dest)?;
+                self.writer.add_object(annot.clone());
+                self.writer.keep_indirect(annot.clone());
+                annots.push(annot);
+            }
+            if !annots.is_empty() {
+                page.add_entry("Annots", Rc::new(annots));
+            }
+        }
+        match self.open_action {
+            Some(OpenAction::Page(i)) => {
+                let (r, ..) = p.get(i).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "The open action shows page {}, but there are {} pages",
+                            i,
+                            p.len()
+                        ),
+                    )
+                })?;
+                self.catalog.add_entry("OpenAction", dest(*r)?);
+            }
+            Some(OpenAction::PageRef(r)) => {
+                self.catalog.add_entry("OpenAction", dest(r)?);
+            }
+            Some(OpenAction::JavaScript(js)) => {
+                self.catalog.add_entry(
+                    "OpenAction",
+                    Dict::from_vec(vec![
+                        ("S", Name::new("JavaScript") as Rc<dyn PDFData>),
+                        ("JS", PDFString::new(js)),
+                    ]),
+                );
+            }
+            None => {}
+        }
+        if let Some(metadata) = &self.metadata {
+            self.writer.set_info(metadata.data());
+        }
+        if !tree.is_empty() {
+            let root = tree.finish(&mut self.writer);
+            self.catalog.add_entry("StructTreeRoot", root);
+        }
+        if !self.layers.is_empty() {
+            self.catalog
+                .add_entry("OCProperties", layer::properties(&self.layers));
+        }
+        if !self.attachments.is_empty() {
+            // The names of a name tree are sorted
+            self.attachments.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut names: Vec<Rc<dyn PDFData>> = vec![];
+            for (name, spec) in self.attachments {
+                names.push(PDFString::new(name));
+                names.push(spec);
+            }
+            self.catalog.add_entry(
+                "Names",
+                Dict::from_vec(vec![(
+                    "EmbeddedFiles",
+                    Dict::from_vec(vec![("Names", Rc::new(names) as Rc<dyn PDFData>)])
+                        as Rc<dyn PDFData>,
+                )]),
+            );
+        }
+        if !fields.is_empty() {
+            // SignaturesExist and AppendOnly
+            self.catalog.add_entry(
+                "AcroForm",
+                Dict::from_vec(vec![
+                    ("Fields", Rc::new(fields) as Rc<dyn PDFData>),
+                    ("SigFlags", Rc::new(3usize)),
+                ]),
+            );
+        }
+        self.writer.keep_indirect(self.pages_obj.clone());
+        self.writer.keep_indirect(self.outlines.clone());
+        self.pages_obj
+            .add_entry("MediaBox", self.media_box.as_data());
+        if self.rotate != 0 {
+            self.pages_obj
+                .add_entry("Rotate", Rc::new(self.rotate.rem_euclid(360) as usize));
+        }
+        let kids: Vec<_> = p
+            .into_iter()
+            .map(|(_, page, ..)| page as Rc<dyn Object>)
             .collect();
-        self.pages_obj.add_entry("Count", Rc::new(p.len()));
-        self.pages_obj.add_entry("Kids", Rc::new(p));
+        self.pages_obj.add_entry("Count", Rc::new(kids.len()));
+        self.pages_obj.add_entry("Kids", Rc::new(kids));
 
         self.writer.write()
     }
@@ -68,48 +898,1098 @@ impl PDF {
 pub struct Page {
     // elements: Vec<Box<dyn Graphic>>,
     graphics: GraphicContext,
+    duration: Option<f64>,
+    transition: Option<Rc<Transition>>,
+    media_box: Option<Rect>,
+    crop_box: Option<Rect>,
+    rotate: Option<i64>,
+    /// The MediaBox and Rotate of the page tree
+    inherited: (Rect, i64),
+    background: Option<Color>,
+    /// The margin around the content, if the MediaBox fits the content
+    fit: Option<f64>,
+    /// The font and size used by text_at
+    default_font: (Rc<Font>, f64),
+    /// Structure type of each marked content id
+    tags: Vec<Rc<Name>>,
+    signature_fields: Vec<Rc<SignatureField>>,
+    links: Vec<Link>,
+    contents: ContentsMode,
 }
 
+impl Default for Page {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Page {
     pub fn new() -> Self {
         Self {
             // elements: vec![],
             graphics: GraphicContext::new(),
+            duration: None,
+            transition: None,
+            media_box: None,
+            crop_box: None,
+            rotate: None,
+            inherited: (Rect::new(0f64, 0f64, 612f64, 792f64), 0),
+            background: None,
+            fit: None,
+            default_font: (Font::helvetica(), 12f64),
+            tags: vec![],
+            signature_fields: vec![],
+            links: vec![],
+            contents: ContentsMode::Single,
         }
     }
-    pub fn add(&mut self, g: Rc<impl Graphic>) {
-        self.graphics.render(g);
+    /// A page without any content, such as a separator between sections
+    pub fn blank() -> Self {
+        Self::new()
     }
-    fn render(self, parent: Rc<dyn PDFData>) -> Rc<dyn Object> {
-        let (streams, resources) = self.graphics.compile();
-        if streams.len() == 1 {
-            ObjRef::new(
-                0,
-                Dict::from_vec(vec![
-                    ("Type", Name::new("Page")),
-                    ("Parent", parent),
-                    (
-                        "MediaBox",
-                        graphics::Rect::new(0f64, 0f64, 612f64, 792f64).as_data(),
-                    ),
-                    ("Contents", streams[0].clone()),
-                    ("Resources", resources),
-                ]),
+    /// Creates a page with its own size, in points, instead of the
+    /// default size of the document, see set_media_box
+    pub fn with_size(width: f64, height: f64) -> Self {
+        let mut page = Self::new();
+        page.set_media_box(Rect::new(0f64, 0f64, width, height));
+        page
+    }
+    /// Creates a new page with the same content and settings as this one
+    ///
+    /// Content added to either page afterwards isn't added to the other,
+    /// so this can be used to build pages from a template. Signature
+    /// fields aren't copied, since a field can only be on one page.
+    pub fn clone_layout(&self) -> Self {
+        Self {
+            graphics: self.graphics.clone(),
+            duration: self.duration,
+            transition: self.transition.clone(),
+            media_box: self.media_box,
+            crop_box: self.crop_box,
+            rotate: self.rotate,
+            inherited: self.inherited,
+            background: self.background.clone(),
+            fit: self.fit,
+            default_font: self.default_font.clone(),
+            tags: self.tags.clone(),
+            signature_fields: vec![],
+            links: self.links.clone(),
+            contents: self.contents,
+        }
+    }
+    /// Removes the content of the page, keeping its settings, such as
+    /// the MediaBox and background
+    ///
+    /// The content buffers are reused, see GraphicContext::reset, so a
+    /// page can be drawn, copied with clone_layout, and cleared for the
+    /// next one when generating many similar pages.
+    pub fn clear(&mut self) {
+        self.graphics.reset();
+        self.tags.clear();
+        self.links.clear();
+        self.signature_fields.clear();
+    }
+    /// Sets the maximum time the page is displayed during a
+    /// presentation, before advancing to the next page
+    ///
+    /// - seconds: the display duration (`/Dur`)
+    pub fn set_duration(&mut self, seconds: f64) {
+        self.duration = Some(seconds);
+    }
+    /// Sets the transition effect used when moving to this page
+    /// during a presentation
+    ///
+    /// - style: See TransitionStyle
+    /// - duration: the duration of the effect, in seconds
+    pub fn set_transition(&mut self, style: TransitionStyle, duration: f64) {
+        self.transition = Some(Transition::new(style, duration));
+    }
+    /// Sets the size of the page (`/MediaBox`), instead of the
+    /// default size of the document, such as `PageSize::A4`
+    pub fn set_media_box(&mut self, rect: impl Into<Rect>) {
+        self.media_box = Some(rect.into());
+    }
+    /// The size of the page
+    ///
+    /// Until the document is written, pages without their own size
+    /// report US Letter, rather than the default size of the document
+    pub fn media_box(&self) -> Rect {
+        self.media_box.unwrap_or(self.inherited.0)
+    }
+    /// Sets the visible area of the page (`/CropBox`), in the
+    /// coordinates of the MediaBox, such as to hide the margins of a
+    /// scanned page
+    pub fn set_crop_box(&mut self, rect: impl Into<Rect>) {
+        self.crop_box = Some(rect.into());
+    }
+    /// The visible area of the page, which is the MediaBox unless
+    /// set_crop_box was used
+    pub fn crop_box(&self) -> Rect {
+        self.crop_box.unwrap_or_else(|| self.media_box())
+    }
+    /// The MediaBox coordinates of a point relative to the bottom left
+    /// corner of the CropBox, such as to place a link in the visible
+    /// area of the page
+    pub fn crop_point(&self, point: impl Into<Point>) -> Point {
+        let (crop, point) = (self.crop_box(), point.into());
+        Point::new(crop.x() + point.x(), crop.y() + point.y())
+    }
+    /// The MediaBox coordinates of a rect relative to the bottom left
+    /// corner of the CropBox, see crop_point
+    pub fn crop_rect(&self, rect: impl Into<Rect>) -> Rect {
+        let rect = rect.into();
+        let origin = self.crop_point((rect.x(), rect.y()));
+        Rect::new(origin.x(), origin.y(), rect.width(), rect.height())
+    }
+    /// Sets the clockwise rotation of the page when it is displayed
+    /// (`/Rotate`)
+    ///
+    /// # Panics
+    ///
+    /// panics if degrees is not a multiple of 90
+    pub fn set_rotate(&mut self, degrees: i64) {
+        assert!(degrees % 90 == 0, "Rotation must be a multiple of 90");
+        self.rotate = Some(degrees);
+    }
+    /// Sizes the page to its content (`/MediaBox`), when it is rendered
+    ///
+    /// - margin: the space added on every side of the content
+    ///
+    /// The box is the union of the bounds of the graphics on the page,
+    /// see Graphic::bounds. Pages without any content with known bounds
+    /// keep their size.
+    pub fn fit_to_content(&mut self, margin: f64) {
+        self.fit = Some(margin);
+    }
+    /// The union of the bounds of the graphics added so far, see
+    /// Graphic::bounds, or None if none of them have known bounds
+    ///
+    /// The bounds are in the coordinates content is drawn in, including
+    /// the top left mode of set_top_left.
+    pub fn content_bounds(&self) -> Option<Rect> {
+        self.graphics.content_bounds()
+    }
+    /// Makes (0, 0) the top left of the page, with y growing downward,
+    /// see GraphicContext::set_top_left
+    ///
+    /// # Panics
+    ///
+    /// panics if content has already been added to the page
+    pub fn set_top_left(&mut self) {
+        self.graphics.set_top_left();
+    }
+    /// The point of the MediaBox at the position, in the coordinates
+    /// content is drawn in, including the top left mode of set_top_left
+    ///
+    /// The MediaBox inherited from the document is only known when the
+    /// page is written: for pages with the default size, resolve the
+    /// position against the MediaBox passed to PDF::set_header or
+    /// PDF::set_footer instead.
+    pub fn resolve(&self, position: Position) -> Point {
+        let media_box = self.media_box();
+        let p = position.resolve(media_box);
+        if self.graphics.is_top_left() {
+            Point::new(
+                p.x() - media_box.x(),
+                media_box.y() + media_box.height() - p.y(),
             )
         } else {
-            ObjRef::new(
-                0,
-                Dict::from_vec(vec![
-                    ("Type", Name::new("Page")),
-                    ("Parent", parent),
-                    (
-                        "MediaBox",
-                        graphics::Rect::new(0f64, 0f64, 612f64, 792f64).as_data(),
-                    ),
-                    ("Contents", Rc::new(streams.clone())),
-                    ("Resources", resources),
-                ]),
-            )
+            p
+        }
+    }
+    /// Sets the line width and colors at the start of the content, for
+    /// content that doesn't set its own
+    ///
+    /// Graphics that only set one color keep the default for the other,
+    /// such as the stroke color of text or filled paths.
+    ///
+    /// # Panics
+    ///
+    /// panics if content has already been added
+    pub fn set_defaults(&mut self, line_width: f64, stroke_color: Color, fill_color: Color) {
+        assert!(self.graphics.is_empty(), "Content has already been added");
+        self.graphics.set_line_width(line_width);
+        self.graphics.set_colors(fill_color, stroke_color);
+    }
+    /// Fills the whole page with a color, behind all of the content
+    ///
+    /// The background is added when the page is rendered, so it covers
+    /// the final MediaBox, and can be set at any point.
+    pub fn set_background(&mut self, color: Color) {
+        self.background = Some(color);
+    }
+    pub fn add(&mut self, g: Rc<impl Graphic + ?Sized>) {
+        self.graphics.render(g);
+    }
+    /// Adds each of the graphics, in order, see add
+    ///
+    /// Graphics of different types can be added together as
+    /// `Rc<dyn Graphic>`
+    pub fn extend<G: Graphic + ?Sized>(&mut self, graphics: impl IntoIterator<Item = Rc<G>>) {
+        for g in graphics {
+            self.add(g);
         }
     }
+    /// Sets the font used by text_at, which defaults to 12 point Helvetica
+    pub fn set_default_font(&mut self, font: Rc<Font>, size: f64) {
+        self.default_font = (font, size);
+    }
+    /// Draws a line of text in black, using the default font
+    ///
+    /// - pos: the start of the baseline
+    pub fn text_at(&mut self, pos: impl Into<Point>, text: &str) {
+        let (font, size) = self.default_font.clone();
+        self.add(Rc::new(
+            Text::new(font, size)
+                .move_to(pos)
+                .text(text)
+                .fill(Color::default()),
+        ));
+    }
+    /// Adds a graphic as tagged content
+    ///
+    /// - tag: the structure type, such as `"P"`, `"H1"`, or `"Figure"`
+    ///
+    /// The graphic is wrapped in a marked content sequence, and gets
+    /// its own structure element in the document's structure tree.
+    pub fn add_tagged(&mut self, tag: &str, g: Rc<impl Graphic + ?Sized>) {
+        let tag = Name::new(tag);
+        self.graphics
+            .begin_marked_content(tag.clone(), Some(self.tags.len()));
+        self.graphics.render(g);
+        self.graphics.end_marked_content();
+        self.tags.push(tag);
+    }
+    /// Adds a graphic to a layer of optional content, which viewers
+    /// can show or hide, see PDF::add_layer
+    ///
+    /// # Panics
+    ///
+    /// panics if the layer wasn't added with PDF::add_layer
+    pub fn add_to_layer(&mut self, layer: &Rc<Layer>, g: Rc<impl Graphic + ?Sized>) {
+        let (object, name) = layer.object();
+        self.graphics.begin_optional_content(name, object);
+        self.graphics.render(g);
+        self.graphics.end_marked_content();
+    }
+    /// Places a stamp on the page, transformed by the matrix, see
+    /// PDF::define_stamp
+    pub fn stamp(&mut self, stamp: &Rc<Stamp>, matrix: Matrix) {
+        self.add(stamp.place(matrix));
+    }
+    /// Adds an entry to the resources of the page, see
+    /// GraphicContext::add_named_resource
+    pub fn add_named_resource(&mut self, category: &str, name: &str, value: &str) {
+        self.graphics.add_named_resource(category, name, value);
+    }
+    /// Adds a signature field to the page, see SignatureField
+    pub fn add_signature_field(&mut self, field: Rc<SignatureField>) {
+        self.signature_fields.push(field);
+    }
+    /// Sets how the `/Contents` of the page is written, see ContentsMode
+    pub fn contents_mode(&mut self, mode: ContentsMode) {
+        self.contents = mode;
+    }
+    /// Adds a link to the page, see Link
+    pub fn add_link(&mut self, link: Link) {
+        self.links.push(link);
+    }
+    fn render(
+        mut self,
+        parent: Rc<dyn PDFData>,
+        color_vision: Option<ColorVision>,
+        write: &mut pdf::PDFWrite,
+    ) -> Result<Rc<ObjRef<Dict>>, CompileError> {
+        // The content is flipped against the MediaBox it was drawn for,
+        // so it stays in place if the MediaBox is fit to the content
+        let media_box = self.media_box();
+        self.graphics.flip(media_box);
+        if let (Some(margin), Some(bounds)) = (self.fit, self.graphics.content_bounds()) {
+            let bounds = if self.graphics.is_top_left() {
+                Rect::new(
+                    bounds.x() + media_box.x(),
+                    media_box.y() + media_box.height() - bounds.y() - bounds.height(),
+                    bounds.width(),
+                    bounds.height(),
+                )
+            } else {
+                bounds
+            };
+            self.media_box = Some(Rect::new(
+                bounds.x() - margin,
+                bounds.y() - margin,
+                bounds.width() + margin * 2f64,
+                bounds.height() + margin * 2f64,
+            ));
+        }
+        if let Some(color) = self.background.take() {
+            self.graphics
+                .render_first(Path::new().rect(self.media_box()).fill(color));
+        }
+        if let Some(vision) = color_vision {
+            self.graphics.simulate_color_vision(vision);
+        }
+        let (streams, resources) = self.graphics.compile(write)?;
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("Page")),
+            ("Parent", parent),
+            ("Resources", resources),
+        ]);
+        if streams.len() == 1 && self.contents == ContentsMode::Single {
+            dict.add_entry("Contents", streams[0].clone());
+        } else {
+            dict.add_entry("Contents", Rc::new(streams.clone()));
+        }
+        // Inherited attributes are only written if the page differs
+        let (media_box, rotate) = self.inherited;
+        dict.add_optional(
+            "MediaBox",
+            self.media_box
+                .filter(|r| *r != media_box)
+                .map(|r| r.as_data() as Rc<dyn PDFData>),
+        );
+        dict.add_optional(
+            "CropBox",
+            self.crop_box.map(|r| r.as_data() as Rc<dyn PDFData>),
+        );
+        dict.add_optional(
+            "Rotate",
+            self.rotate
+                .filter(|r| *r != rotate)
+                .map(|r| Rc::new(r.rem_euclid(360) as usize) as Rc<dyn PDFData>),
+        );
+        dict.add_optional("Dur", self.duration.map(|d| Rc::new(d) as Rc<dyn PDFData>));
+        dict.add_optional("Trans", self.transition.map(|t| t as Rc<dyn PDFData>));
+        Ok(ObjRef::new(0, dict))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// An output writing into the buffer
+    pub(crate) fn output(buffer: &Rc<RefCell<Vec<u8>>>) -> Box<dyn std::io::Write> {
+        Box::new(pdf::Buffer(buffer.clone()))
+    }
+
+    /// Writes the document built by build, and returns the file
+    pub(crate) fn written(build: impl FnOnce(&mut PDF)) -> Vec<u8> {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let mut pdf = PDF::new(output(&buffer));
+        build(&mut pdf);
+        pdf.write().unwrap();
+        let file = buffer.borrow().clone();
+        file
+    }
+
+    /// The file as text, for finding the objects in it
+    pub(crate) fn text(file: &[u8]) -> String {
+        String::from_utf8_lossy(file).into_owned()
+    }
+
+    #[test]
+    fn first_number() {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let pdf = PDF::with_first_number(output(&buffer), 100);
+        pdf.write().unwrap();
+        let file = text(&buffer.borrow());
+        assert!(file.contains("100 0 obj"));
+        assert!(!file.contains("\n1 0 obj"));
+    }
+
+    #[test]
+    #[should_panic(expected = "may already be in use")]
+    fn reserve_numbers_of_the_catalog() {
+        let mut pdf = PDF::with_first_number(Box::new(std::io::sink()), 100);
+        pdf.reserve_numbers(100..103);
+    }
+
+    #[test]
+    fn reserve_numbers_after_the_catalog() {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let mut pdf = PDF::with_first_number(output(&buffer), 100);
+        pdf.reserve_numbers(103..105);
+        pdf.write().unwrap();
+        let file = text(&buffer.borrow());
+        assert!(!file.contains("103 0 obj"));
+        assert!(!file.contains("104 0 obj"));
+        assert!(file.contains("105 0 obj"));
+    }
+
+    #[test]
+    fn fit_to_content() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(
+                Path::new()
+                    .rect((100f64, 100f64, 50f64, 50f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            page.fit_to_content(10f64);
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/MediaBox [90 90 160 160]"));
+    }
+
+    #[test]
+    fn fit_to_content_top_left() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_top_left();
+            page.add(
+                Path::new()
+                    .rect((100f64, 100f64, 50f64, 50f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            page.fit_to_content(10f64);
+            pdf.add_page(page);
+        }));
+        // The content stays where it is drawn on the default page, 100
+        // below its top left corner
+        assert!(file.contains("/MediaBox [90 632 160 702]"));
+        assert!(file.contains("1 0 0 -1 0 792 cm"));
+    }
+
+    #[test]
+    fn reproducible_output() {
+        let document = || {
+            written(|pdf| {
+                pdf.set_reproducible();
+                pdf.set_metadata(Metadata::new().title("Test"));
+                let mut page = Page::new();
+                page.add(
+                    Path::new()
+                        .rect((100f64, 100f64, 50f64, 50f64))
+                        .fill(Color::DeviceGray(0f64)),
+                );
+                pdf.add_page(page);
+            })
+        };
+        assert_eq!(document(), document());
+    }
+
+    #[test]
+    fn omit_metadata() {
+        let file = text(&written(|pdf| {
+            pdf.set_metadata(Metadata::new().title("Test"));
+            pdf.omit_metadata();
+            pdf.add_page(Page::new());
+        }));
+        assert!(!file.contains("/Info"));
+        assert!(!file.contains("Producer"));
+        assert!(!file.contains("CreationDate"));
+    }
+
+    #[test]
+    fn omit_metadata_is_reproducible() {
+        let document = || {
+            written(|pdf| {
+                pdf.omit_metadata();
+                pdf.add_page(Page::new());
+            })
+        };
+        assert_eq!(document(), document());
+    }
+
+    #[test]
+    fn max_objects() {
+        let mut pdf = PDF::new(Box::new(std::io::sink()));
+        pdf.set_max_objects(3);
+        pdf.add_page(Page::new());
+        let e = pdf.write().unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn duration_and_transition() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_duration(5f64);
+            page.set_transition(
+                TransitionStyle::Wipe {
+                    direction: transition::Direction::TopToBottom,
+                },
+                1f64,
+            );
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/Dur 5\n"));
+        assert!(file.contains("/Trans <<\n/Type /Trans\n/D 1\n/Di 270\n/S /Wipe\n>>"));
+    }
+
+    #[test]
+    fn language() {
+        let file = text(&written(|pdf| {
+            pdf.set_language("en-US");
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/Lang (en-US)"));
+    }
+
+    #[test]
+    fn icc_profile_shared_across_pages() {
+        let file = text(&written(|pdf| {
+            for _ in 0..2 {
+                let space = pdf.icc_profile(b"profile".to_vec(), 3);
+                let mut page = Page::new();
+                page.add(
+                    Path::new()
+                        .rect((100f64, 100f64, 50f64, 50f64))
+                        .fill(Color::ICCBased(space, vec![1f64, 0f64, 0f64])),
+                );
+                pdf.add_page(page);
+            }
+        }));
+        assert_eq!(file.matches("/Alternate /DeviceRGB").count(), 1);
+        assert_eq!(file.matches("/ICCBased").count(), 2);
+    }
+
+    #[test]
+    fn struct_parents() {
+        let file = text(&written(|pdf| {
+            for _ in 0..2 {
+                let mut page = Page::new();
+                page.add_tagged(
+                    "P",
+                    Path::new()
+                        .rect((100f64, 100f64, 50f64, 50f64))
+                        .fill(Color::DeviceGray(0f64)),
+                );
+                pdf.add_page(page);
+            }
+        }));
+        assert!(file.contains("/StructParents 0\n"));
+        assert!(file.contains("/StructParents 1\n"));
+        assert!(file.contains("/Nums [0 [6 0 R] 1 [9 0 R]]"));
+        assert!(file.contains("/Pg 5 0 R"));
+        assert!(file.contains("/Pg 8 0 R"));
+    }
+
+    #[test]
+    fn background() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(
+                Path::new()
+                    .rect((100f64, 100f64, 50f64, 50f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            page.set_background(Color::red());
+            page.set_media_box((0f64, 0f64, 200f64, 300f64));
+            pdf.add_page(page);
+        }));
+        // The background covers the final MediaBox, before the content
+        assert!(file.contains(
+            "stream\n q /DeviceRGB cs  1 0 0 scn  0 0 200 300 re f Q 100 100 50 50 re f\n"
+        ));
+    }
+
+    #[test]
+    fn watermark() {
+        let file = text(&written(|pdf| {
+            pdf.add_watermark("DRAFT", 90f64, 0.25);
+            pdf.add_page(Page::new());
+            pdf.add_page(Page::new());
+        }));
+        assert_eq!(file.matches("/ca 0.25\n").count(), 2);
+        let drawn: Vec<_> = file.match_indices(" Tm (DRAFT) Tj").collect();
+        assert_eq!(drawn.len(), 2);
+        for (i, _) in drawn {
+            let matrix: Vec<f64> = file[..i]
+                .rsplit(' ')
+                .take(6)
+                .map(|n| n.parse().unwrap())
+                .collect();
+            // Rotated a quarter turn, so [0 1 -1 0 e f]
+            let (b, c) = (matrix[4], matrix[3]);
+            assert!((b - 1f64).abs() < 1e-9 && (c + 1f64).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn footer() {
+        let file = text(&written(|pdf| {
+            pdf.set_footer(|index, total, rect| {
+                Rc::new(
+                    graphics::Text::new(graphics::Font::helvetica(), 10f64)
+                        .move_to((rect.x() + 10f64, rect.y() + 10f64))
+                        .text(format!("Page {} of {}", index + 1, total))
+                        .fill(Color::DeviceGray(0f64)),
+                )
+            });
+            for _ in 0..3 {
+                pdf.add_page(Page::new());
+            }
+        }));
+        for n in 1..=3 {
+            assert_eq!(file.matches(&format!("(Page {} of 3) Tj", n)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn total_pages() {
+        let file = text(&written(|pdf| {
+            for n in 1..=10 {
+                let mut page = Page::new();
+                page.add(Rc::new(
+                    graphics::Text::new(graphics::Font::helvetica(), 10f64)
+                        .move_to((10f64, 10f64))
+                        .text(format!("Page {} of {}", n, TOTAL_PAGES))
+                        .fill(Color::DeviceGray(0f64)),
+                ));
+                pdf.add_page(page);
+            }
+        }));
+        assert_eq!(file.matches(" of 10) Tj").count(), 10);
+        assert!(!file.contains(TOTAL_PAGES));
+    }
+
+    #[test]
+    fn without_binary_marker() {
+        let file = written(|pdf| {
+            pdf.set_binary_marker(None);
+            pdf.add_page(Page::new());
+        });
+        assert!(file.starts_with(b"%PDF-1.4\n1 0 obj\n"));
+        assert!(file.is_ascii());
+    }
+
+    #[test]
+    fn inherited_media_box_and_rotate() {
+        let file = text(&written(|pdf| {
+            pdf.set_media_box(PageSize::A4);
+            pdf.set_rotate(90);
+            pdf.add_page(Page::new());
+            let mut page = Page::new();
+            page.set_rotate(180);
+            pdf.add_page(page);
+        }));
+        assert_eq!(file.matches("/MediaBox").count(), 1);
+        assert!(file.contains("/MediaBox [0 0 595 842]"));
+        assert_eq!(file.matches("/Rotate 90\n").count(), 1);
+        assert_eq!(file.matches("/Rotate 180\n").count(), 1);
+    }
+
+    #[test]
+    fn clone_layout() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add(
+                Path::new()
+                    .rect((100f64, 100f64, 50f64, 50f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            let mut clone = page.clone_layout();
+            pdf.add_page(page);
+            clone.add(
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            pdf.add_page(clone);
+        }));
+        assert!(file.contains("stream\n 100 100 50 50 re f\nendstream"));
+        assert!(file.contains("stream\n 100 100 50 50 re f 0 0 10 10 re f\nendstream"));
+    }
+
+    #[test]
+    fn clear() {
+        let draw = |page: &mut Page| {
+            page.add(
+                Path::new()
+                    .rect((100f64, 100f64, 50f64, 50f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+        };
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            draw(&mut page);
+            page.add_signature_field(SignatureField::new("Signature", 100));
+            page.clear();
+            draw(&mut page);
+            pdf.add_page(page);
+        }));
+        assert_eq!(
+            file.matches("stream\n 100 100 50 50 re f\nendstream")
+                .count(),
+            1
+        );
+        assert!(!file.contains("/FT /Sig"));
+        assert!(!file.contains("/Annots"));
+    }
+
+    #[test]
+    fn low_contrast_text() {
+        let mut pdf = PDF::new(Box::new(std::io::sink()));
+        let mut page = Page::new();
+        page.set_background(Color::DeviceGray(0.9));
+        page.add(Rc::new(
+            graphics::Text::new(graphics::Font::helvetica(), 10f64)
+                .move_to((10f64, 20f64))
+                .text("Light")
+                .fill(Color::DeviceGray(1f64)),
+        ));
+        page.add(Rc::new(
+            graphics::Text::new(graphics::Font::helvetica(), 10f64)
+                .move_to((10f64, 40f64))
+                .text("Dark")
+                .fill(Color::DeviceGray(0f64)),
+        ));
+        pdf.add_page(page);
+        let issues = pdf.check_contrast(4.5);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].page, 0);
+        assert_eq!(issues[0].position, Point::new(10f64, 20f64));
+        assert!(issues[0].ratio < 1.5);
+    }
+
+    #[test]
+    fn tagged() {
+        let file = text(&written(|pdf| {
+            pdf.set_tagged(true);
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/MarkInfo <<\n/Marked true\n>>"));
+    }
+
+    #[test]
+    fn named_resource() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.add_named_resource("Properties", "MC1", "<< /Custom true >>");
+            pdf.add_page(page);
+        }));
+        assert!(file.contains(
+            "/Resources <<\n/ProcSet [/PDF /Text]\n/Properties <<\n/MC1 << /Custom true >>\n>>"
+        ));
+    }
+
+    #[test]
+    fn catalog_entry() {
+        let file = text(&written(|pdf| {
+            pdf.set_catalog_entry("Lang", "(en-GB)");
+            pdf.set_catalog_entry("PageLayout", "/TwoColumnLeft");
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/Type /Catalog\n"));
+        assert!(file.contains("/Lang (en-GB)\n/PageLayout /TwoColumnLeft\n"));
+    }
+
+    #[test]
+    fn stamp_on_three_pages() {
+        let file = text(&written(|pdf| {
+            let stamp = pdf.define_stamp((0f64, 0f64, 10f64, 10f64), |ctx| {
+                ctx.render(
+                    Path::new()
+                        .rect((0f64, 0f64, 10f64, 10f64))
+                        .fill(Color::DeviceGray(0f64)),
+                );
+            });
+            for i in 0..3 {
+                let mut page = Page::new();
+                page.stamp(&stamp, Matrix::translate(i as f64 * 20f64, 0f64));
+                pdf.add_page(page);
+            }
+        }));
+        assert_eq!(file.matches("/Subtype /Form").count(), 1);
+        assert_eq!(file.matches(" Do").count(), 3);
+    }
+
+    #[test]
+    fn media_box_origin() {
+        let mut page = Page::new();
+        page.set_media_box(Rect::from_corners(100f64, 100f64, 712f64, 892f64));
+        let center = page.resolve(Position::at(graphics::Anchor::Center));
+        assert_eq!(center, Point::new(406f64, 496f64));
+        let width = Font::helvetica().text_width("Hi", 10f64);
+        page.add(Rc::new(
+            Text::new(Font::helvetica(), 10f64)
+                .move_to((0f64, center.y()))
+                .text("Hi")
+                .centered_in(page.media_box())
+                .fill(Color::DeviceGray(0f64)),
+        ));
+        let file = text(&written(|pdf| {
+            pdf.add_page(page);
+        }));
+        assert!(file.contains(&format!(" {} 496 Td (Hi) Tj", 406f64 - width / 2f64)));
+    }
+
+    #[test]
+    fn top_left_origin() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_top_left();
+            page.add(
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0f64)),
+            );
+            pdf.add_page(page);
+        }));
+        // (0, 0) is mapped to the top left of the page, y growing downward
+        assert!(file.contains("stream\n q 1 0 0 -1 0 792 cm 0 0 10 10 re f Q\nendstream"));
+    }
+
+    #[test]
+    fn default_font() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_default_font(Font::courier(), 14f64);
+            page.text_at((10f64, 20f64), "one");
+            page.text_at((10f64, 40f64), "two");
+            pdf.add_page(page);
+        }));
+        assert!(file.contains(
+            " BT /courier 14 Tf 10 20 Td (one) Tj ET BT /courier 14 Tf 10 40 Td (two) Tj ET"
+        ));
+        assert!(file.contains("/BaseFont /Courier\n"));
+    }
+
+    #[test]
+    fn stamp_clipped_to_bbox() {
+        let file = text(&written(|pdf| {
+            let stamp = pdf.define_stamp((0f64, 0f64, 10f64, 10f64), |ctx| {
+                ctx.render(
+                    Path::new()
+                        .rect((0f64, 0f64, 100f64, 100f64))
+                        .fill(Color::DeviceGray(0f64)),
+                );
+            });
+            stamp.set_matrix(Matrix::scale(2f64, 2f64));
+            let placed = stamp.place(Matrix::translate(5f64, 5f64));
+            assert_eq!(
+                placed.bounds(),
+                Some(Rect::from_corners(5f64, 5f64, 25f64, 25f64))
+            );
+            let mut page = Page::new();
+            page.add(placed);
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/BBox [0 0 10 10]\n"));
+        assert!(file.contains("/Matrix [2 0 0 2 0 0]\n"));
+        assert!(file.contains("stream\n q 1 0 0 1 5 5 cm /Im1 Do Q\nendstream"));
+    }
+
+    #[test]
+    #[should_panic(expected = "must have an area")]
+    fn stamp_without_area() {
+        written(|pdf| {
+            pdf.define_stamp((0f64, 0f64, 10f64, 0f64), |_| {});
+        });
+    }
+
+    #[test]
+    fn without_pages() {
+        let bytes = written(|_| {});
+        assert_eq!(PDF::verify_bytes(&bytes), Ok(()));
+        let file = text(&bytes);
+        assert!(file.contains("/Count 1\n/Kids [5 0 R]\n"));
+        assert!(file.contains("5 0 obj\n<<\n/Type /Page\n"));
+    }
+
+    #[test]
+    fn line_endings() {
+        let file = written(|pdf| {
+            pdf.set_metadata(Metadata::new().title("One\r\nTwo"));
+            let mut page = Page::new();
+            page.text_at((10f64, 10f64), "One\r\nTwo");
+            pdf.add_page(page);
+        });
+        assert!(!file.contains(&b'\r'));
+    }
+
+    #[test]
+    fn page_defaults() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_defaults(
+                2f64,
+                Color::DeviceRGB(1f64, 0f64, 0f64),
+                Color::DeviceGray(0.5f64),
+            );
+            page.add(
+                Path::new()
+                    .rect((0f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0.5f64)),
+            );
+            pdf.add_page(page);
+        }));
+        // The fill matching the default isn't set again
+        assert!(file
+            .contains("stream\n 2 w 0.5 scn  /DeviceRGB CS  1 0 0 SCN  0 0 10 10 re f\nendstream"));
+    }
+
+    #[test]
+    fn contents_array() {
+        let page = |mode| {
+            text(&written(|pdf| {
+                let mut page = Page::new();
+                page.contents_mode(mode);
+                pdf.add_page(page);
+            }))
+        };
+        assert!(page(ContentsMode::Array).contains("/Contents [4 0 R]\n"));
+        assert!(page(ContentsMode::Single).contains("/Contents 4 0 R\n"));
+    }
+
+    #[test]
+    fn debug_dump() {
+        let mut pdf = PDF::new(Box::new(std::io::sink()));
+        pdf.add_page(Page::new());
+        let dump = pdf.debug_dump();
+        assert!(dump.contains("3 0 obj\n<<\n  /Type /Catalog\n"));
+        assert!(dump.contains("2 0 obj\n<<\n  /Type /Pages\n"));
+        assert!(dump.contains("Page 1 (not yet written)\n  /MediaBox [0 0 612 792]\n"));
+    }
+
+    #[test]
+    fn print_on_open() {
+        let file = text(&written(|pdf| {
+            pdf.set_open_action(OpenAction::JavaScript("this.print();".to_string()));
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/OpenAction <<\n/S /JavaScript\n/JS (this.print\\(\\);)\n>>\n"));
+    }
+
+    #[test]
+    fn extend() {
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.extend((0..3).map(|i| {
+                Path::new()
+                    .rect((i as f64 * 20f64, 0f64, 10f64, 10f64))
+                    .fill(Color::DeviceGray(0f64))
+            }));
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("stream\n 0 0 10 10 re f 20 0 10 10 re f 40 0 10 10 re f\nendstream"));
+    }
+
+    #[test]
+    fn a4_page() {
+        assert_eq!(
+            PageSize::A4.dimensions(),
+            Rect::new(0f64, 0f64, 595f64, 842f64)
+        );
+        let file = text(&written(|pdf| {
+            let mut page = Page::new();
+            page.set_media_box(PageSize::A4);
+            pdf.add_page(page);
+        }));
+        assert_eq!(file.matches("/MediaBox [0 0 595 842]\n").count(), 1);
+    }
+
+    #[test]
+    fn content_bounds() {
+        let mut page = Page::new();
+        assert_eq!(page.content_bounds(), None);
+        page.add(
+            Path::new()
+                .rect((10f64, 20f64, 30f64, 40f64))
+                .fill(Color::DeviceGray(0f64)),
+        );
+        page.add(
+            Path::from((100f64, 5f64))
+                .line_to((120f64, 80f64))
+                .stroke(Color::DeviceGray(0f64)),
+        );
+        assert_eq!(
+            page.content_bounds(),
+            Some(Rect::from_corners(10f64, 5f64, 120f64, 80f64))
+        );
+    }
+
+    #[test]
+    fn flush() {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        let mut pdf = PDF::new(output(&buffer));
+        pdf.set_reproducible();
+        pdf.add_page(Page::new());
+        pdf.flush().unwrap();
+        let partial = buffer.borrow().clone();
+        assert!(partial.starts_with(b"%PDF-1.4\n"));
+        assert!(!text(&partial).contains(" obj"));
+        pdf.write().unwrap();
+        let file = buffer.borrow().clone();
+        assert_eq!(PDF::verify_bytes(&file), Ok(()));
+        let unflushed = written(|pdf| {
+            pdf.set_reproducible();
+            pdf.add_page(Page::new());
+        });
+        assert_eq!(file, unflushed);
+    }
+
+    #[test]
+    fn with_size() {
+        for mode in [ContentsMode::Single, ContentsMode::Array] {
+            let file = text(&written(|pdf| {
+                let mut page = Page::with_size(400f64, 300f64);
+                page.contents_mode(mode);
+                pdf.add_page(page);
+                pdf.add_page(Page::new());
+            }));
+            assert_eq!(file.matches("/MediaBox [0 0 400 300]\n").count(), 1);
+            assert_eq!(file.matches("/MediaBox [0 0 612 792]\n").count(), 1);
+        }
+    }
+
+    #[test]
+    fn crop_point() {
+        let mut page = Page::new();
+        assert_eq!(page.crop_point((10f64, 10f64)), Point::new(10f64, 10f64));
+        page.set_crop_box(Rect::new(36f64, 72f64, 540f64, 648f64));
+        assert_eq!(page.crop_point((10f64, 10f64)), Point::new(46f64, 82f64));
+        assert_eq!(
+            page.crop_rect((0f64, 0f64, 20f64, 30f64)),
+            Rect::new(36f64, 72f64, 20f64, 30f64)
+        );
+        let file = text(&written(|pdf| {
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/CropBox [36 72 576 720]\n"));
+    }
+
+    #[test]
+    fn link_to_page() {
+        let file = text(&written(|pdf| {
+            let first = pdf.add_page(Page::new());
+            pdf.add_page(Page::new());
+            let mut page = Page::new();
+            page.add_link(Link::page((10f64, 10f64, 100f64, 20f64), first));
+            pdf.add_page(page);
+        }));
+        assert!(file.contains("/Kids [5 0 R 7 0 R 9 0 R]\n"));
+        assert!(file.contains("/Dest [5 0 R /Fit]\n"));
+    }
+
+    #[test]
+    fn info() {
+        let file = text(&written(|pdf| {
+            pdf.set_metadata(Metadata::new().title("Report (draft)").author("A. Writer"));
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/Info 6 0 R\n"));
+        assert!(file.contains(&format!(
+            "6 0 obj\n<<\n/Producer (simple_pdf {})\n/Title (Report \\(draft\\))\n/Author (A. Writer)\n>>",
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+
+    #[test]
+    fn insert_page() {
+        let file = text(&written(|pdf| {
+            let later = pdf.add_page(Page::with_size(200f64, 200f64));
+            let mut page = Page::with_size(100f64, 100f64);
+            page.add_link(Link::page((0f64, 0f64, 10f64, 10f64), later));
+            pdf.insert_page(0, page);
+        }));
+        assert!(file.contains("/Kids [5 0 R 7 0 R]\n"));
+        assert!(file.contains("5 0 obj\n<<\n/Type /Page\n"));
+        assert!(file.contains("/MediaBox [0 0 100 100]\n/Annots [8 0 R]\n"));
+        // The handle still shows the page added first, now the second
+        assert!(file.contains("/P 5 0 R\n/Dest [7 0 R /Fit]\n"));
+        assert!(file.contains("/Contents 6 0 R\n/MediaBox [0 0 200 200]\n"));
+    }
 }
@@ -0,0 +1,488 @@
+//! Compression for the FlateDecode filter
+//!
+//! A small zlib encoder, so the crate doesn't need a dependency for it.
+//! Matches are found with hash chains, and coded with the fixed Huffman
+//! codes, which is somewhat larger than zlib's output, but still a large
+//! saving for content streams and image rows.
+
+/// The compression level used unless another is given, from 0 (no
+/// compression) to 9 (smallest output)
+pub const DEFAULT_LEVEL: u32 = 6;
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: usize = 15;
+
+/// The first length of each length code (257..=285), and its extra bits
+const LENGTHS: [(usize, u32); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// The first distance of each distance code, and its extra bits
+const DISTANCES: [(usize, u32); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+/// Writes bits least significant first, as deflate requires
+struct Bits {
+    out: Vec<u8>,
+    acc: u32,
+    len: u32,
+}
+
+impl Bits {
+    fn put(&mut self, value: u32, bits: u32) {
+        self.acc |= value << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.len -= 8;
+        }
+    }
+    /// Writes a Huffman code, which is stored most significant bit first
+    fn code(&mut self, code: u32, bits: u32) {
+        let reversed = code.reverse_bits() >> (32 - bits);
+        self.put(reversed, bits);
+    }
+    fn flush(&mut self) {
+        if self.len > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.acc = 0;
+        self.len = 0;
+    }
+    /// A literal or length code from the fixed Huffman table
+    fn literal(&mut self, symbol: usize) {
+        let s = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + s, 8),
+            144..=255 => self.code(0x190 + s - 144, 9),
+            256..=279 => self.code(s - 256, 7),
+            _ => self.code(0xC0 + s - 280, 8),
+        }
+    }
+    fn length(&mut self, length: usize) {
+        let i = LENGTHS.iter().rposition(|(l, _)| *l <= length).unwrap();
+        let (base, extra) = LENGTHS[i];
+        self.literal(257 + i);
+        self.put((length - base) as u32, extra);
+    }
+    fn distance(&mut self, distance: usize) {
+        let i = DISTANCES.iter().rposition(|(d, _)| *d <= distance).unwrap();
+        let (base, extra) = DISTANCES[i];
+        self.code(i as u32, 5);
+        self.put((distance - base) as u32, extra);
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v =
+        (usize::from(data[i]) << 16) | (usize::from(data[i + 1]) << 8) | usize::from(data[i + 2]);
+    (v.wrapping_mul(2654435761) >> 8) & ((1 << HASH_BITS) - 1)
+}
+
+/// Compresses data in the zlib format, for a FlateDecode stream
+///
+/// - level: from 0 (no compression) to 9 (smallest output), higher
+///   levels search further for repeated data
+///
+/// # Panics
+///
+/// panics if the level is above 9
+pub fn compress(data: &[u8], level: u32) -> Vec<u8> {
+    assert!(level <= 9, "The compression level must be from 0 to 9");
+    // The header check bits make the first two bytes a multiple of 31
+    let flevel: u16 = match level {
+        0..=1 => 0,
+        2..=5 => 1,
+        6 => 2,
+        _ => 3,
+    };
+    let header = 0x7800 | (flevel << 6);
+    let header = header + 31 - header % 31;
+    let mut bits = Bits {
+        out: header.to_be_bytes().to_vec(),
+        acc: 0,
+        len: 0,
+    };
+    if level == 0 {
+        stored(&mut bits, data);
+    } else {
+        fixed(&mut bits, data, level);
+    }
+    bits.out.extend(adler32(data).to_be_bytes());
+    bits.out
+}
+
+/// Writes the data in stored blocks, without compression
+fn stored(bits: &mut Bits, data: &[u8]) {
+    let mut chunks = data.chunks(65535).peekable();
+    if chunks.peek().is_none() {
+        bits.put(1, 3);
+        bits.flush();
+        bits.out.extend([0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        bits.put(u32::from(last), 3);
+        bits.flush();
+        let len = chunk.len() as u16;
+        bits.out.extend(len.to_le_bytes());
+        bits.out.extend((!len).to_le_bytes());
+        bits.out.extend_from_slice(chunk);
+    }
+}
+
+/// Writes the data in a single block, using the fixed Huffman codes
+fn fixed(bits: &mut Bits, data: &[u8], level: u32) {
+    // How many earlier positions are tried for each match
+    let chain = 1usize << (level + 1);
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let insert = |head: &mut Vec<usize>, prev: &mut Vec<usize>, i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            prev[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+    bits.put(0b011, 3);
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best, mut best_dist) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let max = MAX_MATCH.min(data.len() - i);
+            let mut candidate = head[hash(data, i)];
+            let mut tries = chain;
+            while candidate != usize::MAX && i - candidate <= WINDOW && tries > 0 {
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + max])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best {
+                    best = len;
+                    best_dist = i - candidate;
+                    if len == max {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                tries -= 1;
+            }
+        }
+        if best >= MIN_MATCH {
+            bits.length(best);
+            bits.distance(best_dist);
+            for j in i..i + best {
+                insert(&mut head, &mut prev, j);
+            }
+            i += best;
+        } else {
+            bits.literal(usize::from(data[i]));
+            insert(&mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+    bits.literal(256);
+    bits.flush();
+}
+
+/// The PNG predictor parameters of a stream (`/DecodeParms`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Predictor {
+    /// The color components per sample
+    pub colors: usize,
+    /// The bits per color component
+    pub bits: usize,
+    /// The samples per row
+    pub columns: usize,
+}
+
+impl Predictor {
+    /// Bytes per complete sample, at least one
+    fn bpp(&self) -> usize {
+        (self.colors * self.bits).div_ceil(8)
+    }
+    fn row_len(&self) -> usize {
+        (self.colors * self.bits * self.columns).div_ceil(8)
+    }
+    /// Filters each row with the PNG filter that is likely to compress
+    /// best, as for `/Predictor 15`
+    ///
+    /// Each row gets a byte giving its filter, followed by the filtered
+    /// bytes. A short last row is filtered as it is.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let (bpp, row_len) = (self.bpp(), self.row_len().max(1));
+        let mut out = Vec::with_capacity(data.len() + data.len() / row_len + 1);
+        let empty = vec![0u8; row_len];
+        let mut above: &[u8] = &empty;
+        for row in data.chunks(row_len) {
+            let filtered: Vec<Vec<u8>> = (0u8..5)
+                .map(|filter| {
+                    (0..row.len())
+                        .map(|i| {
+                            let a = if i >= bpp { row[i - bpp] } else { 0 };
+                            let b = above[i];
+                            let c = if i >= bpp { above[i - bpp] } else { 0 };
+                            let predicted = match filter {
+                                0 => 0,
+                                1 => a,
+                                2 => b,
+                                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                                _ => paeth(a, b, c),
+                            };
+                            row[i].wrapping_sub(predicted)
+                        })
+                        .collect()
+                })
+                .collect();
+            // The usual heuristic: the smallest sum of the bytes, as
+            // signed values
+            let (filter, bytes) = filtered
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, f)| {
+                    f.iter()
+                        .map(|b| (*b as i8).unsigned_abs() as usize)
+                        .sum::<usize>()
+                })
+                .unwrap();
+            out.push(filter as u8);
+            out.extend_from_slice(bytes);
+            above = row;
+        }
+        out
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads bits least significant first
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn bit(&mut self) -> usize {
+            let bit = (self.data[self.pos / 8] >> (self.pos % 8)) & 1;
+            self.pos += 1;
+            usize::from(bit)
+        }
+        fn bits(&mut self, bits: u32) -> usize {
+            (0..bits).fold(0, |v, i| v | (self.bit() << i))
+        }
+        /// A Huffman code, which is stored most significant bit first
+        fn code(&mut self, bits: u32) -> usize {
+            (0..bits).fold(0, |v, _| (v << 1) | self.bit())
+        }
+        /// A literal or length code from the fixed Huffman table
+        fn literal(&mut self) -> usize {
+            let code = self.code(7);
+            if code <= 23 {
+                return 256 + code;
+            }
+            let code = (code << 1) | self.bit();
+            match code {
+                0x30..=0xBF => code - 0x30,
+                0xC0..=0xC7 => 280 + code - 0xC0,
+                _ => 144 + ((code << 1) | self.bit()) - 0x190,
+            }
+        }
+    }
+
+    /// Decompresses the stored and fixed Huffman blocks compress writes
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        assert_eq!(u16::from_be_bytes([data[0], data[1]]) % 31, 0);
+        let mut r = Reader { data, pos: 16 };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let last = r.bits(1) == 1;
+            match r.bits(2) {
+                0 => {
+                    let start = r.pos.div_ceil(8);
+                    let len = usize::from(u16::from_le_bytes([data[start], data[start + 1]]));
+                    out.extend_from_slice(&data[start + 4..start + 4 + len]);
+                    r.pos = (start + 4 + len) * 8;
+                }
+                1 => loop {
+                    let symbol = r.literal();
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    } else if symbol == 256 {
+                        break;
+                    }
+                    let (base, extra) = LENGTHS[symbol - 257];
+                    let length = base + r.bits(extra);
+                    let (base, extra) = DISTANCES[r.code(5)];
+                    let start = out.len() - (base + r.bits(extra));
+                    for i in start..start + length {
+                        out.push(out[i]);
+                    }
+                },
+                kind => panic!("Unexpected block type {}", kind),
+            }
+            if last {
+                break;
+            }
+        }
+        let end = r.pos.div_ceil(8);
+        assert_eq!(data[end..], adler32(&out).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn compression_levels() {
+        let data: Vec<u8> = (0..200)
+            .flat_map(|i| format!("{} {} 10 10 re f ", i % 17 * 3, i % 29 * 5).into_bytes())
+            .collect();
+        let fast = compress(&data, 1);
+        let small = compress(&data, 9);
+        assert_eq!(decompress(&fast), data);
+        assert_eq!(decompress(&small), data);
+        assert_eq!(decompress(&compress(&data, 0)), data);
+        assert!(small.len() <= fast.len());
+        assert!(fast.len() < data.len());
+    }
+
+    /// Undoes Predictor::encode, as a reader does
+    fn decode(p: &Predictor, data: &[u8]) -> Vec<u8> {
+        let (bpp, row_len) = (p.bpp(), p.row_len());
+        let mut out: Vec<u8> = vec![];
+        for row in data.chunks(row_len + 1) {
+            let start = out.len();
+            for i in 0..row.len() - 1 {
+                let a = if i >= bpp { out[start + i - bpp] } else { 0 };
+                let b = if start > 0 {
+                    out[start - row_len + i]
+                } else {
+                    0
+                };
+                let c = if start > 0 && i >= bpp {
+                    out[start - row_len + i - bpp]
+                } else {
+                    0
+                };
+                let predicted = match row[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                out.push(row[i + 1].wrapping_add(predicted));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn predictor_round_trip() {
+        let predictor = Predictor {
+            colors: 3,
+            bits: 8,
+            columns: 4,
+        };
+        let samples: Vec<u8> = (0..48u32).map(|i| (i * 37 % 251) as u8).collect();
+        let encoded = predictor.encode(&samples);
+        // A filter byte for each of the four rows
+        assert_eq!(encoded.len(), samples.len() + 4);
+        assert_eq!(decode(&predictor, &encoded), samples);
+    }
+}
@@ -0,0 +1,57 @@
+//! The MD5 digest, which the file identifier is made from
+//!
+//! A small implementation, so the crate doesn't need a dependency for it.
+//! MD5 isn't collision resistant, and is only used where the PDF format
+//! asks for it, not for security.
+
+/// The shift amounts of each round
+const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// The 16 byte MD5 digest of the data
+pub fn digest(data: &[u8]) -> [u8; 16] {
+    // The constants are the integer part of abs(sin(i)) * 2^32
+    let k: Vec<u32> = (1..=64)
+        .map(|i| ((i as f64).sin().abs() * 4294967296f64) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    // The data is padded with a 1 bit, zeros, and its length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+    for block in message.chunks(64) {
+        let words: Vec<u32> = block
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let shift = SHIFTS[(i / 16) * 4 + i % 4];
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(k[i])
+                .wrapping_add(words[g])
+                .rotate_left(shift);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    let mut out = [0u8; 16];
+    for (chunk, s) in out.chunks_mut(4).zip(state.iter()) {
+        chunk.copy_from_slice(&s.to_le_bytes());
+    }
+    out
+}
@@ -1,19 +1,30 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::fmt::Debug;
 use std::io::{self, Write};
 use std::rc::Rc;
 
+pub mod flate;
+pub(crate) mod md5;
 pub mod types;
-pub use types::{Dict, Name, PDFData};
+pub use types::{Array, Dict, Name, PDFData, PDFString, Raw, Stream};
 
 pub struct Output {
     output: Box<dyn Write>,
     pos: usize,
+    /// The most bytes written, if limited
+    limit: Option<usize>,
+    /// A copy of the bytes written, if kept for the file identifier
+    copy: Option<Vec<u8>>,
 }
 
 impl Output {
     pub fn new(output: Box<dyn Write>) -> Self {
-        Self { output, pos: 0 }
+        Self {
+            output,
+            pos: 0,
+            limit: None,
+            copy: None,
+        }
     }
     pub fn get_pos(&self) -> usize {
         self.pos
@@ -22,9 +33,20 @@ impl Output {
 
 impl Write for Output {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(limit) = self.limit {
+            if self.pos + buf.len() > limit {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The file is larger than the limit of {} bytes", limit),
+                ));
+            }
+        }
         let tmp = self.output.write(buf);
         if let Ok(num) = tmp {
             self.pos += num;
+            if let Some(copy) = &mut self.copy {
+                copy.extend_from_slice(&buf[..num]);
+            }
         }
         tmp
     }
@@ -33,12 +55,31 @@ impl Write for Output {
     }
 }
 
-pub struct CRT {
+/// A writer into a shared buffer, so the bytes can be read after the
+/// writer is boxed, see PDFWrite::dump
+pub(crate) struct Buffer(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Crt {
     entries: Vec<(usize, usize, usize, bool)>,
     size: usize,
 }
 
-impl CRT {
+impl Default for Crt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Crt {
     pub fn new() -> Self {
         Self {
             entries: vec![(0, 0, 65535, true)],
@@ -51,41 +92,39 @@ impl CRT {
             self.size = num;
         }
     }
+    /// The number of entries, one more than the highest object number
     pub fn get_size(&self) -> usize {
-        self.size
+        self.size + 1
     }
     pub fn write(mut self, o: &mut dyn Write) -> io::Result<()> {
-        write!(o, "xref\n")?;
+        writeln!(o, "xref")?;
         self.entries.sort_by_key(|(_o, n, _g, _f)| *n);
-        // All numbers will be used by the program
-        // (And it's required by the spec)
-        Self::write_part(&self.entries, 0, o)
-        // let mut tmp = vec![];
-        // let mut iter = self.entries.into_iter();
-        // let mut last_num = 0;
-        // let mut start_num = 0;
-        // while let Some((offset, num, gen, free)) = iter.next() {
-        //     if last_num + 1 != num && (num != 0 && last_num != 0) {
-        //         // write tmp
-        //         Self::write_part(&tmp, start_num, o)?;
-        //         tmp = vec![];
-        //         start_num = num;
-        //     }
-        //     tmp.push((offset, num, gen, free));
-        //     last_num = num;
-        // }
-        // Self::write_part(&tmp, start_num, o)
+        // Numbers that were skipped or reserved split the table
+        // into subsections of consecutive numbers
+        let mut tmp = vec![];
+        let mut start_num = 0;
+        for (offset, num, gen, free) in self.entries {
+            if !tmp.is_empty() && start_num + tmp.len() != num {
+                Self::write_part(&tmp, start_num, o)?;
+                tmp = vec![];
+            }
+            if tmp.is_empty() {
+                start_num = num;
+            }
+            tmp.push((offset, num, gen, free));
+        }
+        Self::write_part(&tmp, start_num, o)
     }
     fn write_part(
         entries: &Vec<(usize, usize, usize, bool)>,
         start_num: usize,
         o: &mut dyn Write,
     ) -> io::Result<()> {
-        write!(o, "{} {}\n", start_num, entries.len())?;
+        writeln!(o, "{} {}", start_num, entries.len())?;
         for (offset, _num, gen, free) in entries {
-            write!(
+            writeln!(
                 o,
-                "{:010} {:05} {} \n",
+                "{:010} {:05} {} ",
                 offset,
                 gen,
                 if *free { 'f' } else { 'n' }
@@ -100,15 +139,51 @@ pub enum ObjError {
     DirectObject,
 }
 pub trait Object: PDFData + Debug {
-    fn write_obj(&self, crt: &mut CRT, out: &mut Output) -> io::Result<()>;
+    fn write_obj(&self, crt: &mut Crt, out: &mut Output) -> io::Result<()>;
     fn assign_num(&self, num: usize) -> Result<(), ObjError>;
     fn is_indirect(&self) -> bool;
+    /// Adds the references in the data of the object, see
+    /// PDFData::references
+    fn data_references(&self, _refs: &mut Vec<*const ()>) {}
+    /// Writes the object inline wherever it is referenced, instead of
+    /// as a numbered object
+    ///
+    /// Returns false if the object can't be written inline
+    fn inline(&self) -> bool {
+        false
+    }
+    /// Forgets the number the object was given, so it can be added to
+    /// another document
+    fn release(&self) {}
+    /// The objects the data depends on, which are added along with the
+    /// object, see PDFData::dependent_objects
+    fn data_dependents(&self) -> Vec<Rc<dyn Object>> {
+        vec![]
+    }
+    /// Writes the data of the object without its number, so objects
+    /// can be compared by content before they are numbered
+    fn write_data(&self, _o: &mut dyn Write) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The object has no data to write",
+        ))
+    }
+}
+/// The bytes as upper case hexadecimal digits
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+/// The address of an object, to compare it with PDFData::references
+fn address(obj: &Rc<dyn Object>) -> *const () {
+    Rc::as_ptr(obj) as *const ()
 }
 pub enum ObjRef<T: PDFData> {
     Indirect {
         num: Cell<Option<usize>>,
         gen: usize,
         data: Rc<T>,
+        /// Whether the data is written wherever the object is referenced
+        inline: Cell<bool>,
     },
     Direct {
         data: Rc<T>,
@@ -120,7 +195,8 @@ impl<T: PDFData> ObjRef<T> {
         Rc::new(Self::Indirect {
             num: Cell::new(None),
             gen,
-            data: data,
+            data,
+            inline: Cell::new(false),
         })
     }
 }
@@ -128,8 +204,8 @@ impl<T: PDFData> std::ops::Deref for ObjRef<T> {
     type Target = Rc<T>;
     fn deref(&self) -> &Self::Target {
         match self {
-            Self::Direct { data } => &data,
-            Self::Indirect { data, .. } => &data,
+            Self::Direct { data } => data,
+            Self::Indirect { data, .. } => data,
         }
     }
 }
@@ -137,20 +213,28 @@ impl<T: PDFData> PDFData for ObjRef<T> {
     fn write(&self, o: &mut dyn Write) -> io::Result<()> {
         match self {
             Self::Direct { data } => data.write(o),
+            Self::Indirect { data, inline, .. } if inline.get() => data.write(o),
             Self::Indirect { num, gen, .. } => {
                 write!(o, "{} {} R", num.get().expect("No number assigned"), gen)
             }
         }
     }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        match self {
+            Self::Direct { data } => data.references(refs),
+            Self::Indirect { .. } => refs.push(self as *const Self as *const ()),
+        }
+    }
 }
 impl<T: PDFData + Debug> Object for ObjRef<T> {
-    fn write_obj(&self, crt: &mut CRT, out: &mut Output) -> io::Result<()> {
+    fn write_obj(&self, crt: &mut Crt, out: &mut Output) -> io::Result<()> {
         match self {
-            Self::Indirect { num, gen, data } => {
+            Self::Indirect { inline, .. } if inline.get() => Ok(()),
+            Self::Indirect { num, gen, data, .. } => {
                 crt.add_entry(out.get_pos(), num.get().expect("No num"), *gen, false);
-                write!(out, "{} {} obj\n", num.get().unwrap(), gen)?;
+                writeln!(out, "{} {} obj", num.get().unwrap(), gen)?;
                 data.write(out)?;
-                write!(out, "endobj\n")
+                writeln!(out, "endobj")
             }
             Self::Direct { .. } => {
                 panic!("Not an indirect object");
@@ -161,7 +245,7 @@ impl<T: PDFData + Debug> Object for ObjRef<T> {
         match self {
             Self::Direct { .. } => Err(ObjError::DirectObject),
             Self::Indirect { num, .. } => {
-                if let Some(_) = num.get() {
+                if num.get().is_some() {
                     Err(ObjError::AlreadyAssigned)
                 } else {
                     num.set(Some(new_num));
@@ -176,12 +260,49 @@ impl<T: PDFData + Debug> Object for ObjRef<T> {
             Self::Indirect { .. } => true,
         }
     }
+    fn data_references(&self, refs: &mut Vec<*const ()>) {
+        match self {
+            Self::Direct { data } => data.references(refs),
+            Self::Indirect { data, .. } => data.references(refs),
+        }
+    }
+    fn inline(&self) -> bool {
+        match self {
+            Self::Indirect { data, inline, .. } if !data.is_stream() => {
+                inline.set(true);
+                true
+            }
+            _ => false,
+        }
+    }
+    fn data_dependents(&self) -> Vec<Rc<dyn Object>> {
+        match self {
+            Self::Direct { data } => data.dependent_objects(),
+            Self::Indirect { data, .. } => data.dependent_objects(),
+        }
+    }
+    fn write_data(&self, o: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::Direct { data } => data.write(o),
+            Self::Indirect { data, .. } => data.write(o),
+        }
+    }
+    fn release(&self) {
+        if let Self::Indirect {
+            num, inline, data, ..
+        } = self
+        {
+            num.set(None);
+            inline.set(false);
+            data.forget();
+        }
+    }
 }
 impl<T: PDFData + Debug> Debug for ObjRef<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::Direct { data } => Debug::fmt(data, f),
-            Self::Indirect { num, gen, data } => {
+            Self::Indirect { num, gen, data, .. } => {
                 if let Some(d) = num.get() {
                     write!(f, "{}", d)?;
                 } else {
@@ -199,6 +320,16 @@ pub struct PDFWrite {
     cur_num: usize,
     trailer: Trailer,
     output: Output,
+    binary_marker: Option<[u8; 4]>,
+    reserved: Vec<usize>,
+    /// Whether objects referenced once are written inline
+    minimize: bool,
+    /// Objects that must stay indirect, even if referenced once
+    keep: Vec<Rc<dyn Object>>,
+    /// Whether the header has been written by flush
+    header_written: bool,
+    /// The most objects written, if limited
+    max_objects: Option<usize>,
 }
 
 impl PDFWrite {
@@ -209,6 +340,81 @@ impl PDFWrite {
             cur_num: 1,
             trailer: Trailer::new(),
             output: Output::new(output),
+            binary_marker: Some([0xE2, 0xE3, 0xCF, 0xD3]),
+            reserved: vec![],
+            minimize: false,
+            keep: vec![],
+            header_written: false,
+            max_objects: None,
+        }
+    }
+    /// Sets the number given to the next object added
+    ///
+    /// # Panics
+    ///
+    /// panics if the number is lower than a number already used
+    pub fn set_next_number(&mut self, num: usize) {
+        assert!(
+            num >= self.cur_num,
+            "Object number {} may already be in use",
+            num
+        );
+        self.cur_num = num;
+    }
+    /// Prevents the numbers from being given to any object
+    ///
+    /// # Panics
+    ///
+    /// panics if a number is lower than the number of the next object
+    /// added, and may already be in use
+    pub fn reserve_numbers(&mut self, nums: impl IntoIterator<Item = usize>) {
+        for num in nums {
+            assert!(
+                num >= self.cur_num || self.reserved.contains(&num),
+                "Object number {} may already be in use",
+                num
+            );
+            self.reserved.push(num);
+        }
+    }
+    /// Sets the bytes of the comment after the header, which marks the
+    /// file as binary, or omits the comment if None
+    ///
+    /// # Panics
+    ///
+    /// panics if the header has already been written by flush
+    pub fn set_binary_marker(&mut self, marker: Option<[u8; 4]>) {
+        assert!(!self.header_written, "The header has already been written");
+        self.binary_marker = marker;
+    }
+    /// Makes write return an error, instead of writing more than the
+    /// number of objects
+    ///
+    /// Only numbered objects are counted, not the ones written inline
+    /// by minimize_objects.
+    pub fn set_max_objects(&mut self, max: usize) {
+        self.max_objects = Some(max);
+    }
+    /// Makes writing return an error, instead of writing more than the
+    /// number of bytes
+    ///
+    /// The output may already contain part of the file.
+    pub fn set_max_size(&mut self, max: usize) {
+        self.output.limit = Some(max);
+    }
+    /// Sets the file identifier (`/ID`) of the trailer, instead of
+    /// computing it when the file is written
+    pub fn set_id(&mut self, id: [u8; 16]) {
+        let hex = hex(&id);
+        self.trailer.id = Some(Rc::new([hex.clone(), hex]));
+    }
+    /// Computes the file identifier from the content of the file alone,
+    /// so writing the same document twice gives the same bytes
+    ///
+    /// By default the current time is part of the identifier.
+    pub fn set_reproducible(&mut self) {
+        if self.output.copy.is_none() {
+            self.output.copy = Some(vec![]);
         }
     }
     /// Add an object the final PDF file
@@ -220,6 +426,9 @@ impl PDFWrite {
     /// panics if the object has already been added to
     /// the pdf file
     pub fn add_object(&mut self, o: Rc<dyn Object>) -> Rc<dyn Object> {
+        while self.reserved.contains(&self.cur_num) {
+            self.cur_num += 1;
+        }
         match o.assign_num(self.cur_num) {
             Ok(()) => {
                 self.objects.push(o.clone());
@@ -228,41 +437,240 @@ impl PDFWrite {
             Err(ObjError::AlreadyAssigned) => {}
             Err(ObjError::DirectObject) => {}
         }
-        for obj in o.dependent_objects() {
+        for obj in o.data_dependents() {
             self.add_object(obj);
         }
         o
     }
+    /// Creates an indirect object, and adds it to the final PDF file
+    ///
+    /// Returns the object, which is written as a reference wherever
+    /// it is used
+    pub fn allocate<T: 'static + PDFData + Debug>(&mut self, data: Rc<T>) -> Rc<ObjRef<T>> {
+        let o = ObjRef::new(0, data);
+        self.add_object(o.clone());
+        o
+    }
     /// Add an object the final PDF file, and sets
     /// the root document object to point at it.
     ///
     /// # Panics
     ///
-    /// panics if the object has already been added to
-    /// the pdf file
-    pub fn create_root<T: 'static + PDFData>(&mut self, root: Rc<T>) -> Rc<ObjRef<T>> {
-        if let Some(_) = self.trailer.root {
+    /// panics if a root has already been set
+    pub fn create_root<T: 'static + PDFData + Debug>(&mut self, root: Rc<T>) -> Rc<ObjRef<T>> {
+        if self.trailer.root.is_some() {
             panic!("An object is already root");
         }
         let o = ObjRef::new(0, root);
-        self.add_object(o.clone());
-        self.trailer.root = Some(o.clone());
+        self.set_root(o.clone());
         o
     }
-    pub fn write(mut self) -> io::Result<()> {
+    /// Sets the root document object, replacing the current root
+    ///
+    /// The object is added to the final PDF file, unless it has
+    /// already been added
+    ///
+    /// # Panics
+    ///
+    /// panics if the object is direct
+    pub fn set_root<T: 'static + PDFData + Debug>(&mut self, root: Rc<ObjRef<T>>) {
+        assert!(root.is_indirect(), "The root must be an indirect object");
+        self.add_object(root.clone());
+        self.trailer.root = Some(root);
+    }
+    /// Sets the Info dictionary of the trailer, which is added as an
+    /// indirect object, unless omit_info has been called
+    pub fn set_info(&mut self, info: Rc<Dict>) {
+        if self.trailer.omit_info {
+            return;
+        }
+        let o = ObjRef::new(0, info);
+        self.add_object(o.clone());
+        self.trailer.info = Some(o);
+    }
+    /// Leaves the Info dictionary out of the trailer
+    ///
+    /// The file identifier is computed as if set_reproducible was
+    /// called, so the time isn't part of the file either.
+    pub fn omit_info(&mut self) {
+        self.trailer.omit_info = true;
+        self.set_reproducible();
+    }
+    /// A readable listing of the objects added so far, with nested
+    /// dictionaries indented, and the data of streams left out
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        for obj in self.objects.iter() {
+            let buffer = Rc::new(RefCell::new(vec![]));
+            let mut out = Output::new(Box::new(Buffer(buffer.clone())));
+            if let Err(e) = obj.write_obj(&mut Crt::new(), &mut out) {
+                dump.push_str(&format!("{:?}: {}\n", obj, e));
+                continue;
+            }
+            let bytes = buffer.borrow();
+            let text = String::from_utf8_lossy(&bytes);
+            let (text, stream) = match text.find("\nstream\n") {
+                Some(i) => {
+                    let end = text.rfind("\nendstream").unwrap_or(text.len());
+                    (text[..i].to_string(), Some(end.saturating_sub(i + 8)))
+                }
+                None => (text.trim_end().trim_end_matches("endobj").to_string(), None),
+            };
+            let mut depth = 0;
+            for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                if line.starts_with(">>") {
+                    depth -= 1;
+                }
+                dump.push_str(&"  ".repeat(depth.max(0) as usize));
+                dump.push_str(line);
+                dump.push('\n');
+                depth += line.matches("<<").count() as isize;
+                depth -= line.matches(">>").count() as isize;
+                if line.starts_with(">>") {
+                    depth += 1;
+                }
+            }
+            if let Some(len) = stream {
+                dump.push_str(&format!("  stream of {} bytes\n", len));
+            }
+        }
+        dump
+    }
+    /// Writes objects that are referenced once inline, where they are
+    /// referenced, instead of as numbered objects
+    ///
+    /// Streams, the root, the Info dictionary, and objects passed to
+    /// keep_indirect stay indirect. References in Raw data aren't
+    /// counted, so objects referenced that way should be kept.
+    pub fn minimize_objects(&mut self) {
+        self.minimize = true;
+    }
+    /// Keeps the object indirect, see minimize_objects
+    pub fn keep_indirect(&mut self, obj: Rc<dyn Object>) {
+        self.keep.push(obj);
+    }
+    /// Marks the objects referenced once to be written inline
+    ///
+    /// Objects in a cycle are referenced at least twice, once from
+    /// outside the cycle, unless nothing outside references them, so
+    /// inlining never writes an object inside itself
+    ///
+    /// Returns the number of objects written inline
+    fn inline_objects(&self) -> usize {
+        let mut refs = vec![];
+        for obj in self.objects.iter() {
+            obj.data_references(&mut refs);
+        }
+        let mut counts = std::collections::HashMap::new();
+        for r in refs {
+            *counts.entry(r).or_insert(0usize) += 1;
+        }
+        let mut kept: Vec<_> = self.keep.iter().map(address).collect();
+        if let Some(root) = &self.trailer.root {
+            root.references(&mut kept);
+        }
+        if let Some(info) = &self.trailer.info {
+            info.references(&mut kept);
+        }
+        let mut inlined = 0;
+        for obj in self.objects.iter() {
+            let addr = address(obj);
+            if counts.get(&addr) == Some(&1) && !kept.contains(&addr) && obj.inline() {
+                inlined += 1;
+            }
+        }
+        inlined
+    }
+    fn write_header(&mut self) -> io::Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+        self.header_written = true;
         // let mut output = Output::new(o);
-        write!(self.output, "%PDF-1.4\n%����\n")?;
-        let mut crt = CRT::new();
+        writeln!(self.output, "%PDF-1.4")?;
+        if let Some(marker) = self.binary_marker {
+            self.output.write_all(b"%")?;
+            self.output.write_all(&marker)?;
+            writeln!(self.output)?;
+        }
+        Ok(())
+    }
+    /// Writes the header, if it hasn't been written, and flushes the
+    /// output
+    ///
+    /// Objects can change until the file is written, so they are only
+    /// written by write.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        self.output.flush()
+    }
+    /// The file identifier, an MD5 digest of the current time, the
+    /// size of the file so far, and the Info dictionary, or of the file
+    /// so far if set_reproducible was called
+    ///
+    /// The file is new, so both parts of the identifier are the same.
+    fn file_id(&self) -> io::Result<[String; 2]> {
+        if let Some(copy) = &self.output.copy {
+            let hex = hex(&md5::digest(copy));
+            return Ok([hex.clone(), hex]);
+        }
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let mut data = time.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.output.get_pos().to_le_bytes());
+        data.extend_from_slice(&self.objects.len().to_le_bytes());
+        if let Some(info) = &self.trailer.info {
+            let dict: &Dict = info;
+            dict.write(&mut data)?;
+        }
+        let hex = hex(&md5::digest(&data));
+        Ok([hex.clone(), hex])
+    }
+    pub fn write(mut self) -> io::Result<()> {
+        let inlined = if self.minimize {
+            self.inline_objects()
+        } else {
+            0
+        };
+        if let Some(max) = self.max_objects {
+            let count = self.objects.len() - inlined;
+            if count > max {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "The file has {} objects, more than the limit of {}",
+                        count, max
+                    ),
+                ));
+            }
+        }
+        self.write_header()?;
+        let mut crt = Crt::new();
         for obj in self.objects.iter() {
             obj.write_obj(&mut crt, &mut self.output)?;
         }
         self.trailer.size = Some(crt.get_size());
+        if self.trailer.id.is_none() {
+            self.trailer.id = Some(Rc::new(self.file_id()?));
+        }
         let startxref = self.output.get_pos();
         crt.write(&mut self.output)?;
         self.trailer.write(&mut self.output)?;
         write!(self.output, "startxref\n{}\n%%EOF", startxref)
     }
 }
+/// Objects shared between documents, such as the fonts of a
+/// ResourcePool, are numbered again by the next document they are
+/// added to
+impl Drop for PDFWrite {
+    fn drop(&mut self) {
+        for obj in self.objects.iter() {
+            obj.release();
+        }
+    }
+}
 #[derive(Debug)]
 struct Trailer {
     // /Size 8
@@ -270,7 +678,9 @@ struct Trailer {
     //   /ID [<8=1b14aafa313db63dbd6f981e49f94f4> <81b14aafa313db63dbd6f981e49f94f4>]
     size: Option<usize>,
     root: Option<Rc<dyn PDFData>>,
-    info: Option<Rc<dyn PDFData>>,
+    info: Option<Rc<ObjRef<Dict>>>,
+    /// Whether the Info dictionary is left out, even if it is set
+    omit_info: bool,
     id: Option<Rc<[String; 2]>>,
 }
 
@@ -280,6 +690,7 @@ impl Trailer {
             size: None,
             root: None,
             info: None,
+            omit_info: false,
             id: None,
         }
     }
@@ -287,12 +698,12 @@ impl Trailer {
 
 impl PDFData for Trailer {
     fn write(&self, o: &mut dyn Write) -> io::Result<()> {
-        write!(o, "trailer\n")?;
+        writeln!(o, "trailer")?;
         let dict = Dict::from_vec(vec![
             ("Size", Rc::new(self.size.expect("Size not set"))),
             ("Root", self.root.clone().expect("Root not set")),
         ]);
-        if let Some(info) = self.info.clone() {
+        if let (Some(info), false) = (self.info.clone(), self.omit_info) {
             dict.add_entry("Info", info);
         }
         if let Some(id) = self.id.clone() {
@@ -301,3 +712,157 @@ impl PDFData for Trailer {
         dict.write(o)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer into a buffer, and the buffer
+    fn writer() -> (PDFWrite, Rc<RefCell<Vec<u8>>>) {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        (PDFWrite::new(Box::new(Buffer(buffer.clone()))), buffer)
+    }
+
+    #[test]
+    fn first_number() {
+        let (mut write, buffer) = writer();
+        write.set_next_number(100);
+        write.create_root(Dict::new());
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("100 0 obj"));
+        assert!(file.contains("/Root 100 0 R"));
+    }
+
+    #[test]
+    fn reserved_numbers_are_skipped() {
+        let (mut write, buffer) = writer();
+        write.reserve_numbers([1, 2]);
+        write.create_root(Dict::new());
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("3 0 obj"));
+        assert!(!file.contains("1 0 obj"));
+    }
+
+    #[test]
+    #[should_panic(expected = "may already be in use")]
+    fn reserve_used_number() {
+        let (mut write, _) = writer();
+        write.set_next_number(100);
+        write.allocate(Dict::new());
+        write.reserve_numbers([100]);
+    }
+
+    /// The two parts of the `/ID` in the trailer of the file
+    fn file_id_of(file: &str) -> Vec<String> {
+        let start = file.find("/ID [").expect("No /ID") + "/ID [".len();
+        let end = start + file[start..].find(']').unwrap();
+        file[start..end].split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn file_id() {
+        let (mut write, buffer) = writer();
+        write.create_root(Dict::new());
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        let id = file_id_of(&file);
+        assert_eq!(id.len(), 2);
+        for part in id {
+            assert_eq!(part.len(), 34);
+            assert!(part.starts_with('<') && part.ends_with('>'));
+            assert!(part[1..33].chars().all(|c| c.is_ascii_hexdigit()));
+        }
+    }
+
+    #[test]
+    fn set_id() {
+        let (mut write, buffer) = writer();
+        write.set_id([0xAB; 16]);
+        write.create_root(Dict::new());
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        let part = format!("<{}>", "AB".repeat(16));
+        assert_eq!(file_id_of(&file), vec![part.clone(), part]);
+    }
+
+    #[test]
+    fn reproducible_file_id() {
+        let file = || {
+            let (mut write, buffer) = writer();
+            write.set_reproducible();
+            write.create_root(Dict::from_vec(vec![("Title", PDFString::new("Test"))]));
+            write.write().unwrap();
+            let file = buffer.borrow().clone();
+            file
+        };
+        assert_eq!(file(), file());
+    }
+
+    #[test]
+    fn max_objects() {
+        let (mut write, _) = writer();
+        write.set_max_objects(2);
+        let a = write.allocate(Dict::new());
+        let b = write.allocate(Dict::new());
+        write.create_root(Dict::from_vec(vec![
+            ("A", a as Rc<dyn PDFData>),
+            ("B", b as Rc<dyn PDFData>),
+        ]));
+        let e = write.write().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn max_objects_leaves_out_inline_objects() {
+        let (mut write, buffer) = writer();
+        write.set_max_objects(1);
+        write.minimize_objects();
+        let a = write.allocate(Dict::new());
+        write.create_root(Dict::from_vec(vec![("A", a as Rc<dyn PDFData>)]));
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("/A <<"));
+    }
+
+    #[test]
+    fn allocate() {
+        let (mut write, buffer) = writer();
+        let data = write.allocate(Dict::from_vec(vec![("Test", Rc::new(1usize))]));
+        write.create_root(Dict::from_vec(vec![("Data", data as Rc<dyn PDFData>)]));
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("1 0 obj\n<<\n/Test 1\n>>\nendobj"));
+        assert!(file.contains("/Data 1 0 R"));
+    }
+
+    #[test]
+    fn set_root() {
+        let (mut write, buffer) = writer();
+        let catalog = write.allocate(Dict::from_vec(vec![("Type", Name::new("Catalog"))]));
+        write.set_root(catalog);
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("1 0 obj\n<<\n/Type /Catalog\n>>\nendobj"));
+        assert!(file.contains("/Root 1 0 R"));
+    }
+
+    #[test]
+    fn minimize_objects() {
+        let (mut write, buffer) = writer();
+        write.minimize_objects();
+        let once = write.allocate(Dict::from_vec(vec![("Test", Rc::new(1usize))]));
+        let twice = write.allocate(Dict::from_vec(vec![("Test", Rc::new(2usize))]));
+        write.create_root(Dict::from_vec(vec![
+            ("Once", once as Rc<dyn PDFData>),
+            ("Twice", twice.clone()),
+            ("Again", twice),
+        ]));
+        write.write().unwrap();
+        let file = String::from_utf8_lossy(&buffer.borrow()).into_owned();
+        assert!(file.contains("/Once <<\n/Test 1\n>>\n"));
+        assert!(!file.contains("1 0 obj"));
+        assert!(file.contains("/Twice 2 0 R\n/Again 2 0 R\n"));
+    }
+}
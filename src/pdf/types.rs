@@ -1,6 +1,6 @@
+use super::flate::{self, Predictor};
 use super::Object;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::io::{Result, Write};
 use std::rc::Rc;
 
@@ -9,6 +9,16 @@ pub trait PDFData: std::fmt::Debug {
     fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
         vec![]
     }
+    /// Adds the address of each indirect object written as a reference
+    /// in the data, once per reference
+    fn references(&self, _refs: &mut Vec<*const ()>) {}
+    /// Whether the data is a stream, which can only be an indirect object
+    fn is_stream(&self) -> bool {
+        false
+    }
+    /// Forgets what the data recorded for the document it was written
+    /// in, see Object::release
+    fn forget(&self) {}
 }
 
 impl PDFData for usize {
@@ -16,14 +26,20 @@ impl PDFData for usize {
         write!(o, "{}", self)
     }
 }
+impl PDFData for bool {
+    fn write(&self, o: &mut dyn Write) -> Result<()> {
+        write!(o, "{}", self)
+    }
+}
 impl PDFData for f64 {
     fn write(&self, o: &mut dyn Write) -> Result<()> {
         write!(o, "{}", self)
     }
 }
+/// The two parts of a file identifier, as hex digits
 impl PDFData for [std::string::String; 2] {
     fn write(&self, o: &mut dyn Write) -> Result<()> {
-        write!(o, "[{}, {}]", self[0], self[1])
+        write!(o, "[<{}> <{}>]", self[0], self[1])
     }
 }
 impl<T: PDFData + ?Sized> PDFData for Vec<Rc<T>> {
@@ -46,6 +62,63 @@ impl<T: PDFData + ?Sized> PDFData for Vec<Rc<T>> {
         }
         tmp
     }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        for obj in self.iter() {
+            obj.references(refs);
+        }
+    }
+}
+
+/// An array whose elements can have different types, such as
+/// `[1 /Name (str) 2.0]`
+#[derive(Debug)]
+pub struct Array {
+    items: RefCell<Vec<Rc<dyn PDFData>>>,
+}
+impl Array {
+    pub fn new() -> Rc<Self> {
+        Self::from_vec(vec![])
+    }
+    pub fn from_vec(v: Vec<Rc<dyn PDFData>>) -> Rc<Self> {
+        Rc::new(Self {
+            items: RefCell::new(v),
+        })
+    }
+    pub fn push(&self, data: Rc<dyn PDFData>) {
+        self.items.borrow_mut().push(data);
+    }
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+impl PDFData for Array {
+    fn write(&self, o: &mut dyn Write) -> Result<()> {
+        self.items.borrow().write(o)
+    }
+    fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
+        self.items.borrow().dependent_objects()
+    }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        self.items.borrow().references(refs);
+    }
+}
+
+/// Data that is written exactly as given, for PDF syntax without
+/// its own type
+#[derive(Debug)]
+pub struct Raw(Vec<u8>);
+impl Raw {
+    pub fn new(data: impl Into<Vec<u8>>) -> Rc<Self> {
+        Rc::new(Self(data.into()))
+    }
+}
+impl PDFData for Raw {
+    fn write(&self, o: &mut dyn Write) -> Result<()> {
+        o.write_all(&self.0)
+    }
 }
 
 #[derive(Eq, PartialEq, Hash, Debug, Clone)]
@@ -81,80 +154,275 @@ impl PDFData for Name {
     }
 }
 
+/// A text string, written as a literal `(...)`
+///
+/// Backslashes and parentheses are escaped when written, as are
+/// carriage returns, which readers would otherwise turn into LF. Strings
+/// containing non-ASCII characters are written as UTF-16BE hex
+/// strings, with a byte order mark, as required by the spec.
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct PDFString(String);
+impl PDFString {
+    pub fn new(s: impl Into<String>) -> Rc<Self> {
+        Rc::new(Self(s.into()))
+    }
+}
+impl PDFData for PDFString {
+    fn write(&self, o: &mut dyn Write) -> Result<()> {
+        if !self.0.is_ascii() {
+            write!(o, "<FEFF")?;
+            for u in self.0.encode_utf16() {
+                write!(o, "{:04X}", u)?;
+            }
+            return write!(o, ">");
+        }
+        write!(o, "(")?;
+        for c in self.0.chars() {
+            match c {
+                '\\' | '(' | ')' => write!(o, "\\{}", c)?,
+                '\r' => write!(o, "\\r")?,
+                c => write!(o, "{}", c)?,
+            }
+        }
+        write!(o, ")")
+    }
+}
+
 #[derive(Debug)]
 pub struct Dict {
-    items: RefCell<HashMap<Name, Rc<dyn PDFData>>>,
+    /// The entries, in the order the keys were first added
+    items: RefCell<Vec<(Name, Rc<dyn PDFData>)>>,
 }
 impl Dict {
     pub fn new() -> Rc<Self> {
         Rc::new(Self {
-            items: RefCell::new(HashMap::new()),
+            items: RefCell::new(vec![]),
         })
     }
     pub fn from_vec(v: Vec<(impl Into<Name>, Rc<dyn PDFData>)>) -> Rc<Self> {
-        let mut items = HashMap::new();
+        let dict = Self::new();
         for (n, d) in v {
-            items.insert(n.into(), d);
+            dict.add_entry(n, d);
         }
-        Rc::new(Self {
-            items: RefCell::new(items),
-        })
+        dict
     }
+    /// Adds the entry, replacing the value if the key is already in
+    /// the dictionary, without moving the entry
     pub fn add_entry(&self, n: impl Into<Name>, data: Rc<dyn PDFData>) {
-        self.items.borrow_mut().insert(n.into(), data);
+        let n = n.into();
+        let mut items = self.items.borrow_mut();
+        match items.iter_mut().find(|(k, _)| *k == n) {
+            Some((_, v)) => *v = data,
+            None => items.push((n, data)),
+        }
     }
     pub fn add_optional(&self, n: impl Into<Name>, data: Option<Rc<dyn PDFData>>) {
         if let Some(data) = data {
-            self.items.borrow_mut().insert(n.into(), data);
+            self.add_entry(n, data);
         }
     }
     pub fn get_entry(&self, n: impl Into<Name>) -> Option<Rc<dyn PDFData>> {
-        self.items.borrow_mut().get(&n.into()).cloned()
+        let n = n.into();
+        self.items
+            .borrow()
+            .iter()
+            .find(|(k, _)| *k == n)
+            .map(|(_, v)| v.clone())
+    }
+    /// The value of the entry, the same as get_entry
+    pub fn get(&self, n: impl Into<Name>) -> Option<Rc<dyn PDFData>> {
+        self.get_entry(n)
+    }
+    /// The entries of the dictionary, in the order they were added
+    ///
+    /// The entries are copied out, so the dictionary can be changed
+    /// while iterating
+    pub fn iter(&self) -> impl Iterator<Item = (Name, Rc<dyn PDFData>)> {
+        let items: Vec<_> = self.items.borrow().clone();
+        items.into_iter()
     }
     pub fn is_empty(&self) -> bool {
         self.items.borrow().is_empty()
     }
+    /// Removes every entry, keeping the allocated space
+    pub fn clear(&self) {
+        self.items.borrow_mut().clear();
+    }
+    /// A new dictionary with the same entries
+    ///
+    /// The values are shared, but entries added to one dictionary
+    /// aren't added to the other
+    pub fn copy(&self) -> Rc<Self> {
+        Rc::new(Self {
+            items: RefCell::new(self.items.borrow().clone()),
+        })
+    }
 }
 impl PDFData for Dict {
     fn write(&self, o: &mut dyn Write) -> Result<()> {
-        write!(o, "<<\n")?;
+        writeln!(o, "<<")?;
         for (k, v) in self.items.borrow().iter() {
             k.write(o)?;
             write!(o, " ")?;
             v.write(o)?;
-            write!(o, "\n")?;
+            writeln!(o)?;
         }
-        write!(o, ">>\n")
+        writeln!(o, ">>")
     }
     fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
         let mut tmp = vec![];
-        for obj in self.items.borrow().values() {
+        for (_, obj) in self.items.borrow().iter() {
             tmp.extend(obj.dependent_objects());
         }
         tmp
     }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        for (_, obj) in self.items.borrow().iter() {
+            obj.references(refs);
+        }
+    }
 }
 #[derive(Debug)]
 pub struct Stream {
     meta: Rc<Dict>,
     data: Vec<u8>,
+    /// Objects the stream refers to, such as the resources of its
+    /// content, which are added along with the stream
+    dependents: RefCell<Vec<Rc<dyn Object>>>,
 }
 
 impl Stream {
     pub fn new(meta: Rc<Dict>, data: Vec<u8>) -> Rc<Self> {
         meta.add_entry("Length", Rc::new(data.len()));
-        Rc::new(Self { meta, data })
+        Rc::new(Self {
+            meta,
+            data,
+            dependents: RefCell::new(vec![]),
+        })
+    }
+    /// A stream compressed with the FlateDecode filter
+    ///
+    /// - predictor: filters the rows of sample data before they are
+    ///   compressed, which compresses images much better
+    /// - level: from 0 (no compression) to 9 (smallest output), see
+    ///   flate::compress
+    pub fn flate(
+        meta: Rc<Dict>,
+        data: &[u8],
+        predictor: Option<Predictor>,
+        level: u32,
+    ) -> Rc<Self> {
+        meta.add_entry("Filter", Name::new("FlateDecode"));
+        let data = match predictor {
+            Some(p) => {
+                meta.add_entry(
+                    "DecodeParms",
+                    Dict::from_vec(vec![
+                        ("Predictor", Rc::new(15usize)),
+                        ("Colors", Rc::new(p.colors)),
+                        ("BitsPerComponent", Rc::new(p.bits)),
+                        ("Columns", Rc::new(p.columns)),
+                    ]),
+                );
+                flate::compress(&p.encode(data), level)
+            }
+            None => flate::compress(data, level),
+        };
+        Self::new(meta, data)
     }
     pub fn add_entry(&self, n: impl Into<Name>, data: Rc<dyn PDFData>) {
         self.meta.add_entry(n, data);
     }
+    pub fn meta(&self) -> Rc<Dict> {
+        self.meta.clone()
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// Adds an object the stream refers to, which is added to the
+    /// writer whenever the stream is
+    pub fn add_dependent(&self, obj: Rc<dyn Object>) {
+        self.dependents.borrow_mut().push(obj);
+    }
 }
 
 impl PDFData for Stream {
     fn write(&self, o: &mut dyn Write) -> Result<()> {
         self.meta.write(o)?;
-        write!(o, "stream\n")?;
+        writeln!(o, "stream")?;
         o.write_all(&self.data)?;
         write!(o, "\nendstream\n")
     }
+    fn dependent_objects(&self) -> Vec<Rc<dyn Object>> {
+        self.dependents.borrow().clone()
+    }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        self.meta.references(refs);
+    }
+    fn is_stream(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The data as written to a file
+    fn written(data: &dyn PDFData) -> String {
+        let mut out = vec![];
+        data.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn dict_get() {
+        let dict = Dict::new();
+        dict.add_entry("Count", Rc::new(3usize));
+        assert_eq!(written(&*dict.get("Count").unwrap()), "3");
+        assert_eq!(written(&*dict.get_entry("Count").unwrap()), "3");
+        assert!(dict.get("Kids").is_none());
+    }
+
+    #[test]
+    fn dict_iter() {
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("Page") as Rc<dyn PDFData>),
+            ("Count", Rc::new(3usize)),
+        ]);
+        let keys: Vec<_> = dict.iter().map(|(k, _)| written(&k)).collect();
+        assert_eq!(keys, ["/Type", "/Count"]);
+    }
+
+    #[test]
+    fn dict_order_is_deterministic() {
+        let build = || {
+            let dict = Dict::new();
+            for key in ["Type", "Subtype", "Width", "Height", "ColorSpace", "Filter"] {
+                dict.add_entry(key, Name::new(key));
+            }
+            written(&*dict)
+        };
+        let first = build();
+        for _ in 0..10 {
+            assert_eq!(build(), first);
+        }
+    }
+
+    #[test]
+    fn dict_add_entry_replaces_in_place() {
+        let dict = Dict::new();
+        dict.add_entry("A", Rc::new(1usize));
+        dict.add_entry("B", Rc::new(2usize));
+        dict.add_entry("A", Rc::new(3usize));
+        assert_eq!(written(&*dict), "<<\n/A 3\n/B 2\n>>\n");
+    }
+
+    #[test]
+    fn array_mixed() {
+        let array = Array::from_vec(vec![Rc::new(3usize), Name::new("Fit")]);
+        array.push(PDFString::new("text"));
+        assert_eq!(array.len(), 3);
+        assert_eq!(written(&*array), "[3 /Fit (text)]");
+    }
 }
@@ -0,0 +1,109 @@
+use crate::graphics::Font;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Fonts shared by many documents, so a batch of documents only builds
+/// each font once, see PDF::new_with_pool
+///
+/// Each document still writes its own copy of the font objects. The
+/// objects are numbered by the document they are written in, so the
+/// documents sharing a pool must be written one at a time.
+#[derive(Debug, Default)]
+pub struct ResourcePool {
+    fonts: RefCell<Vec<(String, Rc<Font>)>>,
+    /// Whether a document using the pool hasn't been written yet
+    in_use: Cell<bool>,
+}
+
+impl ResourcePool {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self::default())
+    }
+    /// The font with the key, which is built by make the first time
+    /// it is used
+    ///
+    /// - key: identifies the font in the pool, such as `"helvetica"`
+    /// - make: builds the font, such as `Font::helvetica`
+    pub fn font(&self, key: &str, make: impl FnOnce() -> Rc<Font>) -> Rc<Font> {
+        if let Some((_, font)) = self.fonts.borrow().iter().find(|(k, _)| k == key) {
+            return font.clone();
+        }
+        let font = make();
+        self.fonts
+            .borrow_mut()
+            .push((key.to_string(), font.clone()));
+        font
+    }
+    /// The number of fonts built so far
+    pub fn len(&self) -> usize {
+        self.fonts.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Marks the pool as in use, until the document using it is written
+/// or dropped
+pub(crate) struct PoolLease(Rc<ResourcePool>);
+
+impl PoolLease {
+    /// # Panics
+    ///
+    /// panics if another document using the pool hasn't been written
+    pub(crate) fn new(pool: &Rc<ResourcePool>) -> Self {
+        assert!(
+            !pool.in_use.get(),
+            "Another document using the pool hasn't been written yet"
+        );
+        pool.in_use.set(true);
+        Self(pool.clone())
+    }
+}
+
+impl Drop for PoolLease {
+    fn drop(&mut self) {
+        self.0.in_use.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{output, text};
+    use crate::{Page, PDF};
+
+    #[test]
+    fn shared_font() {
+        let pool = ResourcePool::new();
+        let built = Cell::new(0);
+        let mut files = vec![];
+        for i in 0..5 {
+            let buffer = Rc::new(RefCell::new(vec![]));
+            let mut pdf = PDF::new_with_pool(output(&buffer), &pool);
+            let font = pool.font("helvetica", || {
+                built.set(built.get() + 1);
+                Font::helvetica()
+            });
+            let mut page = Page::new();
+            page.set_default_font(font, 12f64);
+            page.text_at((10f64, 10f64), &format!("Document {}", i));
+            pdf.add_page(page);
+            pdf.write().unwrap();
+            files.push(text(&buffer.borrow()));
+        }
+        assert_eq!((built.get(), pool.len()), (1, 1));
+        // Each document writes its own copy of the font
+        for file in files {
+            assert_eq!(file.matches("/BaseFont /Helvetica\n").count(), 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "hasn't been written yet")]
+    fn two_documents_at_once() {
+        let pool = ResourcePool::new();
+        let _first = PDF::new_with_pool(Box::new(std::io::sink()), &pool);
+        PDF::new_with_pool(Box::new(std::io::sink()), &pool);
+    }
+}
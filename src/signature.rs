@@ -0,0 +1,309 @@
+use crate::graphics::Rect;
+use crate::pdf::{Crt, Dict, Name, ObjError, ObjRef, Object, Output, PDFData, PDFString, PDFWrite};
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// The changes allowed to a certified document (`/P` of `/DocMDP`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DocMdp {
+    /// No changes invalidate the certification
+    NoChanges,
+    /// Filling in forms, adding page templates and signing
+    FormFilling,
+    /// Form filling, as well as adding or changing annotations
+    Annotations,
+}
+impl DocMdp {
+    fn level(&self) -> usize {
+        match self {
+            Self::NoChanges => 1,
+            Self::FormFilling => 2,
+            Self::Annotations => 3,
+        }
+    }
+}
+
+/// The permissions dictionary of the document (`/Perms`)
+///
+/// Each permission is a signature dictionary with a `/Reference` to the
+/// transform it grants. No signature is computed: the dictionaries are
+/// placeholders for a signer to complete.
+#[derive(Debug, Clone, Default)]
+pub struct Perms {
+    doc_mdp: Option<DocMdp>,
+    usage_rights: Vec<(String, Vec<String>)>,
+}
+
+impl Perms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Certifies the document, allowing only the changes given (`/DocMDP`)
+    pub fn doc_mdp(mut self, allowed: DocMdp) -> Self {
+        self.doc_mdp = Some(allowed);
+        self
+    }
+    /// Grants additional usage rights (`/UR3`)
+    ///
+    /// - category: the key in the transform parameters, such as
+    ///   `"Document"`, `"Form"`, or `"Annots"`
+    /// - rights: the rights granted, such as `["FullSave"]`
+    pub fn usage_rights(mut self, category: &str, rights: &[&str]) -> Self {
+        self.usage_rights.push((
+            category.to_string(),
+            rights.iter().map(|r| r.to_string()).collect(),
+        ));
+        self
+    }
+    /// A signature dictionary referencing a transform
+    fn signature(method: &str, params: Rc<Dict>, write: &mut PDFWrite) -> Rc<dyn PDFData> {
+        params.add_entry("Type", Name::new("TransformParams"));
+        let reference = Dict::from_vec(vec![
+            ("Type", Name::new("SigRef") as Rc<dyn PDFData>),
+            ("TransformMethod", Name::new(method)),
+            ("TransformParams", params),
+        ]);
+        let sig = ObjRef::new(
+            0,
+            Dict::from_vec(vec![
+                ("Type", Name::new("Sig") as Rc<dyn PDFData>),
+                ("Filter", Name::new("Adobe.PPKLite")),
+                ("Reference", Rc::new(vec![reference as Rc<dyn PDFData>])),
+            ]),
+        );
+        write.add_object(sig.clone());
+        sig
+    }
+    /// Adds the signature dictionaries to the writer, and returns the
+    /// `/Perms` dictionary
+    pub(crate) fn add(self, write: &mut PDFWrite) -> Rc<Dict> {
+        let perms = Dict::new();
+        if let Some(allowed) = self.doc_mdp {
+            let params = Dict::from_vec(vec![
+                ("P", Rc::new(allowed.level()) as Rc<dyn PDFData>),
+                ("V", Name::new("1.2")),
+            ]);
+            perms.add_entry("DocMDP", Self::signature("DocMDP", params, write));
+        }
+        if !self.usage_rights.is_empty() {
+            let params = Dict::from_vec(vec![("V", Name::new("2.2") as Rc<dyn PDFData>)]);
+            for (category, rights) in self.usage_rights {
+                let rights: Vec<Rc<dyn PDFData>> = rights
+                    .iter()
+                    .map(|r| Name::new(r) as Rc<dyn PDFData>)
+                    .collect();
+                params.add_entry(category, Rc::new(rights));
+            }
+            perms.add_entry("UR3", Self::signature("UR3", params, write));
+        }
+        perms
+    }
+}
+
+/// Width of the `/ByteRange` placeholder, enough for offsets below 10^10
+const RANGE_WIDTH: usize = 36;
+
+/// The signature dictionary of a signature field, which records where
+/// its placeholders are written
+#[derive(Debug)]
+struct SignatureValue {
+    num: Cell<Option<usize>>,
+    size: usize,
+    /// Offset of the `/ByteRange` array
+    range: Cell<Option<usize>>,
+    /// Offset of the `<` starting the `/Contents` string
+    contents: Cell<Option<usize>>,
+}
+
+impl PDFData for SignatureValue {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        write!(o, "{} 0 R", self.num.get().expect("No number assigned"))
+    }
+    fn references(&self, refs: &mut Vec<*const ()>) {
+        refs.push(self as *const Self as *const ());
+    }
+}
+
+impl Object for SignatureValue {
+    fn write_obj(&self, crt: &mut Crt, out: &mut Output) -> io::Result<()> {
+        let num = self.num.get().expect("No num");
+        crt.add_entry(out.get_pos(), num, 0, false);
+        writeln!(out, "{} 0 obj", num)?;
+        writeln!(out, "<<")?;
+        writeln!(out, "/Type /Sig")?;
+        writeln!(out, "/Filter /Adobe.PPKLite")?;
+        writeln!(out, "/SubFilter /adbe.pkcs7.detached")?;
+        write!(out, "/ByteRange ")?;
+        self.range.set(Some(out.get_pos()));
+        writeln!(out, "[0 {:010} {:010} {:010}]", 0, 0, 0)?;
+        write!(out, "/Contents ")?;
+        self.contents.set(Some(out.get_pos()));
+        writeln!(out, "<{}>", "0".repeat(self.size * 2))?;
+        writeln!(out, ">>")?;
+        writeln!(out, "endobj")
+    }
+    fn assign_num(&self, num: usize) -> Result<(), ObjError> {
+        if self.num.get().is_some() {
+            Err(ObjError::AlreadyAssigned)
+        } else {
+            self.num.set(Some(num));
+            Ok(())
+        }
+    }
+    fn is_indirect(&self) -> bool {
+        true
+    }
+}
+
+/// A signature form field, with space reserved for a signature
+///
+/// The signature dictionary is written with a `/ByteRange` placeholder
+/// and a zero-filled `/Contents` string. After the document is written,
+/// set_byte_range fills in the byte range, and an external signer
+/// computes a detached PKCS#7 signature of those bytes, which is added
+/// with set_contents.
+///
+/// The field is invisible: its widget has an empty rect.
+#[derive(Debug)]
+pub struct SignatureField {
+    widget: Rc<ObjRef<Dict>>,
+    value: Rc<SignatureValue>,
+}
+
+impl SignatureField {
+    /// - name: the name of the field (`/T`)
+    /// - size: the number of bytes reserved for the signature
+    pub fn new(name: &str, size: usize) -> Rc<Self> {
+        let value = Rc::new(SignatureValue {
+            num: Cell::new(None),
+            size,
+            range: Cell::new(None),
+            contents: Cell::new(None),
+        });
+        let widget = ObjRef::new(
+            0,
+            Dict::from_vec(vec![
+                ("Type", Name::new("Annot") as Rc<dyn PDFData>),
+                ("Subtype", Name::new("Widget")),
+                ("FT", Name::new("Sig")),
+                ("T", PDFString::new(name)),
+                ("Rect", Rect::new(0f64, 0f64, 0f64, 0f64).as_data()),
+                ("V", value.clone()),
+            ]),
+        );
+        Rc::new(Self { widget, value })
+    }
+    /// The number of bytes reserved for the signature
+    pub fn size(&self) -> usize {
+        self.value.size
+    }
+    /// The offsets of the `/Contents` string in the written file,
+    /// including the angle brackets
+    ///
+    /// Returns None until the document is written
+    pub fn contents_range(&self) -> Option<(usize, usize)> {
+        let start = self.value.contents.get()?;
+        Some((start, start + self.value.size * 2 + 2))
+    }
+    /// The `/ByteRange` of the signature: every byte of the file except
+    /// the `/Contents` string
+    ///
+    /// - len: the length of the written file
+    ///
+    /// Returns None until the document is written
+    pub fn byte_range(&self, len: usize) -> Option<[usize; 4]> {
+        let (start, end) = self.contents_range()?;
+        Some([0, start, end, len - end])
+    }
+    /// Writes the byte range into the placeholder
+    ///
+    /// - file: the written document
+    ///
+    /// # Panics
+    ///
+    /// panics if the document hasn't been written
+    pub fn set_byte_range(&self, file: &mut [u8]) {
+        let range = self
+            .byte_range(file.len())
+            .expect("The document hasn't been written");
+        let pos = self.value.range.get().unwrap();
+        let text = format!(
+            "{:width$}",
+            format!("[{} {} {} {}]", range[0], range[1], range[2], range[3]),
+            width = RANGE_WIDTH
+        );
+        file[pos..pos + RANGE_WIDTH].copy_from_slice(text.as_bytes());
+    }
+    /// Writes the signature into the `/Contents` string, as hex
+    ///
+    /// - file: the written document
+    /// - signature: the DER encoded PKCS#7 signature
+    ///
+    /// # Panics
+    ///
+    /// panics if the document hasn't been written, or the signature is
+    /// larger than the reserved size
+    pub fn set_contents(&self, file: &mut [u8], signature: &[u8]) {
+        assert!(
+            signature.len() <= self.value.size,
+            "The signature is larger than the reserved space"
+        );
+        let (start, _) = self
+            .contents_range()
+            .expect("The document hasn't been written");
+        let hex: String = signature.iter().map(|b| format!("{:02X}", b)).collect();
+        file[start + 1..start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+    }
+    /// Adds the widget and signature dictionary to the writer
+    ///
+    /// - page: the page the widget is on
+    pub(crate) fn add(&self, page: Rc<dyn PDFData>, write: &mut PDFWrite) -> Rc<dyn PDFData> {
+        self.widget.add_entry("P", page);
+        write.add_object(self.widget.clone());
+        write.add_object(self.value.clone());
+        self.widget.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{text, written};
+    use crate::Page;
+
+    #[test]
+    fn perms() {
+        let file = text(&written(|pdf| {
+            pdf.set_perms(Perms::new().doc_mdp(DocMdp::FormFilling));
+            pdf.add_page(Page::new());
+        }));
+        assert!(file.contains("/Perms <<\n/DocMDP "));
+        assert!(file.contains("/TransformMethod /DocMDP\n"));
+        assert!(file.contains("/P 2\n"));
+    }
+
+    #[test]
+    fn signature_field() {
+        let field = SignatureField::new("Signature", 100);
+        let mut file = written(|pdf| {
+            let mut page = Page::new();
+            page.add_signature_field(field.clone());
+            pdf.add_page(page);
+        });
+        let (start, end) = field.contents_range().unwrap();
+        assert_eq!(end - start, 202);
+        assert_eq!(file[start], b'<');
+        assert_eq!(file[end - 1], b'>');
+        assert!(file[start + 1..end - 1].iter().all(|b| *b == b'0'));
+
+        let len = file.len();
+        field.set_byte_range(&mut file);
+        let expected = format!("/ByteRange [0 {} {} {}]", start, end, len - end);
+        let file = text(&file);
+        let range = &file[file.find("/ByteRange ").unwrap()..];
+        assert!(range.starts_with(&expected));
+        // The placeholder keeps its width, so no offsets change
+        assert!(range["/ByteRange ".len() + RANGE_WIDTH..].starts_with("\n/Contents <"));
+    }
+}
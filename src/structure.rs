@@ -0,0 +1,73 @@
+use crate::pdf::{Array, Dict, Name, ObjRef, PDFData, PDFWrite};
+use std::rc::Rc;
+
+/// Builds the logical structure tree of a tagged document
+///
+/// Every tagged page gets a `/StructParents` key, which indexes the
+/// `/ParentTree` number tree. The entry for a page is an array mapping
+/// each marked content id (MCID) on the page to its structure element.
+#[derive(Debug)]
+pub(crate) struct StructTree {
+    root: Rc<ObjRef<Dict>>,
+    kids: Vec<Rc<dyn PDFData>>,
+    /// Alternating keys and arrays of structure elements
+    nums: Rc<Array>,
+}
+
+impl StructTree {
+    pub fn new() -> Self {
+        Self {
+            root: ObjRef::new(
+                0,
+                Dict::from_vec(vec![("Type", Name::new("StructTreeRoot"))]),
+            ),
+            kids: vec![],
+            nums: Array::new(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.kids.is_empty()
+    }
+    /// Adds a structure element for each tagged piece of content
+    /// on the page
+    ///
+    /// - page: the page object
+    /// - tags: the structure type of each MCID, in order
+    ///
+    /// Returns the `/StructParents` key for the page
+    pub fn add_page(
+        &mut self,
+        page: Rc<dyn PDFData>,
+        tags: &[Rc<Name>],
+        write: &mut PDFWrite,
+    ) -> usize {
+        let key = self.nums.len() / 2;
+        let mut parents: Vec<Rc<dyn PDFData>> = vec![];
+        for (mcid, tag) in tags.iter().enumerate() {
+            let elem = write.allocate(Dict::from_vec(vec![
+                ("Type", Name::new("StructElem") as Rc<dyn PDFData>),
+                ("S", tag.clone()),
+                ("P", self.root.clone()),
+                ("Pg", page.clone()),
+                ("K", Rc::new(mcid)),
+            ]));
+            self.kids.push(elem.clone());
+            parents.push(elem);
+        }
+        self.nums.push(Rc::new(key));
+        self.nums.push(Rc::new(parents));
+        key
+    }
+    /// Completes the tree, and adds the root to the writer
+    pub fn finish(self, write: &mut PDFWrite) -> Rc<ObjRef<Dict>> {
+        let next_key = self.nums.len() / 2;
+        self.root.add_entry("K", Rc::new(self.kids));
+        self.root.add_entry(
+            "ParentTree",
+            Dict::from_vec(vec![("Nums", self.nums as Rc<dyn PDFData>)]),
+        );
+        self.root.add_entry("ParentTreeNextKey", Rc::new(next_key));
+        write.add_object(self.root.clone());
+        self.root
+    }
+}
@@ -0,0 +1,117 @@
+use crate::pdf::{Dict, Name, PDFData};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Direction a transition moves in, in degrees counter-clockwise
+/// from left to right
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    LeftToRight,
+    BottomToTop,
+    RightToLeft,
+    TopToBottom,
+    /// Top-left to bottom-right, only valid for Glitter
+    TopLeftToBottomRight,
+}
+impl Direction {
+    fn degrees(&self) -> usize {
+        match self {
+            Self::LeftToRight => 0,
+            Self::BottomToTop => 90,
+            Self::RightToLeft => 180,
+            Self::TopToBottom => 270,
+            Self::TopLeftToBottomRight => 315,
+        }
+    }
+}
+
+/// The visual effect used when moving to a page during a presentation
+///
+/// - horizontal: the dimension the effect happens in (`/Dm`)
+/// - inward: the motion of the effect (`/M`)
+/// - direction: see Direction (`/Di`)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransitionStyle {
+    Split { horizontal: bool, inward: bool },
+    Blinds { horizontal: bool },
+    Box { inward: bool },
+    Wipe { direction: Direction },
+    Dissolve,
+    Glitter { direction: Direction },
+    Replace,
+    Fly { horizontal: bool, inward: bool },
+    Push { direction: Direction },
+    Cover { direction: Direction },
+    Uncover { direction: Direction },
+    Fade,
+}
+
+/// A page transition dictionary
+#[derive(Debug, Clone)]
+pub struct Transition {
+    style: TransitionStyle,
+    duration: f64,
+}
+
+impl Transition {
+    pub fn new(style: TransitionStyle, duration: f64) -> Rc<Self> {
+        Rc::new(Self { style, duration })
+    }
+}
+
+impl PDFData for Transition {
+    fn write(&self, o: &mut dyn Write) -> io::Result<()> {
+        let dimension = |h: bool| Name::new(if h { "H" } else { "V" });
+        let motion = |i: bool| Name::new(if i { "I" } else { "O" });
+        let direction = |d: Direction| Rc::new(d.degrees());
+        let dict = Dict::from_vec(vec![
+            ("Type", Name::new("Trans")),
+            ("D", Rc::new(self.duration)),
+        ]);
+        let style = match self.style {
+            TransitionStyle::Split { horizontal, inward } => {
+                dict.add_entry("Dm", dimension(horizontal));
+                dict.add_entry("M", motion(inward));
+                "Split"
+            }
+            TransitionStyle::Blinds { horizontal } => {
+                dict.add_entry("Dm", dimension(horizontal));
+                "Blinds"
+            }
+            TransitionStyle::Box { inward } => {
+                dict.add_entry("M", motion(inward));
+                "Box"
+            }
+            TransitionStyle::Wipe { direction: d } => {
+                dict.add_entry("Di", direction(d));
+                "Wipe"
+            }
+            TransitionStyle::Dissolve => "Dissolve",
+            TransitionStyle::Glitter { direction: d } => {
+                dict.add_entry("Di", direction(d));
+                "Glitter"
+            }
+            TransitionStyle::Replace => "R",
+            TransitionStyle::Fly { horizontal, inward } => {
+                dict.add_entry("Dm", dimension(horizontal));
+                dict.add_entry("M", motion(inward));
+                "Fly"
+            }
+            TransitionStyle::Push { direction: d } => {
+                dict.add_entry("Di", direction(d));
+                "Push"
+            }
+            TransitionStyle::Cover { direction: d } => {
+                dict.add_entry("Di", direction(d));
+                "Cover"
+            }
+            TransitionStyle::Uncover { direction: d } => {
+                dict.add_entry("Di", direction(d));
+                "Uncover"
+            }
+            TransitionStyle::Fade => "Fade",
+        };
+        dict.add_entry("S", Name::new(style));
+        dict.write(o)
+    }
+}
@@ -0,0 +1,204 @@
+//! Structural checks of a written PDF file
+//!
+//! This isn't a parser: it only checks the parts of the file needed to
+//! find the objects, which is where a truncated or corrupted file
+//! normally fails first.
+
+use std::fmt;
+
+/// The reason a file failed PDF::verify_bytes
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// The file doesn't start with `%PDF-`
+    Header,
+    /// The file doesn't end with `%%EOF`, so it was probably truncated
+    Truncated,
+    /// The `startxref` offset is missing, or doesn't point at `xref`
+    StartXref,
+    /// The cross-reference table can't be read
+    Xref,
+    /// The trailer is missing, or is missing a required key
+    Trailer(&'static str),
+    /// The trailer `/Size` isn't one more than the highest object number
+    Size { size: usize, expected: usize },
+    /// The trailer `/Root` isn't an object in the table
+    Root(usize),
+    /// The table entry for the object doesn't point at its `obj` header
+    Object { num: usize, gen: usize },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Header => write!(f, "missing %PDF- header"),
+            Self::Truncated => write!(f, "missing %%EOF, the file may be truncated"),
+            Self::StartXref => write!(f, "startxref doesn't point at a cross-reference table"),
+            Self::Xref => write!(f, "invalid cross-reference table"),
+            Self::Trailer(key) => write!(f, "trailer is missing {}", key),
+            Self::Size { size, expected } => {
+                write!(f, "trailer /Size is {}, expected {}", size, expected)
+            }
+            Self::Root(num) => write!(f, "trailer /Root {} is not in use", num),
+            Self::Object { num, gen } => {
+                write!(f, "object {} {} is not at its xref offset", num, gen)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Splits off the next whitespace separated word
+fn word(s: &[u8]) -> (&[u8], &[u8]) {
+    let start = s
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(s.len());
+    let s = &s[start..];
+    let end = s
+        .iter()
+        .position(|b| b.is_ascii_whitespace() || *b == b'/' || *b == b'>')
+        .unwrap_or(s.len());
+    (&s[..end], &s[end..])
+}
+
+fn number(s: &[u8]) -> Option<(usize, &[u8])> {
+    let (w, rest) = word(s);
+    Some((std::str::from_utf8(w).ok()?.parse().ok()?, rest))
+}
+
+fn rfind(s: &[u8], pat: &[u8]) -> Option<usize> {
+    (0..=s.len().checked_sub(pat.len())?)
+        .rev()
+        .find(|i| s[*i..].starts_with(pat))
+}
+
+fn find(s: &[u8], pat: &[u8]) -> Option<usize> {
+    (0..=s.len().checked_sub(pat.len())?).find(|i| s[*i..].starts_with(pat))
+}
+
+/// An entry of the cross-reference table
+struct Entry {
+    num: usize,
+    offset: usize,
+    gen: usize,
+    in_use: bool,
+}
+
+/// Reads the subsections of the table, and returns the entries and
+/// the rest of the file, which should start with the trailer
+fn xref(s: &[u8]) -> Result<(Vec<Entry>, &[u8]), VerifyError> {
+    let (w, mut s) = word(s);
+    if w != b"xref" {
+        return Err(VerifyError::StartXref);
+    }
+    let mut entries = vec![];
+    loop {
+        let (w, _) = word(s);
+        if w == b"trailer" || w.is_empty() {
+            return Ok((entries, s));
+        }
+        let (start, rest) = number(s).ok_or(VerifyError::Xref)?;
+        let (count, rest) = number(rest).ok_or(VerifyError::Xref)?;
+        s = rest;
+        for num in start..start + count {
+            let (offset, rest) = number(s).ok_or(VerifyError::Xref)?;
+            let (gen, rest) = number(rest).ok_or(VerifyError::Xref)?;
+            let (kind, rest) = word(rest);
+            let in_use = match kind {
+                b"n" => true,
+                b"f" => false,
+                _ => return Err(VerifyError::Xref),
+            };
+            entries.push(Entry {
+                num,
+                offset,
+                gen,
+                in_use,
+            });
+            s = rest;
+        }
+    }
+}
+
+pub(crate) fn verify(bytes: &[u8]) -> Result<(), VerifyError> {
+    if !bytes.starts_with(b"%PDF-") {
+        return Err(VerifyError::Header);
+    }
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    if !bytes[..end].ends_with(b"%%EOF") {
+        return Err(VerifyError::Truncated);
+    }
+    let start = rfind(bytes, b"startxref").ok_or(VerifyError::StartXref)?;
+    let (offset, _) = number(&bytes[start + b"startxref".len()..]).ok_or(VerifyError::StartXref)?;
+    if offset >= start {
+        return Err(VerifyError::StartXref);
+    }
+    let (entries, rest) = xref(&bytes[offset..])?;
+
+    let trailer = &rest[..find(rest, b"startxref").unwrap_or(rest.len())];
+    if word(trailer).0 != b"trailer" {
+        return Err(VerifyError::Trailer("trailer"));
+    }
+    let size = find(trailer, b"/Size")
+        .and_then(|i| number(&trailer[i + 5..]))
+        .ok_or(VerifyError::Trailer("/Size"))?
+        .0;
+    let root = find(trailer, b"/Root")
+        .and_then(|i| number(&trailer[i + 5..]))
+        .ok_or(VerifyError::Trailer("/Root"))?
+        .0;
+    let expected = entries.iter().map(|e| e.num + 1).max().unwrap_or(0);
+    if size != expected {
+        return Err(VerifyError::Size { size, expected });
+    }
+    if !entries.iter().any(|e| e.in_use && e.num == root) {
+        return Err(VerifyError::Root(root));
+    }
+    for e in entries.iter().filter(|e| e.in_use) {
+        let header = format!("{} {} obj", e.num, e.gen);
+        if e.offset >= bytes.len() || !bytes[e.offset..].starts_with(header.as_bytes()) {
+            return Err(VerifyError::Object {
+                num: e.num,
+                gen: e.gen,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::written;
+    use crate::Page;
+
+    #[test]
+    fn written_file() {
+        let file = written(|pdf| {
+            pdf.add_page(Page::new());
+        });
+        assert_eq!(verify(&file), Ok(()));
+    }
+
+    #[test]
+    fn truncated_file() {
+        let file = written(|pdf| {
+            pdf.add_page(Page::new());
+        });
+        assert_eq!(verify(&file[..file.len() / 2]), Err(VerifyError::Truncated));
+    }
+
+    #[test]
+    fn wrong_object_header() {
+        let mut file = written(|pdf| {
+            pdf.add_page(Page::new());
+        });
+        let i = find(&file, b"2 0 obj").unwrap();
+        file[i + 2] = b'1';
+        assert_eq!(verify(&file), Err(VerifyError::Object { num: 2, gen: 0 }));
+    }
+}
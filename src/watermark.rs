@@ -0,0 +1,53 @@
+use crate::graphics::{Color, ExtGState, Font, Matrix, Text};
+use crate::Page;
+use std::rc::Rc;
+
+/// Text drawn across the middle of every page, see PDF::add_watermark
+#[derive(Debug)]
+pub(crate) struct Watermark {
+    text: String,
+    angle: f64,
+    font: Rc<Font>,
+    state: Rc<ExtGState>,
+}
+
+impl Watermark {
+    pub fn new(text: &str, angle: f64, alpha: f64) -> Self {
+        Self {
+            text: text.to_string(),
+            angle,
+            font: Font::helvetica_bold(),
+            state: Rc::new(ExtGState::new().fill_alpha(alpha).stroke_alpha(alpha)),
+        }
+    }
+    /// Draws the watermark over the content of the page
+    ///
+    /// The text spans half of the diagonal of the MediaBox, but is
+    /// never taller than half the shorter side.
+    pub fn draw(&self, page: &mut Page) {
+        let r = page.media_box();
+        let diagonal = r.width().hypot(r.height());
+        let unit = self.font.text_width(&self.text, 1f64);
+        let mut size = r.width().min(r.height()) / 2f64;
+        if unit > 0f64 {
+            size = size.min(diagonal / 2f64 / unit);
+        }
+        let width = self.font.text_width(&self.text, size);
+        // Place the center of the text, roughly at half the cap height,
+        // at the center of the page
+        let matrix = Matrix::translate(-width / 2f64, -size * 0.35)
+            .then(&Matrix::rotate(self.angle.to_radians()))
+            .then(&Matrix::translate(
+                r.x() + r.width() / 2f64,
+                r.y() + r.height() / 2f64,
+            ));
+        let text = Text::new(self.font.clone(), size)
+            .matrix(matrix)
+            .text(self.text.clone())
+            .fill(Color::DeviceGray(0.5));
+        page.graphics.save_state();
+        page.graphics.set_ext_gstate(self.state.clone());
+        page.graphics.render(Rc::new(text));
+        page.graphics.restore_state();
+    }
+}